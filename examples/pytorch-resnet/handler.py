@@ -1,21 +1,25 @@
-from ferrix import InferRequest, InferResponse, InferInput, InferOutput, preprocessor, postprocessor
+from ferrix import InferRequest, InferResponse, InferInput, InferOutput, State, preprocessor, postprocessor, setup
 from PIL import Image
 from torchvision import transforms
 
 import torch
 
 
-@preprocessor
-def test(infer_input: InferRequest) -> InferRequest:
-    image_url = infer_input.parameters["image"]
-    input_image = Image.open(image_url)
-    preprocess = transforms.Compose([
+@setup
+def load(config: dict, state: State):
+    state.preprocess = transforms.Compose([
         transforms.Resize(256),
         transforms.CenterCrop(224),
         transforms.ToTensor(),
         transforms.Normalize(mean=[0.485, 0.456, 0.406], std=[0.229, 0.224, 0.225]),
     ])
-    input_tensor = preprocess(input_image)
+
+
+@preprocessor
+def test(infer_input: InferRequest, state: State) -> InferRequest:
+    image_url = infer_input.parameters["image"]
+    input_image = Image.open(image_url)
+    input_tensor = state.preprocess(input_image)
     input_batch = input_tensor.unsqueeze(0)
 
     result_input = InferInput(
@@ -0,0 +1,105 @@
+use std::path::Path;
+
+use anyhow::bail;
+use ferrix_model_api::{platform, ArtifactInfo, Model, ModelConfig};
+use ferrix_model_pytorch::PyTorchModel;
+
+/// Model backends ferrix knows about. Only PyTorch is always compiled in, the
+/// others are behind cargo features of the same name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    PyTorch,
+    Onnx,
+    Candle,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 3] = [Backend::PyTorch, Backend::Onnx, Backend::Candle];
+
+    /// KServe platform name, as used in `ModelConfig::platform`. Backends
+    /// that aren't compiled in still need theirs, to name the missing feature.
+    pub fn platform(self) -> &'static str {
+        match self {
+            Backend::PyTorch => platform::PYTORCH,
+            Backend::Onnx => platform::ONNX,
+            Backend::Candle => platform::CANDLE,
+        }
+    }
+
+    pub fn feature(self) -> &'static str {
+        match self {
+            Backend::PyTorch => "pytorch",
+            Backend::Onnx => "onnx",
+            Backend::Candle => "candle",
+        }
+    }
+
+    pub fn compiled_in(self) -> bool {
+        match self {
+            Backend::PyTorch => true,
+            Backend::Onnx => cfg!(feature = "onnx"),
+            Backend::Candle => cfg!(feature = "candle"),
+        }
+    }
+
+    pub fn from_platform(platform: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|backend| backend.platform() == platform)
+    }
+
+    /// The configured platform, falling back to the artifact's extension.
+    pub fn for_config(config: &ModelConfig) -> anyhow::Result<Self> {
+        match &config.platform {
+            Some(platform) => match Self::from_platform(platform) {
+                Some(backend) => Ok(backend),
+                None => bail!("unknown platform {}", platform),
+            },
+            None => match Self::for_path(Path::new(&config.base_path)) {
+                Some(backend) => Ok(backend),
+                None => bail!(
+                    "no backend reads {}, set platform in the model entry",
+                    config.base_path
+                ),
+            },
+        }
+    }
+
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "pt" | "pth" | "torchscript" => Some(Backend::PyTorch),
+            "onnx" => Some(Backend::Onnx),
+            "safetensors" => Some(Backend::Candle),
+            _ => None,
+        }
+    }
+
+    pub fn inspect(self, path: &str) -> anyhow::Result<ArtifactInfo> {
+        if !self.compiled_in() {
+            bail!(
+                "ferrix was built without the {} backend, rebuild with --features {}",
+                self.feature(),
+                self.feature()
+            );
+        }
+
+        match self {
+            Backend::PyTorch => ferrix_model_pytorch::inspect(path),
+            #[cfg(feature = "onnx")]
+            Backend::Onnx => ferrix_model_onnx::inspect(path),
+            #[cfg(feature = "candle")]
+            Backend::Candle => ferrix_model_candle::inspect(path),
+            #[allow(unreachable_patterns)]
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn model(self, config: ModelConfig) -> anyhow::Result<Box<dyn Model>> {
+        match self {
+            Backend::PyTorch => Ok(Box::new(PyTorchModel::new(config))),
+            _ => bail!("the {} backend cannot serve models yet", self.feature()),
+        }
+    }
+}
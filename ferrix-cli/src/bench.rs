@@ -0,0 +1,458 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context};
+use clap::{Args, ValueEnum};
+use ferrix_client::{Client, InferRequest, Tensor};
+use hdrhistogram::Histogram;
+use rand::Rng;
+use serde_json::{json, Value};
+use tokio::task::JoinSet;
+
+use crate::connection::ConnectionArgs;
+use crate::infer::parse_input;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+
+    /// Model to benchmark
+    #[arg(short, long)]
+    model: String,
+
+    /// Model version, chosen by the server if not given
+    #[arg(long, default_value = "")]
+    model_version: String,
+
+    /// Requests kept in flight; a comma separated list runs each level in turn
+    #[arg(short, long, value_delimiter = ',', default_value = "1")]
+    concurrency: Vec<usize>,
+
+    /// Send requests at a fixed rate per second instead of a fixed
+    /// concurrency; a comma separated list runs each rate in turn
+    #[arg(short, long, value_delimiter = ',', conflicts_with = "concurrency")]
+    rate: Vec<f64>,
+
+    /// Seconds to measure each level for
+    #[arg(short, long, default_value_t = 10.0)]
+    duration: f64,
+
+    /// Seconds to send requests before measuring each level
+    #[arg(long, default_value_t = 2.0)]
+    warmup: f64,
+
+    /// Size used for variable (-1) dimensions of generated inputs
+    #[arg(long, default_value_t = 1)]
+    batch_size: usize,
+
+    /// Input as NAME[:DATATYPE]=VALUE, as for `infer`. Inputs not given are
+    /// generated from the model metadata
+    #[arg(short, long = "input")]
+    inputs: Vec<String>,
+
+    /// Report format
+    #[arg(short, long, value_enum, default_value_t = ReportFormat::Text)]
+    format: ReportFormat,
+
+    /// Write the report to a file instead of stdout
+    #[arg(long)]
+    report: Option<PathBuf>,
+}
+
+/// How requests are issued for one measurement.
+#[derive(Clone, Copy, Debug)]
+enum Load {
+    Concurrency(usize),
+    Rate(f64),
+}
+
+struct Measurement {
+    load: Load,
+    requests: u64,
+    errors: u64,
+    first_error: Option<String>,
+    elapsed: Duration,
+    latency: Histogram<u64>,
+}
+
+pub async fn run(args: BenchArgs) -> anyhow::Result<()> {
+    let client = Arc::new(args.connection.client()?);
+    let request = Arc::new(build_request(&client, &args).await?);
+    let loads: Vec<Load> = match args.rate.is_empty() {
+        true => args
+            .concurrency
+            .iter()
+            .map(|c| Load::Concurrency(*c))
+            .collect(),
+        false => args.rate.iter().map(|rate| Load::Rate(*rate)).collect(),
+    };
+    let mut measurements = Vec::with_capacity(loads.len());
+
+    for load in loads {
+        match load {
+            Load::Concurrency(0) => bail!("concurrency must be at least 1"),
+            Load::Rate(rate) if rate <= 0.0 => bail!("rate must be positive"),
+            _ => {}
+        }
+
+        if args.warmup > 0.0 {
+            measure(
+                &client,
+                &request,
+                load,
+                Duration::from_secs_f64(args.warmup),
+            )
+            .await;
+        }
+
+        let measurement = measure(
+            &client,
+            &request,
+            load,
+            Duration::from_secs_f64(args.duration),
+        )
+        .await;
+
+        if let Some(error) = &measurement.first_error {
+            eprintln!(
+                "{} of {} requests failed, first error: {}",
+                measurement.errors, measurement.requests, error
+            );
+        }
+
+        // Structured reports go to stdout at the end, so progress goes to stderr.
+        match args.format {
+            ReportFormat::Text => println!("{}\n", text_report(&measurement)),
+            _ => eprintln!("{}\n", text_report(&measurement)),
+        }
+
+        measurements.push(measurement);
+    }
+
+    let report = match args.format {
+        ReportFormat::Text => measurements
+            .iter()
+            .map(text_report)
+            .collect::<Vec<String>>()
+            .join("\n\n"),
+        ReportFormat::Json => serde_json::to_string_pretty(&Value::Array(
+            measurements.iter().map(json_report).collect(),
+        ))?,
+        ReportFormat::Csv => csv_report(&measurements),
+    };
+
+    match (&args.report, args.format) {
+        (Some(path), _) => std::fs::write(path, report + "\n")
+            .with_context(|| format!("failed to write {}", path.display()))?,
+        // Text results were already printed as each level finished.
+        (None, ReportFormat::Text) => {}
+        (None, _) => println!("{}", report),
+    }
+
+    Ok(())
+}
+
+/// Uses the given inputs and fills in the rest with random data shaped after
+/// the model metadata, so the same request is sent for the whole run.
+async fn build_request(client: &Client, args: &BenchArgs) -> anyhow::Result<InferRequest> {
+    let mut request = InferRequest::new(&args.model).version(&args.model_version);
+
+    for spec in &args.inputs {
+        let (name, tensor) =
+            parse_input(spec).with_context(|| format!("invalid input {}", spec))?;
+
+        request = request.input(name, tensor);
+    }
+
+    let metadata = client
+        .model_metadata(&args.model, &args.model_version)
+        .await
+        .context("failed to fetch model metadata")?;
+
+    for input in metadata.inputs {
+        if request.inputs.iter().any(|(name, _)| *name == input.name) {
+            continue;
+        }
+
+        let shape: Vec<usize> = input
+            .shape
+            .iter()
+            .map(|dim| match *dim {
+                -1 => args.batch_size,
+                dim => dim as usize,
+            })
+            .collect();
+        let tensor = synthetic_tensor(&input.datatype, &shape)
+            .with_context(|| format!("can't generate input {}", input.name))?;
+
+        request = request.input(input.name, tensor);
+    }
+
+    if request.inputs.is_empty() {
+        bail!(
+            "model {} declares no inputs, pass them with --input",
+            args.model
+        );
+    }
+
+    Ok(request)
+}
+
+fn synthetic_tensor(datatype: &str, shape: &[usize]) -> anyhow::Result<Tensor> {
+    let elements: usize = shape.iter().product();
+    let mut rng = rand::thread_rng();
+
+    macro_rules! random {
+        ($type:ty, $range:expr) => {
+            Tensor::new(
+                shape,
+                &(0..elements)
+                    .map(|_| rng.gen_range($range))
+                    .collect::<Vec<$type>>(),
+            )?
+        };
+    }
+
+    Ok(match datatype {
+        "BOOL" => Tensor::new(
+            shape,
+            &(0..elements).map(|_| rng.gen()).collect::<Vec<bool>>(),
+        )?,
+        "UINT8" => random!(u8, 0..=u8::MAX),
+        "UINT16" => random!(u16, 0..100),
+        "UINT32" => random!(u32, 0..100),
+        "UINT64" => random!(u64, 0..100),
+        "INT8" => random!(i8, 0..100),
+        "INT16" => random!(i16, 0..100),
+        "INT32" => random!(i32, 0..100),
+        "INT64" => random!(i64, 0..100),
+        "FP32" => random!(f32, 0.0..1.0),
+        "FP64" => random!(f64, 0.0..1.0),
+        "BYTES" => {
+            let values: Vec<String> = (0..elements)
+                .map(|_| {
+                    (0..16)
+                        .map(|_| rng.sample(rand::distributions::Alphanumeric) as char)
+                        .collect()
+                })
+                .collect();
+
+            Tensor::from_bytes(shape, &values)?
+        }
+        other => return Err(anyhow!("unsupported datatype {}", other)),
+    })
+}
+
+async fn measure(
+    client: &Arc<Client>,
+    request: &Arc<InferRequest>,
+    load: Load,
+    duration: Duration,
+) -> Measurement {
+    let started = Instant::now();
+    let deadline = started + duration;
+    let mut tasks = JoinSet::new();
+
+    match load {
+        Load::Concurrency(concurrency) => {
+            for _ in 0..concurrency {
+                let client = client.clone();
+                let request = request.clone();
+
+                tasks.spawn(async move {
+                    let mut results = Vec::new();
+
+                    while Instant::now() < deadline {
+                        results.push(timed(&client, &request).await);
+                    }
+
+                    results
+                });
+            }
+        }
+        Load::Rate(rate) => {
+            let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / rate));
+
+            while Instant::now() < deadline {
+                interval.tick().await;
+
+                let client = client.clone();
+                let request = request.clone();
+
+                tasks.spawn(async move { vec![timed(&client, &request).await] });
+            }
+        }
+    }
+
+    let mut measurement = Measurement {
+        load,
+        requests: 0,
+        errors: 0,
+        first_error: None,
+        elapsed: Duration::ZERO,
+        latency: Histogram::new(3).unwrap(),
+    };
+
+    while let Some(results) = tasks.join_next().await {
+        for result in results.unwrap_or_default() {
+            measurement.requests += 1;
+
+            match result {
+                Ok(latency) => {
+                    let _ = measurement.latency.record(latency.as_micros() as u64);
+                }
+                Err(error) => {
+                    measurement.errors += 1;
+                    measurement.first_error.get_or_insert(error);
+                }
+            }
+        }
+    }
+
+    measurement.elapsed = started.elapsed();
+    measurement
+}
+
+async fn timed(client: &Client, request: &InferRequest) -> Result<Duration, String> {
+    let started = Instant::now();
+
+    client
+        .infer_request(request)
+        .await
+        .map(|_| started.elapsed())
+        .map_err(|error| error.to_string())
+}
+
+impl Measurement {
+    fn throughput(&self) -> f64 {
+        (self.requests - self.errors) as f64 / self.elapsed.as_secs_f64()
+    }
+
+    fn percentile_ms(&self, percentile: f64) -> f64 {
+        self.latency.value_at_percentile(percentile) as f64 / 1000.0
+    }
+
+    fn load_description(&self) -> String {
+        match self.load {
+            Load::Concurrency(concurrency) => format!("concurrency {}", concurrency),
+            Load::Rate(rate) => format!("rate {}/s", rate),
+        }
+    }
+}
+
+fn text_report(measurement: &Measurement) -> String {
+    format!(
+        "{}\n  requests    {} ({} errors) in {:.2}s\n  throughput  {:.2} infer/s\n  latency     p50 {:.3}ms  p90 {:.3}ms  p99 {:.3}ms  max {:.3}ms  mean {:.3}ms",
+        measurement.load_description(),
+        measurement.requests,
+        measurement.errors,
+        measurement.elapsed.as_secs_f64(),
+        measurement.throughput(),
+        measurement.percentile_ms(50.0),
+        measurement.percentile_ms(90.0),
+        measurement.percentile_ms(99.0),
+        measurement.latency.max() as f64 / 1000.0,
+        measurement.latency.mean() / 1000.0,
+    )
+}
+
+fn json_report(measurement: &Measurement) -> Value {
+    let (concurrency, rate) = match measurement.load {
+        Load::Concurrency(concurrency) => (Some(concurrency), None),
+        Load::Rate(rate) => (None, Some(rate)),
+    };
+
+    json!({
+        "concurrency": concurrency,
+        "rate": rate,
+        "requests": measurement.requests,
+        "errors": measurement.errors,
+        "duration_s": measurement.elapsed.as_secs_f64(),
+        "throughput": measurement.throughput(),
+        "latency_ms": {
+            "p50": measurement.percentile_ms(50.0),
+            "p90": measurement.percentile_ms(90.0),
+            "p99": measurement.percentile_ms(99.0),
+            "max": measurement.latency.max() as f64 / 1000.0,
+            "mean": measurement.latency.mean() / 1000.0,
+        },
+    })
+}
+
+fn csv_report(measurements: &[Measurement]) -> String {
+    let mut lines = vec![
+        "concurrency,rate,requests,errors,duration_s,throughput,p50_ms,p90_ms,p99_ms,max_ms,mean_ms"
+            .to_string(),
+    ];
+
+    for measurement in measurements {
+        let (concurrency, rate) = match measurement.load {
+            Load::Concurrency(concurrency) => (concurrency.to_string(), String::new()),
+            Load::Rate(rate) => (String::new(), rate.to_string()),
+        };
+
+        lines.push(format!(
+            "{},{},{},{},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}",
+            concurrency,
+            rate,
+            measurement.requests,
+            measurement.errors,
+            measurement.elapsed.as_secs_f64(),
+            measurement.throughput(),
+            measurement.percentile_ms(50.0),
+            measurement.percentile_ms(90.0),
+            measurement.percentile_ms(99.0),
+            measurement.latency.max() as f64 / 1000.0,
+            measurement.latency.mean() / 1000.0,
+        ));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthetic_tensor_matches_shape() {
+        let tensor = synthetic_tensor("FP32", &[2, 3]).unwrap();
+
+        assert_eq!(&[2, 3], tensor.shape());
+        assert_eq!(6, tensor.to_vec::<f32>().unwrap().len());
+    }
+
+    #[test]
+    fn test_synthetic_bytes_tensor() {
+        let tensor = synthetic_tensor("BYTES", &[4]).unwrap();
+
+        assert_eq!(4, tensor.to_bytes().unwrap().len());
+    }
+
+    #[test]
+    fn test_csv_report() {
+        let mut latency = Histogram::new(3).unwrap();
+        latency.record(2000).unwrap();
+
+        let report = csv_report(&[Measurement {
+            load: Load::Concurrency(4),
+            requests: 10,
+            errors: 0,
+            first_error: None,
+            elapsed: Duration::from_secs(1),
+            latency,
+        }]);
+        let row = report.lines().nth(1).unwrap();
+
+        assert!(row.starts_with("4,,10,0,1.000,10.000,2.000"));
+    }
+}
@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Args, ValueEnum};
+use ferrix_client::{Client, Protocol, TlsConfig};
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ProtocolArg {
+    Grpc,
+    Http,
+}
+
+#[derive(Args, Debug)]
+pub struct ConnectionArgs {
+    /// Server address as host:port or a URL; use https:// for TLS
+    #[arg(short, long, default_value = "localhost:6565")]
+    pub server: String,
+
+    /// Protocol used to reach the server
+    #[arg(long, value_enum, default_value_t = ProtocolArg::Grpc)]
+    pub protocol: ProtocolArg,
+
+    /// Per-request timeout in seconds
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+
+    /// PEM certificate of the CA that signed the server certificate
+    #[arg(long)]
+    pub ca_cert: Option<PathBuf>,
+
+    /// PEM client certificate, for servers that require mutual TLS
+    #[arg(long, requires = "client_key")]
+    pub client_cert: Option<PathBuf>,
+
+    /// PEM private key of the client certificate
+    #[arg(long, requires = "client_cert")]
+    pub client_key: Option<PathBuf>,
+
+    /// Token sent as `Authorization: Bearer <token>`
+    #[arg(long, conflicts_with = "api_key")]
+    pub token: Option<String>,
+
+    /// Key sent in the `x-api-key` header
+    #[arg(long)]
+    pub api_key: Option<String>,
+}
+
+impl ConnectionArgs {
+    pub fn client(&self) -> anyhow::Result<Client> {
+        let endpoint = match self.server.contains("://") {
+            true => self.server.clone(),
+            false => format!("http://{}", self.server),
+        };
+        let protocol = match self.protocol {
+            ProtocolArg::Grpc => Protocol::Grpc,
+            ProtocolArg::Http => Protocol::Http,
+        };
+
+        let mut builder = Client::builder(endpoint)
+            .protocol(protocol)
+            .timeout(Duration::from_secs(self.timeout));
+
+        if self.ca_cert.is_some() || self.client_cert.is_some() {
+            builder = builder.tls(TlsConfig {
+                ca_certificate: self.ca_cert.clone(),
+                certificate: self.client_cert.clone(),
+                key: self.client_key.clone(),
+                domain: None,
+            });
+        }
+
+        if let Some(token) = &self.token {
+            builder = builder.bearer_token(token);
+        }
+
+        if let Some(key) = &self.api_key {
+            builder = builder.api_key(key);
+        }
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,395 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context};
+use clap::Args;
+use ferrix_client::{Element, InferRequest, InferResult, Parameter, Tensor};
+use ndarray::{ArrayD, IxDyn, ShapeBuilder};
+use npyz::{DType, NpyFile, Order, TypeChar};
+use serde_json::{json, Value};
+
+use crate::connection::ConnectionArgs;
+
+#[derive(Args, Debug)]
+pub struct InferArgs {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+
+    /// Model to run
+    #[arg(short, long)]
+    model: String,
+
+    /// Model version or version label, chosen by the server if not given
+    #[arg(long, default_value = "")]
+    model_version: String,
+
+    /// Input as NAME[:DATATYPE]=VALUE, where VALUE is a JSON array or @FILE.
+    /// Files ending in .npy or .json are decoded, anything else is sent as a
+    /// single BYTES element
+    #[arg(short, long = "input", required = true)]
+    inputs: Vec<String>,
+
+    /// Request parameter as KEY=VALUE. true/false and integers are sent as
+    /// such, anything else as a string
+    #[arg(long = "parameter")]
+    parameters: Vec<String>,
+
+    /// Only return these outputs
+    #[arg(short, long = "output")]
+    outputs: Vec<String>,
+
+    /// Print the K largest values of each output as a table instead of JSON
+    #[arg(long)]
+    top_k: Option<usize>,
+
+    /// File with one label per line, used to name top-k indices
+    #[arg(long)]
+    labels: Option<PathBuf>,
+}
+
+pub async fn run(args: InferArgs) -> anyhow::Result<()> {
+    let client = args.connection.client()?;
+    let mut request = InferRequest::new(&args.model).version(&args.model_version);
+
+    for spec in &args.inputs {
+        let (name, tensor) =
+            parse_input(spec).with_context(|| format!("invalid input {}", spec))?;
+
+        request = request.input(name, tensor);
+    }
+
+    for spec in &args.parameters {
+        let (name, value) = spec
+            .split_once('=')
+            .ok_or_else(|| anyhow!("invalid parameter {}, expected KEY=VALUE", spec))?;
+
+        request = request.parameter(name, parse_parameter(value));
+    }
+
+    for output in &args.outputs {
+        request = request.output(output);
+    }
+
+    let result = client.infer_request(&request).await?;
+
+    match args.top_k {
+        Some(k) => {
+            let labels = match &args.labels {
+                Some(path) => std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read labels {}", path.display()))?
+                    .lines()
+                    .map(str::to_string)
+                    .collect(),
+                None => vec![],
+            };
+
+            print_top_k(&result, k, &labels)
+        }
+        None => print_json(&result),
+    }
+}
+
+pub(crate) fn parse_input(spec: &str) -> anyhow::Result<(String, Tensor)> {
+    let (name, value) = spec
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected NAME[:DATATYPE]=VALUE"))?;
+    let (name, datatype) = match name.split_once(':') {
+        Some((name, datatype)) => (name, Some(datatype.to_uppercase())),
+        None => (name, None),
+    };
+
+    let tensor = match value.strip_prefix('@') {
+        Some(path) => read_file(Path::new(path), datatype)?,
+        None => json_tensor(serde_json::from_str(value)?, datatype)?,
+    };
+
+    Ok((name.to_string(), tensor))
+}
+
+fn parse_parameter(value: &str) -> Parameter {
+    if let Ok(value) = value.parse::<bool>() {
+        Parameter::Bool(value)
+    } else if let Ok(value) = value.parse::<i64>() {
+        Parameter::Int64(value)
+    } else {
+        Parameter::String(value.to_string())
+    }
+}
+
+fn read_file(path: &Path, datatype: Option<String>) -> anyhow::Result<Tensor> {
+    let extension = path.extension().and_then(|extension| extension.to_str());
+
+    match extension {
+        Some("npy") => read_npy(path),
+        Some("json") => {
+            let file =
+                File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+
+            json_tensor(serde_json::from_reader(BufReader::new(file))?, datatype)
+        }
+        _ => {
+            let bytes = std::fs::read(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+
+            Ok(Tensor::from_bytes(&[1], &[bytes])?)
+        }
+    }
+}
+
+/// Accepts either a KServe tensor object with `datatype`, `shape` and `data`,
+/// or bare (possibly nested) data whose shape and datatype are inferred.
+fn json_tensor(value: Value, datatype: Option<String>) -> anyhow::Result<Tensor> {
+    if let Value::Object(mut object) = value {
+        let data = object
+            .remove("data")
+            .ok_or_else(|| anyhow!("tensor object has no data"))?;
+        let shape = match object.remove("shape") {
+            Some(shape) => serde_json::from_value(shape)?,
+            None => json_shape(&data)?,
+        };
+        let datatype = match (datatype, object.remove("datatype")) {
+            (Some(datatype), _) => datatype,
+            (None, Some(Value::String(datatype))) => datatype,
+            (None, _) => infer_datatype(&data)?,
+        };
+
+        return Ok(Tensor::from_json(datatype, shape, data)?);
+    }
+
+    let value = match value {
+        Value::Array(_) => value,
+        scalar => Value::Array(vec![scalar]),
+    };
+    let datatype = match datatype {
+        Some(datatype) => datatype,
+        None => infer_datatype(&value)?,
+    };
+
+    Ok(Tensor::from_json(datatype, json_shape(&value)?, value)?)
+}
+
+fn json_shape(value: &Value) -> anyhow::Result<Vec<i64>> {
+    match value {
+        Value::Array(items) => {
+            let inner = match items.first() {
+                Some(first) => json_shape(first)?,
+                None => vec![],
+            };
+
+            for item in items.iter().skip(1) {
+                if json_shape(item)? != inner {
+                    bail!("nested arrays must all have the same length");
+                }
+            }
+
+            Ok([vec![items.len() as i64], inner].concat())
+        }
+        _ => Ok(vec![]),
+    }
+}
+
+fn infer_datatype(value: &Value) -> anyhow::Result<String> {
+    fn leaves<'a>(value: &'a Value, found: &mut Vec<&'a Value>) {
+        match value {
+            Value::Array(items) => items.iter().for_each(|item| leaves(item, found)),
+            value => found.push(value),
+        }
+    }
+
+    let mut found = Vec::new();
+    leaves(value, &mut found);
+
+    let datatype = if found.iter().all(|value| value.is_boolean()) {
+        "BOOL"
+    } else if found.iter().all(|value| value.is_string()) {
+        "BYTES"
+    } else if found.iter().all(|value| value.is_i64()) {
+        "INT64"
+    } else if found.iter().all(|value| value.is_number()) {
+        "FP32"
+    } else {
+        bail!("can't infer a datatype for mixed values, use NAME:DATATYPE=VALUE");
+    };
+
+    Ok(datatype.to_string())
+}
+
+fn read_npy(path: &Path) -> anyhow::Result<Tensor> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let npy = NpyFile::new(BufReader::new(file))?;
+    let DType::Plain(type_str) = npy.dtype() else {
+        bail!("{} has a structured dtype", path.display());
+    };
+
+    match (type_str.type_char(), type_str.size_field()) {
+        (TypeChar::Bool, 1) => npy_tensor::<bool, _>(npy),
+        (TypeChar::Uint, 1) => npy_tensor::<u8, _>(npy),
+        (TypeChar::Uint, 2) => npy_tensor::<u16, _>(npy),
+        (TypeChar::Uint, 4) => npy_tensor::<u32, _>(npy),
+        (TypeChar::Uint, 8) => npy_tensor::<u64, _>(npy),
+        (TypeChar::Int, 1) => npy_tensor::<i8, _>(npy),
+        (TypeChar::Int, 2) => npy_tensor::<i16, _>(npy),
+        (TypeChar::Int, 4) => npy_tensor::<i32, _>(npy),
+        (TypeChar::Int, 8) => npy_tensor::<i64, _>(npy),
+        (TypeChar::Float, 4) => npy_tensor::<f32, _>(npy),
+        (TypeChar::Float, 8) => npy_tensor::<f64, _>(npy),
+        _ => bail!("{} has unsupported dtype {}", path.display(), type_str),
+    }
+}
+
+fn npy_tensor<T, R>(npy: NpyFile<R>) -> anyhow::Result<Tensor>
+where
+    T: Element + npyz::Deserialize,
+    R: std::io::Read,
+{
+    let shape: Vec<usize> = npy.shape().iter().map(|dim| *dim as usize).collect();
+    let fortran = matches!(npy.order(), Order::Fortran);
+    let values = npy.into_vec::<T>()?;
+    let array = match fortran {
+        true => ArrayD::from_shape_vec(IxDyn(&shape).f(), values)?,
+        false => ArrayD::from_shape_vec(IxDyn(&shape), values)?,
+    };
+
+    Ok(Tensor::from_array(&array))
+}
+
+fn print_json(result: &InferResult) -> anyhow::Result<()> {
+    let outputs = result
+        .outputs
+        .iter()
+        .map(|(name, tensor)| {
+            Ok(json!({
+                "name": name,
+                "datatype": tensor.datatype(),
+                "shape": tensor.shape(),
+                "data": tensor.to_json()?,
+            }))
+        })
+        .collect::<anyhow::Result<Vec<Value>>>()?;
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json!({
+            "model_name": result.model_name,
+            "model_version": result.model_version,
+            "id": result.id,
+            "outputs": outputs,
+        }))?
+    );
+
+    Ok(())
+}
+
+fn print_top_k(result: &InferResult, k: usize, labels: &[String]) -> anyhow::Result<()> {
+    for (name, tensor) in &result.outputs {
+        let mut values: Vec<(usize, f64)> = match tensor.to_json()? {
+            Value::Array(values) => values
+                .iter()
+                .enumerate()
+                .filter_map(|(index, value)| Some((index, value.as_f64()?)))
+                .collect(),
+            _ => vec![],
+        };
+
+        if values.is_empty() {
+            println!(
+                "{}: {} output has no numeric values",
+                name,
+                tensor.datatype()
+            );
+            continue;
+        }
+
+        values.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        println!("{} ({} {:?})", name, tensor.datatype(), tensor.shape());
+        println!(
+            "{:>6}  {:>8}  {:<24}  {:>12}",
+            "rank", "index", "label", "value"
+        );
+
+        for (rank, (index, value)) in values.iter().take(k).enumerate() {
+            let label = labels.get(*index).map(String::as_str).unwrap_or("");
+
+            println!(
+                "{:>6}  {:>8}  {:<24}  {:>12.6}",
+                rank + 1,
+                index,
+                label,
+                value
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use npyz::WriterBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_nested_json_input() {
+        let (name, tensor) = parse_input("x=[[1, 2, 3], [4, 5, 6]]").unwrap();
+
+        assert_eq!("x", name);
+        assert_eq!("INT64", tensor.datatype());
+        assert_eq!(&[2, 3], tensor.shape());
+    }
+
+    #[test]
+    fn test_parse_input_with_datatype() {
+        let (_, tensor) = parse_input("x:fp64=[1, 2.5]").unwrap();
+
+        assert_eq!(vec![1.0, 2.5], tensor.to_vec::<f64>().unwrap());
+    }
+
+    #[test]
+    fn test_parse_tensor_object() {
+        let (_, tensor) =
+            parse_input(r#"x={"datatype": "UINT8", "shape": [2, 1], "data": [7, 8]}"#).unwrap();
+
+        assert_eq!(&[2, 1], tensor.shape());
+        assert_eq!(vec![7u8, 8], tensor.to_vec::<u8>().unwrap());
+    }
+
+    #[test]
+    fn test_parse_parameter() {
+        assert_eq!(Parameter::Bool(true), parse_parameter("true"));
+        assert_eq!(Parameter::Int64(-3), parse_parameter("-3"));
+        assert_eq!(
+            Parameter::String("cat.jpeg".to_string()),
+            parse_parameter("cat.jpeg")
+        );
+    }
+
+    #[test]
+    fn test_rejects_ragged_input() {
+        assert!(parse_input("x=[[1, 2], [3]]").is_err());
+    }
+
+    #[test]
+    fn test_reads_npy_file() {
+        let path = std::env::temp_dir().join("ferrix-cli-test.npy");
+        let mut bytes = Vec::new();
+        let mut writer = npyz::WriteOptions::new()
+            .default_dtype()
+            .shape(&[2, 2])
+            .writer(&mut bytes)
+            .begin_nd()
+            .unwrap();
+
+        writer.extend([1.0f32, 2.0, 3.0, 4.0]).unwrap();
+        writer.finish().unwrap();
+        std::fs::write(&path, bytes).unwrap();
+
+        let (_, tensor) = parse_input(&format!("x=@{}", path.display())).unwrap();
+
+        assert_eq!("FP32", tensor.datatype());
+        assert_eq!(&[2, 2], tensor.shape());
+        assert_eq!(vec![1.0f32, 2.0, 3.0, 4.0], tensor.to_vec::<f32>().unwrap());
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use clap::Args;
+use ferrix_model_api::{ArtifactInfo, ModelConfig, TensorSpec};
+
+use crate::backend::Backend;
+
+#[derive(Args, Debug)]
+pub struct InspectArgs {
+    /// Model artifact: TorchScript (.pt, .pth), ONNX (.onnx) or safetensors
+    path: PathBuf,
+
+    /// Print a starter ferrix.toml entry instead of a summary
+    #[arg(long)]
+    config: bool,
+
+    /// Model name for the generated entry, defaults to the file name
+    #[arg(long)]
+    model_name: Option<String>,
+}
+
+pub fn run(args: InspectArgs) -> anyhow::Result<()> {
+    let path = args
+        .path
+        .to_str()
+        .ok_or_else(|| anyhow!("{} is not valid UTF-8", args.path.display()))?;
+    let backend = Backend::for_path(&args.path)
+        .ok_or_else(|| anyhow!("no backend reads {}", args.path.display()))?;
+    let info = backend
+        .inspect(path)
+        .with_context(|| format!("failed to inspect {}", path))?;
+
+    match args.config {
+        true => println!("{}", starter_config(&args, info)?),
+        false => print_summary(path, &info),
+    }
+
+    Ok(())
+}
+
+fn starter_config(args: &InspectArgs, info: ArtifactInfo) -> anyhow::Result<String> {
+    let model_name = match &args.model_name {
+        Some(model_name) => model_name.clone(),
+        None => args
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    };
+    let base_path = args
+        .path
+        .canonicalize()
+        .unwrap_or_else(|_| args.path.clone());
+    let config = ModelConfig {
+        model_name,
+        base_path: base_path.display().to_string(),
+        sha256: None,
+        platform: Some(info.platform),
+        inputs: info.inputs,
+        outputs: info.outputs,
+        extended_config: None,
+        python: None,
+    };
+
+    Ok(toml::to_string(&config)?)
+}
+
+fn print_summary(path: &str, info: &ArtifactInfo) {
+    println!("{}", path);
+    println!("  platform    {}", info.platform);
+
+    if let Some(count) = info.parameter_count {
+        println!("  parameters  {}", thousands(count));
+    }
+
+    print_tensors("inputs", &info.inputs);
+    print_tensors("outputs", &info.outputs);
+
+    if !info.metadata.is_empty() {
+        let width = info.metadata.keys().map(String::len).max().unwrap_or(0);
+
+        println!("metadata");
+
+        for (key, value) in &info.metadata {
+            println!("  {:<width$}  {}", key, value, width = width);
+        }
+    }
+}
+
+fn print_tensors(title: &str, tensors: &[TensorSpec]) {
+    if tensors.is_empty() {
+        println!("{}: not recorded in this artifact", title);
+        return;
+    }
+
+    let width = tensors
+        .iter()
+        .map(|tensor| tensor.name.len())
+        .max()
+        .unwrap_or(0);
+
+    println!("{}", title);
+
+    for tensor in tensors {
+        println!(
+            "  {:<width$}  {:<6}  {:?}",
+            tensor.name,
+            tensor.datatype,
+            tensor.shape,
+            width = width
+        );
+    }
+}
+
+fn thousands(value: u64) -> String {
+    value
+        .to_string()
+        .as_bytes()
+        .rchunks(3)
+        .rev()
+        .map(|group| std::str::from_utf8(group).unwrap())
+        .collect::<Vec<&str>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thousands() {
+        assert_eq!("0", thousands(0));
+        assert_eq!("999", thousands(999));
+        assert_eq!("11,689,512", thousands(11_689_512));
+    }
+
+    #[test]
+    fn test_starter_config_round_trips() {
+        let args = InspectArgs {
+            path: PathBuf::from("/models/resnet.onnx"),
+            config: true,
+            model_name: None,
+        };
+        let info = ArtifactInfo {
+            platform: "onnxruntime_onnx".to_string(),
+            inputs: vec![TensorSpec {
+                name: "input".to_string(),
+                datatype: "FP32".to_string(),
+                shape: vec![-1, 3, 224, 224],
+            }],
+            ..Default::default()
+        };
+        let config: ModelConfig = toml::from_str(&starter_config(&args, info).unwrap()).unwrap();
+
+        assert_eq!("resnet", config.model_name);
+        assert_eq!("/models/resnet.onnx", config.base_path);
+        assert_eq!(Some("onnxruntime_onnx".to_string()), config.platform);
+        assert_eq!(vec![-1, 3, 224, 224], config.inputs[0].shape);
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::Context;
+use clap::{Args, ValueEnum};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{self, Tracer};
+use opentelemetry_sdk::{runtime, Resource};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+const DEFAULT_FILTER: &str = "info";
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    /// One JSON object per line, including the fields of enclosing spans
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub struct LogArgs {
+    /// Format of log lines written to stderr
+    #[arg(long, value_enum, global = true, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Which logs to write, e.g. "warn" or "info,ferrix_server=debug".
+    /// Defaults to RUST_LOG, or "info" if that isn't set either
+    #[arg(long, global = true)]
+    log_filter: Option<String>,
+
+    /// OTLP gRPC collector to export traces to, e.g. http://localhost:4317
+    #[arg(long, global = true)]
+    otlp_endpoint: Option<String>,
+
+    /// service.name reported with exported traces
+    #[arg(long, global = true, default_value = "ferrix")]
+    otlp_service_name: String,
+}
+
+pub fn init(args: &LogArgs) -> anyhow::Result<()> {
+    let filter = match &args.log_filter {
+        Some(filter) => {
+            EnvFilter::try_new(filter).with_context(|| format!("invalid log filter {}", filter))?
+        }
+        None => {
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER))
+        }
+    };
+    let fmt = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+    let fmt = match args.log_format {
+        LogFormat::Text => fmt.boxed(),
+        LogFormat::Json => fmt
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    };
+    let otlp = match &args.otlp_endpoint {
+        Some(endpoint) => Some(tracer(endpoint, &args.otlp_service_name)?),
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(fmt)
+        .with(otlp.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)))
+        .with(filter)
+        .init();
+
+    Ok(())
+}
+
+/// Exports spans through a batching exporter in the background.
+fn tracer(endpoint: &str, service_name: &str) -> anyhow::Result<Tracer> {
+    let exporter = opentelemetry_otlp::new_exporter()
+        .tonic()
+        .with_endpoint(endpoint);
+    let resource = Resource::new([KeyValue::new("service.name", service_name.to_string())]);
+
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(trace::config().with_resource(resource))
+        .install_batch(runtime::Tokio)
+        .with_context(|| format!("failed to set up trace export to {}", endpoint))
+}
+
+/// Flushes spans that haven't been exported yet.
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
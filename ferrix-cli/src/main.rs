@@ -1,49 +1,398 @@
-use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use clap::{Args, Parser, Subcommand};
+use tracing::{info, warn};
 
 use ferrix_model_api::ModelConfig;
-use ferrix_model_pytorch::*;
-use ferrix_server::inference::{Inference, InferenceConfig};
-use ferrix_server::GrpcInferenceServiceImpl;
+use ferrix_server::auth::{Auth, AuthConfig};
+use ferrix_server::directory::ModelDirectory;
+use ferrix_server::listen::ListenAddr;
+use ferrix_server::metrics::Metrics;
+use ferrix_server::repository::{Repository, RepositoryConfig};
+use ferrix_server::storage::{Storage, StorageConfig};
+use ferrix_server::tls::TlsConfig;
+use ferrix_server::{GrpcInferenceServiceImpl, ServeConfig};
+
+use crate::backend::Backend;
+use crate::bench::BenchArgs;
+use crate::infer::InferArgs;
+use crate::inspect::InspectArgs;
+use crate::logging::LogArgs;
+use crate::validate::ValidateArgs;
+
+mod backend;
+mod bench;
+mod connection;
+mod infer;
+mod inspect;
+mod logging;
+mod validate;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    serve: Config,
+
+    #[command(flatten)]
+    log: LogArgs,
+}
+
+// Parsed once at startup, so the size of Serve doesn't matter.
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the inference server (the default)
+    Serve(Config),
+    /// Send an inference request to a running server
+    Infer(InferArgs),
+    /// Measure throughput and latency of a running server
+    Bench(BenchArgs),
+    /// Describe a model artifact and optionally print a starter config entry
+    Inspect(InspectArgs),
+    /// Check a model config, its artifacts and handlers before deploying
+    Validate(ValidateArgs),
+}
+
+#[derive(Args, Debug)]
 struct Config {
-    /// gRPC service port
-    #[arg(short, long, default_value_t = 6565_i16)]
-    port: i16,
+    /// gRPC service port, on every IPv4 and IPv6 interface
+    #[arg(short, long, default_value_t = 6565)]
+    port: u16,
+
+    /// gRPC listen address (HOST:PORT, PORT or unix:PATH), replaces --port.
+    /// Repeat to listen on several
+    #[arg(short, long)]
+    listen: Vec<ListenAddr>,
 
-    /// Path to Ferrix model configuration
+    /// HTTP port for the KServe REST health, metadata and repository endpoints
+    #[arg(long)]
+    http_port: Option<u16>,
+
+    /// HTTP listen address (HOST:PORT, PORT or unix:PATH), replaces
+    /// --http-port. Repeat to listen on several
+    #[arg(long)]
+    http_listen: Vec<ListenAddr>,
+
+    /// HTTP port for Prometheus metrics on /metrics
+    #[arg(long)]
+    metrics_port: Option<u16>,
+
+    /// Metrics listen address (HOST:PORT, PORT or unix:PATH), replaces
+    /// --metrics-port. Repeat to listen on several
+    #[arg(long)]
+    metrics_listen: Vec<ListenAddr>,
+
+    /// PEM certificate chain served on TCP listeners, enables TLS
+    #[arg(long, requires = "tls_key")]
+    tls_certificate: Option<PathBuf>,
+
+    /// PEM private key for --tls-certificate
+    #[arg(long, requires = "tls_certificate")]
+    tls_key: Option<PathBuf>,
+
+    /// PEM CA certificates that client certificates must be signed by,
+    /// enables mutual TLS
+    #[arg(long, requires = "tls_certificate")]
+    tls_client_ca: Option<PathBuf>,
+
+    /// TOML file with the tokens, JWKS, admins and per-model allow lists
+    /// callers are checked against. Without it the server is open to anyone
+    /// who can reach it
+    #[arg(long)]
+    auth_config: Option<PathBuf>,
+
+    /// Path to Ferrix model configuration, holding one model or a [[models]]
+    /// array. The models are loaded at startup
     #[arg(short, long, default_value = "./ferrix.toml")]
     model_config: String,
 
+    /// Directory with a subdirectory per model and a numbered subdirectory
+    /// per version, each model optionally configured by a config.toml. The
+    /// versions picked by each model's version_policy are loaded at startup
+    #[arg(long)]
+    model_repository: Option<PathBuf>,
+
+    /// Seconds between scans of --model-repository for added, removed and
+    /// changed versions. Without it the directory is only read at startup
+    #[arg(long, requires = "model_repository")]
+    repository_poll_interval: Option<f64>,
+
     /// Path to Ferrix transformer Python file
-    #[arg(short, long, default_value = "/workspaces/ferrix/examples/pytorch-resnet/handler.py")]
+    #[arg(
+        short,
+        long,
+        default_value = "/workspaces/ferrix/examples/pytorch-resnet/handler.py"
+    )]
     transformer: String,
+
+    /// Include Python exception messages in error statuses returned to clients
+    #[arg(long, default_value_t = false)]
+    expose_hook_errors: bool,
+
+    /// Reload the transformer Python file of every loaded model when it
+    /// changes on disk
+    #[arg(long, default_value_t = false)]
+    reload_handler: bool,
+
+    /// Seconds to keep serving after readiness turns false on SIGTERM or
+    /// SIGINT, so load balancers stop routing here first
+    #[arg(long, default_value = "0", value_parser = seconds)]
+    shutdown_delay: Duration,
+
+    /// Seconds in-flight requests get to finish on shutdown. Ferrix exits
+    /// with an error if any are still running after it
+    #[arg(long, default_value = "30", value_parser = seconds)]
+    drain_timeout: Duration,
+
+    /// Model that must be loaded for the server to report ready. Repeat for
+    /// several. Defaults to every model in --model-config
+    #[arg(long)]
+    required_model: Vec<String>,
+
+    /// Seconds an inference request may run before the server reports itself
+    /// as not live, so a stuck model gets the process restarted. 0 disables
+    /// the check
+    #[arg(long, default_value = "300", value_parser = seconds)]
+    stuck_inference_timeout: Duration,
+
+    #[command(flatten)]
+    python: PythonArgs,
+
+    #[command(flatten)]
+    storage: StorageArgs,
+}
+
+#[derive(Args, Debug)]
+struct PythonArgs {
+    /// Virtualenv providing the transformer's Python dependencies
+    #[arg(long)]
+    python_venv: Option<String>,
+
+    /// Extra entries to prepend to the transformer's sys.path
+    #[arg(long)]
+    python_path: Vec<String>,
+
+    /// PYTHONHOME for the embedded interpreter
+    #[arg(long)]
+    python_home: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct StorageArgs {
+    /// Directory s3:// model artifacts are downloaded to, defaults to a
+    /// directory under the system temp dir. The endpoint, region and
+    /// credentials come from the usual AWS_* environment variables
+    #[arg(long)]
+    artifact_cache: Option<PathBuf>,
+}
+
+impl StorageArgs {
+    fn storage(&self) -> Storage {
+        let mut config = StorageConfig::default();
+
+        if let Some(cache_dir) = &self.artifact_cache {
+            config.cache_dir = cache_dir.clone();
+        }
+
+        Storage::new(config)
+    }
 }
 
 #[tokio::main]
 async fn main() {
-    let config = Config::parse();
-    let model_config = toml::from_str::<ModelConfig>(
-        r#"
-        base_path = "/workspaces/ferrix/ferrix-model-pytorch/resource/model.pt"
-        model_name = "Blah"
-    "#,
-    )
-    .unwrap();
+    let cli = Cli::parse();
+
+    exit_on_error(logging::init(&cli.log));
+
+    let result = match cli.command.unwrap_or(Command::Serve(cli.serve)) {
+        Command::Serve(config) => serve(config).await,
+        Command::Infer(args) => infer::run(args).await,
+        Command::Bench(args) => bench::run(args).await,
+        Command::Inspect(args) => inspect::run(args),
+        Command::Validate(args) => validate::run(args).await,
+    };
+
+    logging::shutdown();
+    exit_on_error(result);
+}
+
+fn exit_on_error(result: anyhow::Result<()>) {
+    if let Err(err) = result {
+        eprintln!("Error! {:#}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn serve(config: Config) -> anyhow::Result<()> {
     let handler_path = if std::path::Path::new(&config.transformer).exists() {
-        Some(config.transformer)
+        Some(config.transformer.clone())
     } else {
         None
     };
-    let model = PyTorchModel::new(model_config);
-    let boxed_model = Box::new(model);
-    let mut inference = Inference::new(InferenceConfig { handler_path }, boxed_model);
-    let _ = inference.load();
-    let service = GrpcInferenceServiceImpl::with_model(inference);
+    let mut repository = Repository::new(
+        RepositoryConfig {
+            handler_path,
+            expose_hook_errors: config.expose_hook_errors,
+            handler_reload_interval: config.reload_handler.then(|| Duration::from_secs(1)),
+        },
+        Arc::new(|model_config: &ModelConfig| {
+            Backend::for_config(model_config)?.model(model_config.clone())
+        }),
+    );
+    let serve_config = serve_config(&config);
+    repository = repository.with_storage(config.storage.storage());
+    if !serve_config.metrics.is_empty() {
+        repository = repository.with_metrics(Metrics::new()?);
+    }
+    let model_configs = model_configs(Path::new(&config.model_config), &config.python)?;
+    let required_models = match config.required_model.is_empty() {
+        true => model_configs
+            .iter()
+            .map(|model_config| model_config.model_name.clone())
+            .collect(),
+        false => config.required_model.clone(),
+    };
+    let repository = Arc::new(repository);
+    for model_config in model_configs {
+        let name = model_config.model_name.clone();
+        repository.add(model_config, "");
+        // Failures are logged, and the model can be loaded again later.
+        let _ = repository.load(&name, None).await;
+    }
+    let _poller = match config.model_repository {
+        Some(root) => {
+            let mut directory = ModelDirectory::new(root);
+            directory.sync(&repository).await;
+            match config.repository_poll_interval {
+                Some(interval) if interval > 0.0 && interval.is_finite() => {
+                    Some(directory.watch(repository.clone(), Duration::from_secs_f64(interval)))
+                }
+                Some(interval) => {
+                    bail!(
+                        "--repository-poll-interval must be positive, got {}",
+                        interval
+                    )
+                }
+                None => None,
+            }
+        }
+        None => None,
+    };
+    let mut service =
+        GrpcInferenceServiceImpl::with_repository(repository).with_required_models(required_models);
+    if !config.stuck_inference_timeout.is_zero() {
+        service = service.with_stuck_timeout(config.stuck_inference_timeout);
+    }
+    let service = match config.auth_config.as_deref().map(auth).transpose()? {
+        Some(auth) => service.with_auth(auth),
+        None => service,
+    };
 
-    match ferrix_server::serve(config.port, service).await {
-        Ok(()) => println!("Ferrix started"),
-        Err(err) => println!("Error! {}", err.to_string())
+    ferrix_server::serve(serve_config, service).await?;
+    info!("Ferrix stopped");
+
+    Ok(())
+}
+
+/// Without a config file the server starts with no models, which can then be
+/// loaded through the repository API.
+fn model_configs(path: &Path, python: &PythonArgs) -> anyhow::Result<Vec<ModelConfig>> {
+    if !path.exists() {
+        warn!(path = %path.display(), "No model config, starting without models");
+        return Ok(vec![]);
     }
+
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let entries = validate::model_entries(&contents)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            entry
+                .try_into::<ModelConfig>()
+                .map(|model_config| with_python_overrides(model_config, python))
+                .with_context(|| format!("invalid model entry in {}", path.display()))
+        })
+        .collect()
+}
+
+/// Explicit listen addresses take precedence over the port options.
+fn serve_config(config: &Config) -> ServeConfig {
+    let grpc = match config.listen.is_empty() {
+        true => vec![ListenAddr::any(config.port)],
+        false => config.listen.clone(),
+    };
+    let http = match config.http_listen.is_empty() {
+        true => config.http_port.map(ListenAddr::any).into_iter().collect(),
+        false => config.http_listen.clone(),
+    };
+    let metrics = match config.metrics_listen.is_empty() {
+        true => config
+            .metrics_port
+            .map(ListenAddr::any)
+            .into_iter()
+            .collect(),
+        false => config.metrics_listen.clone(),
+    };
+
+    let tls = config
+        .tls_certificate
+        .clone()
+        .zip(config.tls_key.clone())
+        .map(|(certificate, key)| TlsConfig {
+            certificate,
+            key,
+            client_ca: config.tls_client_ca.clone(),
+        });
+
+    ServeConfig {
+        grpc,
+        http,
+        metrics,
+        tls,
+        shutdown_delay: config.shutdown_delay,
+        drain_timeout: config.drain_timeout,
+    }
+}
+
+fn seconds(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("{} is not a number of seconds", value))
+}
+
+fn auth(path: &Path) -> anyhow::Result<Auth> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let config: AuthConfig = toml::from_str(&contents)
+        .with_context(|| format!("invalid auth config {}", path.display()))?;
+
+    Auth::new(config).with_context(|| format!("invalid auth config {}", path.display()))
+}
+
+/// Command line interpreter options take precedence over the model config.
+fn with_python_overrides(mut model_config: ModelConfig, config: &PythonArgs) -> ModelConfig {
+    let mut python = model_config.python.take().unwrap_or_default();
+
+    if config.python_venv.is_some() {
+        python.virtualenv = config.python_venv.clone();
+    }
+    if config.python_home.is_some() {
+        python.python_home = config.python_home.clone();
+    }
+    python.sys_path.extend(config.python_path.iter().cloned());
+    model_config.python = Some(python);
+
+    model_config
 }
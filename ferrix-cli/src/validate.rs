@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context};
+use clap::Args;
+use ferrix_model_api::internal::{InferRequest, InferResponse, InputTensor, TensorData};
+use ferrix_model_api::{ModelConfig, TensorSpec};
+use ferrix_server::inference::{Inference, InferenceConfig};
+use toml::{Table, Value};
+
+use crate::backend::Backend;
+use crate::{with_python_overrides, PythonArgs, StorageArgs};
+
+#[derive(Args, Debug)]
+pub struct ValidateArgs {
+    /// Path to Ferrix model configuration
+    #[arg(default_value = "./ferrix.toml")]
+    model_config: PathBuf,
+
+    /// Path to the Ferrix transformer Python file imported for every model
+    #[arg(short, long)]
+    transformer: Option<String>,
+
+    #[command(flatten)]
+    python: PythonArgs,
+
+    #[command(flatten)]
+    storage: StorageArgs,
+}
+
+pub async fn run(args: ValidateArgs) -> anyhow::Result<()> {
+    let contents = std::fs::read_to_string(&args.model_config)
+        .with_context(|| format!("failed to read {}", args.model_config.display()))?;
+    let entries = model_entries(&contents)
+        .with_context(|| format!("failed to parse {}", args.model_config.display()))?;
+    let mut failed = 0;
+
+    for (index, entry) in entries.iter().enumerate() {
+        let report = validate_model(index, entry, &args).await;
+
+        report.print();
+
+        if report.failed() {
+            failed += 1;
+        }
+    }
+
+    match failed {
+        0 => {
+            println!("{} model(s) passed validation", entries.len());
+            Ok(())
+        }
+        _ => bail!("{} of {} model(s) failed validation", failed, entries.len()),
+    }
+}
+
+/// A config holds either a single model at the top level or a `[[models]]`
+/// array of them.
+pub(crate) fn model_entries(contents: &str) -> anyhow::Result<Vec<Value>> {
+    let mut document: Table = toml::from_str(contents)?;
+
+    match document.remove("models") {
+        Some(Value::Array(models)) if models.is_empty() => bail!("models is empty"),
+        Some(Value::Array(models)) => Ok(models),
+        Some(_) => bail!("models must be an array of tables"),
+        None => Ok(vec![Value::Table(document)]),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Status {
+    Passed,
+    Skipped,
+    Failed,
+}
+
+struct Report {
+    model: String,
+    checks: Vec<(Status, String)>,
+}
+
+impl Report {
+    fn check<T>(&mut self, description: &str, result: anyhow::Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.checks.push((Status::Passed, description.to_string()));
+                Some(value)
+            }
+            Err(error) => {
+                self.checks
+                    .push((Status::Failed, format!("{}: {:#}", description, error)));
+                None
+            }
+        }
+    }
+
+    fn skip(&mut self, description: &str) {
+        self.checks.push((Status::Skipped, description.to_string()));
+    }
+
+    fn failed(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|(status, _)| *status == Status::Failed)
+    }
+
+    fn print(&self) {
+        println!("{}", self.model);
+
+        for (status, description) in &self.checks {
+            let label = match status {
+                Status::Passed => "ok",
+                Status::Skipped => "skip",
+                Status::Failed => "FAIL",
+            };
+
+            println!("  {:<4}  {}", label, description);
+        }
+    }
+}
+
+async fn validate_model(index: usize, entry: &Value, args: &ValidateArgs) -> Report {
+    let mut report = Report {
+        model: entry
+            .get("model_name")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("models[{}]", index)),
+        checks: vec![],
+    };
+
+    check_model(entry, args, &mut report).await;
+
+    report
+}
+
+/// Runs the checks in the order the server would hit them, stopping at the
+/// first failure since every later step depends on it.
+async fn check_model(entry: &Value, args: &ValidateArgs, report: &mut Report) -> Option<()> {
+    let config = entry
+        .clone()
+        .try_into::<ModelConfig>()
+        .map_err(anyhow::Error::from);
+    let mut config =
+        with_python_overrides(report.check("parse model entry", config)?, &args.python);
+
+    config.base_path = report.check(
+        &format!("artifact {} exists", config.base_path),
+        fetch_artifact(&args.storage, &config).await,
+    )?;
+
+    let backend = report.check("resolve platform", Backend::for_config(&config))?;
+
+    report.check(
+        &format!("{} backend compiled in", backend.platform()),
+        compiled_in(backend),
+    )?;
+
+    let model = report.check("create model", backend.model(config.clone()))?;
+    let inference = Inference::new(
+        InferenceConfig {
+            model_config: config.clone(),
+            handler_path: args.transformer.clone(),
+            expose_hook_errors: true,
+        },
+        model,
+    );
+    let mut inference = match &args.transformer {
+        Some(path) => report.check(&format!("import handler {}", path), inference)?,
+        None => report.check("set up inference", inference)?,
+    };
+
+    report.check("load model", inference.load())?;
+
+    let signature = match config.inputs.is_empty() {
+        true => backend
+            .inspect(&config.base_path)
+            .map(|info| info.inputs)
+            .unwrap_or_default(),
+        false => config.inputs.clone(),
+    };
+
+    if signature.is_empty() {
+        report.skip("synthetic request: no input signature, declare inputs in the model entry");
+        return Some(());
+    }
+
+    let request = report.check(
+        "build synthetic request",
+        synthetic_request(&config.model_name, &signature),
+    )?;
+    // Run on its own task so a panicking backend is reported instead of
+    // aborting the remaining models.
+    let response = tokio::spawn(async move { inference.predict(request).await })
+        .await
+        .unwrap_or_else(|error| Err(panicked(error)));
+    let response = report.check("run synthetic request", response)?;
+
+    if !config.outputs.is_empty() {
+        report.check(
+            "declared outputs returned",
+            declared_outputs(&config.outputs, &response),
+        )?;
+    }
+
+    Some(())
+}
+
+fn panicked(error: tokio::task::JoinError) -> anyhow::Error {
+    let payload = match error.try_into_panic() {
+        Ok(payload) => payload,
+        Err(error) => return anyhow!(error),
+    };
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+
+    anyhow!("model panicked: {}", message)
+}
+
+/// Downloads remote artifacts and checks the checksum like the server does,
+/// returning the local path the backend loads.
+async fn fetch_artifact(storage: &StorageArgs, config: &ModelConfig) -> anyhow::Result<String> {
+    let path = storage
+        .storage()
+        .fetch(&config.base_path, config.sha256.as_deref())
+        .await?;
+
+    match path.exists() {
+        true => Ok(path.display().to_string()),
+        false => bail!("not found"),
+    }
+}
+
+fn compiled_in(backend: Backend) -> anyhow::Result<()> {
+    match backend.compiled_in() {
+        true => Ok(()),
+        false => bail!("rebuild with --features {}", backend.feature()),
+    }
+}
+
+fn declared_outputs(outputs: &[TensorSpec], response: &InferResponse) -> anyhow::Result<()> {
+    let missing: Vec<&str> = outputs
+        .iter()
+        .map(|output| output.name.as_str())
+        .filter(|name| !response.outputs.iter().any(|output| output.name == *name))
+        .collect();
+
+    match missing.is_empty() {
+        true => Ok(()),
+        false => bail!("missing {}", missing.join(", ")),
+    }
+}
+
+/// A request of zeros matching the signature, with variable dimensions set to 1.
+fn synthetic_request(model_name: &str, signature: &[TensorSpec]) -> anyhow::Result<InferRequest> {
+    Ok(InferRequest {
+        model_name: model_name.to_string(),
+        model_version: "".to_string(),
+        id: "ferrix-validate".to_string(),
+        parameters: HashMap::new(),
+        inputs: signature
+            .iter()
+            .map(synthetic_input)
+            .collect::<anyhow::Result<Vec<InputTensor>>>()?,
+        outputs: vec![],
+        raw_input_contents: vec![],
+    })
+}
+
+fn synthetic_input(spec: &TensorSpec) -> anyhow::Result<InputTensor> {
+    let shape: Vec<i64> = spec.shape.iter().map(|dim| (*dim).max(1)).collect();
+    let size = element_size(&spec.datatype).ok_or_else(|| {
+        anyhow!(
+            "input {} has unsupported datatype {}",
+            spec.name,
+            spec.datatype
+        )
+    })?;
+    // BYTES elements are empty strings: a zero 4-byte length prefix each.
+    let bytes = vec![0u8; shape.iter().product::<i64>() as usize * size];
+
+    Ok(InputTensor {
+        name: spec.name.clone(),
+        datatype: spec.datatype.clone(),
+        shape,
+        parameters: HashMap::new(),
+        data: TensorData::from_bytes(spec.datatype.clone(), &bytes)?,
+    })
+}
+
+fn element_size(datatype: &str) -> Option<usize> {
+    match datatype {
+        "BOOL" | "UINT8" | "INT8" => Some(1),
+        "UINT16" | "INT16" => Some(2),
+        "UINT32" | "INT32" | "FP32" | "BYTES" => Some(4),
+        "UINT64" | "INT64" | "FP64" => Some(8),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args() -> ValidateArgs {
+        ValidateArgs {
+            model_config: PathBuf::from("ferrix.toml"),
+            transformer: None,
+            python: PythonArgs {
+                python_venv: None,
+                python_path: vec![],
+                python_home: None,
+            },
+            storage: StorageArgs {
+                artifact_cache: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_model_entries() {
+        let single = model_entries("model_name = \"resnet\"\nbase_path = \"./model.pt\"").unwrap();
+        let many = model_entries(
+            "[[models]]\nmodel_name = \"a\"\nbase_path = \"a.pt\"\n\n[[models]]\nmodel_name = \"b\"\nbase_path = \"b.pt\"",
+        )
+        .unwrap();
+
+        assert_eq!(1, single.len());
+        assert_eq!(
+            Some("resnet"),
+            single[0].get("model_name").and_then(Value::as_str)
+        );
+        assert_eq!(2, many.len());
+        assert!(model_entries("models = []").is_err());
+        assert!(model_entries("models = \"resnet\"").is_err());
+    }
+
+    #[test]
+    fn test_synthetic_input() {
+        let input = synthetic_input(&TensorSpec {
+            name: "input".to_string(),
+            datatype: "FP32".to_string(),
+            shape: vec![-1, 3, 2],
+        })
+        .unwrap();
+        let bytes = synthetic_input(&TensorSpec {
+            name: "text".to_string(),
+            datatype: "BYTES".to_string(),
+            shape: vec![-1],
+        })
+        .unwrap();
+
+        assert_eq!(vec![1, 3, 2], input.shape);
+        assert_eq!(vec![0.0; 6], input.data.fp32_contents);
+        assert_eq!(vec![Vec::<u8>::new()], bytes.data.bytes_contents);
+        assert!(synthetic_input(&TensorSpec {
+            name: "half".to_string(),
+            datatype: "FP16".to_string(),
+            shape: vec![1],
+        })
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reports_first_failure() {
+        let missing =
+            toml::from_str::<Value>("model_name = \"resnet\"\nbase_path = \"./does-not-exist.pt\"")
+                .unwrap();
+        let invalid = toml::from_str::<Value>("base_path = \"./model.pt\"").unwrap();
+        let report = validate_model(0, &missing, &args()).await;
+
+        assert!(report.failed());
+        assert_eq!("resnet", report.model);
+        assert_eq!(2, report.checks.len());
+        assert_eq!(Status::Failed, report.checks[1].0);
+
+        let report = validate_model(3, &invalid, &args()).await;
+
+        assert_eq!("models[3]", report.model);
+        assert_eq!(1, report.checks.len());
+        assert!(report.failed());
+    }
+}
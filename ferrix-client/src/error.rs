@@ -0,0 +1,52 @@
+use thiserror::Error;
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("invalid endpoint {0}")]
+    Endpoint(String),
+    #[error("failed to read TLS material {path}: {source}")]
+    Tls {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("credentials can't be sent in a header")]
+    Credentials,
+    #[error(transparent)]
+    Transport(#[from] tonic::transport::Error),
+    #[error("request failed with {}: {}", .0.code(), .0.message())]
+    Status(Box<tonic::Status>),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("server returned HTTP {status}: {message}")]
+    HttpStatus { status: u16, message: String },
+    #[error("invalid tensor: {0}")]
+    Tensor(String),
+    #[error("response has no output named {0}")]
+    MissingOutput(String),
+}
+
+impl From<tonic::Status> for ClientError {
+    fn from(status: tonic::Status) -> Self {
+        ClientError::Status(Box::new(status))
+    }
+}
+
+impl ClientError {
+    /// Whether the request may succeed if sent again: the server couldn't be
+    /// reached or was temporarily unable to serve it. Timed out requests may
+    /// already have run, so sending an inference again could run it twice.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Transport(_) => true,
+            ClientError::Status(status) => matches!(
+                status.code(),
+                tonic::Code::Unavailable | tonic::Code::ResourceExhausted
+            ),
+            ClientError::Http(error) => error.is_connect(),
+            ClientError::HttpStatus { status, .. } => *status == 429 || *status == 503,
+            _ => false,
+        }
+    }
+}
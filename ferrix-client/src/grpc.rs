@@ -0,0 +1,237 @@
+use ferrix_protos::grpc_inference_service_client::GrpcInferenceServiceClient;
+use ferrix_protos::infer_parameter::ParameterChoice;
+use ferrix_protos::model_infer_request::{InferInputTensor, InferRequestedOutputTensor};
+use ferrix_protos::{
+    InferParameter, ModelInferRequest, ModelMetadataRequest, ModelMetadataResponse,
+    ModelReadyRequest, ServerLiveRequest, ServerMetadataRequest, ServerMetadataResponse,
+    ServerReadyRequest,
+};
+use tonic::codec::CompressionEncoding;
+use tonic::metadata::AsciiMetadataValue;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity};
+use tonic::Request;
+
+use crate::error::{ClientError, ClientResult};
+use crate::tensor::Tensor;
+use crate::{ClientBuilder, InferRequest, InferResult, Parameter, TlsConfig};
+
+pub(crate) struct GrpcTransport {
+    client: GrpcInferenceServiceClient<Channel>,
+    credentials: Option<(&'static str, AsciiMetadataValue)>,
+}
+
+impl GrpcTransport {
+    /// Connects lazily: the channel is established on the first call, so this
+    /// must run inside a Tokio runtime but never waits on the network.
+    pub(crate) fn new(builder: &ClientBuilder) -> ClientResult<Self> {
+        let mut endpoint = Endpoint::from_shared(builder.endpoint.clone())
+            .map_err(|_| ClientError::Endpoint(builder.endpoint.clone()))?;
+
+        if let Some(timeout) = builder.connect_timeout {
+            endpoint = endpoint.connect_timeout(timeout);
+        }
+
+        if let Some(timeout) = builder.timeout {
+            endpoint = endpoint.timeout(timeout);
+        }
+
+        match &builder.tls {
+            Some(tls) => endpoint = endpoint.tls_config(tls_config(tls)?)?,
+            None if endpoint.uri().scheme_str() == Some("https") => {
+                endpoint = endpoint.tls_config(ClientTlsConfig::new())?
+            }
+            None => {}
+        }
+
+        let mut client = GrpcInferenceServiceClient::new(endpoint.connect_lazy());
+
+        if builder.compression {
+            client = client
+                .send_compressed(CompressionEncoding::Gzip)
+                .accept_compressed(CompressionEncoding::Gzip);
+        }
+
+        let credentials = match &builder.credentials {
+            Some(credentials) => {
+                let (name, value) = credentials.header();
+                let value = value.parse().map_err(|_| ClientError::Credentials)?;
+                Some((name, value))
+            }
+            None => None,
+        };
+
+        Ok(GrpcTransport {
+            client,
+            credentials,
+        })
+    }
+
+    fn request<T>(&self, message: T) -> Request<T> {
+        let mut request = Request::new(message);
+
+        if let Some((name, value)) = &self.credentials {
+            request.metadata_mut().insert(*name, value.clone());
+        }
+
+        request
+    }
+
+    pub(crate) async fn live(&self) -> ClientResult<bool> {
+        let response = self
+            .client
+            .clone()
+            .server_live(self.request(ServerLiveRequest {}))
+            .await?;
+
+        Ok(response.into_inner().live)
+    }
+
+    pub(crate) async fn ready(&self) -> ClientResult<bool> {
+        let response = self
+            .client
+            .clone()
+            .server_ready(self.request(ServerReadyRequest {}))
+            .await?;
+
+        Ok(response.into_inner().ready)
+    }
+
+    pub(crate) async fn model_ready(&self, name: &str, version: &str) -> ClientResult<bool> {
+        let response = self
+            .client
+            .clone()
+            .model_ready(self.request(ModelReadyRequest {
+                name: name.to_string(),
+                version: version.to_string(),
+            }))
+            .await?;
+
+        Ok(response.into_inner().ready)
+    }
+
+    pub(crate) async fn server_metadata(&self) -> ClientResult<ServerMetadataResponse> {
+        let response = self
+            .client
+            .clone()
+            .server_metadata(self.request(ServerMetadataRequest {}))
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    pub(crate) async fn model_metadata(
+        &self,
+        name: &str,
+        version: &str,
+    ) -> ClientResult<ModelMetadataResponse> {
+        let response = self
+            .client
+            .clone()
+            .model_metadata(self.request(ModelMetadataRequest {
+                name: name.to_string(),
+                version: version.to_string(),
+            }))
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    pub(crate) async fn infer(&self, request: &InferRequest) -> ClientResult<InferResult> {
+        let response = self
+            .client
+            .clone()
+            .model_infer(self.request(to_proto(request.clone())))
+            .await?
+            .into_inner();
+        let mut raw = response.raw_output_contents.into_iter();
+        let mut outputs = Vec::with_capacity(response.outputs.len());
+
+        for output in response.outputs {
+            let tensor = match (raw.next(), output.contents) {
+                (Some(data), _) => Tensor::from_raw(output.datatype, output.shape, data),
+                (None, contents) => Tensor::from_contents(
+                    output.datatype,
+                    output.shape,
+                    contents.unwrap_or_default(),
+                )?,
+            };
+
+            outputs.push((output.name, tensor));
+        }
+
+        Ok(InferResult {
+            model_name: response.model_name,
+            model_version: response.model_version,
+            id: response.id,
+            outputs,
+        })
+    }
+}
+
+fn to_proto(request: InferRequest) -> ModelInferRequest {
+    let mut inputs = Vec::with_capacity(request.inputs.len());
+    let mut raw_input_contents = Vec::with_capacity(request.inputs.len());
+
+    for (name, tensor) in request.inputs {
+        inputs.push(InferInputTensor {
+            name,
+            datatype: tensor.datatype().to_string(),
+            shape: tensor.shape().to_vec(),
+            parameters: Default::default(),
+            contents: None,
+        });
+        raw_input_contents.push(tensor.into_raw());
+    }
+
+    ModelInferRequest {
+        model_name: request.model_name,
+        model_version: request.model_version,
+        id: request.id,
+        parameters: request
+            .parameters
+            .into_iter()
+            .map(|(name, value)| {
+                let choice = match value {
+                    Parameter::Bool(value) => ParameterChoice::BoolParam(value),
+                    Parameter::Int64(value) => ParameterChoice::Int64Param(value),
+                    Parameter::String(value) => ParameterChoice::StringParam(value),
+                };
+
+                (
+                    name,
+                    InferParameter {
+                        parameter_choice: Some(choice),
+                    },
+                )
+            })
+            .collect(),
+        inputs,
+        outputs: request
+            .outputs
+            .into_iter()
+            .map(|name| InferRequestedOutputTensor {
+                name,
+                parameters: Default::default(),
+            })
+            .collect(),
+        raw_input_contents,
+    }
+}
+
+fn tls_config(tls: &TlsConfig) -> ClientResult<ClientTlsConfig> {
+    let mut config = ClientTlsConfig::new();
+
+    if let Some(ca_certificate) = &tls.ca_certificate {
+        config = config.ca_certificate(Certificate::from_pem(tls.read(ca_certificate)?));
+    }
+
+    if let (Some(certificate), Some(key)) = (&tls.certificate, &tls.key) {
+        config = config.identity(Identity::from_pem(tls.read(certificate)?, tls.read(key)?));
+    }
+
+    if let Some(domain) = &tls.domain {
+        config = config.domain_name(domain);
+    }
+
+    Ok(config)
+}
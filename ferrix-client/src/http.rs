@@ -0,0 +1,304 @@
+use ferrix_protos::model_metadata_response::TensorMetadata;
+use ferrix_protos::{ModelMetadataResponse, ServerMetadataResponse};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Identity, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{ClientError, ClientResult};
+use crate::tensor::Tensor;
+use crate::{ClientBuilder, InferRequest, InferResult, Parameter, TlsConfig};
+
+/// Speaks the KServe v2 REST protocol, sending tensor data as JSON.
+pub(crate) struct HttpTransport {
+    client: reqwest::Client,
+    base: String,
+}
+
+#[derive(Serialize)]
+struct JsonInferRequest {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    id: String,
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    parameters: serde_json::Map<String, Value>,
+    inputs: Vec<JsonTensor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    outputs: Vec<JsonRequestedOutput>,
+}
+
+#[derive(Serialize)]
+struct JsonRequestedOutput {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct JsonInferResponse {
+    model_name: String,
+    #[serde(default)]
+    model_version: String,
+    #[serde(default)]
+    id: String,
+    outputs: Vec<JsonTensor>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonTensor {
+    name: String,
+    shape: Vec<i64>,
+    datatype: String,
+    data: Value,
+}
+
+#[derive(Deserialize)]
+struct JsonServerMetadata {
+    name: String,
+    version: String,
+    #[serde(default)]
+    extensions: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonModelMetadata {
+    name: String,
+    #[serde(default)]
+    versions: Vec<String>,
+    platform: String,
+    #[serde(default)]
+    inputs: Vec<JsonTensorMetadata>,
+    #[serde(default)]
+    outputs: Vec<JsonTensorMetadata>,
+}
+
+#[derive(Deserialize)]
+struct JsonTensorMetadata {
+    name: String,
+    datatype: String,
+    shape: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+struct JsonError {
+    error: String,
+}
+
+impl From<JsonTensorMetadata> for TensorMetadata {
+    fn from(metadata: JsonTensorMetadata) -> Self {
+        TensorMetadata {
+            name: metadata.name,
+            datatype: metadata.datatype,
+            shape: metadata.shape,
+        }
+    }
+}
+
+impl HttpTransport {
+    pub(crate) fn new(builder: &ClientBuilder) -> ClientResult<Self> {
+        let base = reqwest::Url::parse(&builder.endpoint)
+            .map_err(|_| ClientError::Endpoint(builder.endpoint.clone()))?
+            .to_string();
+        let mut client = reqwest::Client::builder().gzip(builder.compression);
+
+        if let Some(timeout) = builder.connect_timeout {
+            client = client.connect_timeout(timeout);
+        }
+
+        if let Some(timeout) = builder.timeout {
+            client = client.timeout(timeout);
+        }
+
+        if let Some(tls) = &builder.tls {
+            client = configure_tls(client, tls)?;
+        }
+
+        if let Some(credentials) = &builder.credentials {
+            let (name, value) = credentials.header();
+            let mut value = HeaderValue::from_str(&value).map_err(|_| ClientError::Credentials)?;
+            value.set_sensitive(true);
+            client = client.default_headers(HeaderMap::from_iter([(
+                HeaderName::from_static(name),
+                value,
+            )]));
+        }
+
+        Ok(HttpTransport {
+            client: client.build()?,
+            base: base.trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base, path)
+    }
+
+    /// Health endpoints answer 503 when not live or ready. Any other error,
+    /// such as an unknown model or missing credentials, is returned.
+    async fn get_status(&self, path: &str) -> ClientResult<bool> {
+        let response = self.client.get(self.url(path)).send().await?;
+
+        if response.status() == StatusCode::SERVICE_UNAVAILABLE {
+            return Ok(false);
+        }
+
+        checked(response).await?;
+
+        Ok(true)
+    }
+
+    pub(crate) async fn live(&self) -> ClientResult<bool> {
+        self.get_status("v2/health/live").await
+    }
+
+    pub(crate) async fn ready(&self) -> ClientResult<bool> {
+        self.get_status("v2/health/ready").await
+    }
+
+    pub(crate) async fn model_ready(&self, name: &str, version: &str) -> ClientResult<bool> {
+        self.get_status(&format!("{}/ready", model_path(name, version)))
+            .await
+    }
+
+    pub(crate) async fn server_metadata(&self) -> ClientResult<ServerMetadataResponse> {
+        let response = self.client.get(self.url("v2")).send().await?;
+        let metadata: JsonServerMetadata = checked(response).await?.json().await?;
+
+        Ok(ServerMetadataResponse {
+            name: metadata.name,
+            version: metadata.version,
+            extensions: metadata.extensions,
+        })
+    }
+
+    pub(crate) async fn model_metadata(
+        &self,
+        name: &str,
+        version: &str,
+    ) -> ClientResult<ModelMetadataResponse> {
+        let response = self
+            .client
+            .get(self.url(&model_path(name, version)))
+            .send()
+            .await?;
+        let metadata: JsonModelMetadata = checked(response).await?.json().await?;
+
+        Ok(ModelMetadataResponse {
+            name: metadata.name,
+            versions: metadata.versions,
+            platform: metadata.platform,
+            inputs: metadata.inputs.into_iter().map(Into::into).collect(),
+            outputs: metadata.outputs.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    pub(crate) async fn infer(&self, request: &InferRequest) -> ClientResult<InferResult> {
+        let body = JsonInferRequest {
+            id: request.id.clone(),
+            parameters: request
+                .parameters
+                .iter()
+                .map(|(name, value)| {
+                    let value = match value {
+                        Parameter::Bool(value) => Value::from(*value),
+                        Parameter::Int64(value) => Value::from(*value),
+                        Parameter::String(value) => Value::from(value.as_str()),
+                    };
+
+                    (name.clone(), value)
+                })
+                .collect(),
+            inputs: request
+                .inputs
+                .iter()
+                .map(|(name, tensor)| {
+                    Ok(JsonTensor {
+                        name: name.clone(),
+                        shape: tensor.shape().to_vec(),
+                        datatype: tensor.datatype().to_string(),
+                        data: tensor.to_json()?,
+                    })
+                })
+                .collect::<ClientResult<Vec<JsonTensor>>>()?,
+            outputs: request
+                .outputs
+                .iter()
+                .map(|name| JsonRequestedOutput { name: name.clone() })
+                .collect(),
+        };
+        let path = format!(
+            "{}/infer",
+            model_path(&request.model_name, &request.model_version)
+        );
+        let response = self.client.post(self.url(&path)).json(&body).send().await?;
+        let response: JsonInferResponse = checked(response).await?.json().await?;
+
+        Ok(InferResult {
+            model_name: response.model_name,
+            model_version: response.model_version,
+            id: response.id,
+            outputs: response
+                .outputs
+                .into_iter()
+                .map(|output| {
+                    let tensor = Tensor::from_json(output.datatype, output.shape, output.data)?;
+
+                    Ok((output.name, tensor))
+                })
+                .collect::<ClientResult<Vec<(String, Tensor)>>>()?,
+        })
+    }
+}
+
+fn model_path(name: &str, version: &str) -> String {
+    match version {
+        "" => format!("v2/models/{}", name),
+        version => format!("v2/models/{}/versions/{}", name, version),
+    }
+}
+
+/// Turns a non-2xx response into an error carrying the server's message.
+async fn checked(response: Response) -> ClientResult<Response> {
+    let status = response.status();
+
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<JsonError>(&body)
+        .map(|error| error.error)
+        .unwrap_or(body);
+
+    Err(ClientError::HttpStatus {
+        status: status.as_u16(),
+        message,
+    })
+}
+
+fn configure_tls(
+    mut client: reqwest::ClientBuilder,
+    tls: &TlsConfig,
+) -> ClientResult<reqwest::ClientBuilder> {
+    if let Some(ca_certificate) = &tls.ca_certificate {
+        client = client.add_root_certificate(Certificate::from_pem(&tls.read(ca_certificate)?)?);
+    }
+
+    if let (Some(certificate), Some(key)) = (&tls.certificate, &tls.key) {
+        let mut pem = tls.read(certificate)?;
+
+        pem.push(b'\n');
+        pem.extend(tls.read(key)?);
+        client = client.identity(Identity::from_pem(&pem)?);
+    }
+
+    Ok(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_path() {
+        assert_eq!("v2/models/resnet", model_path("resnet", ""));
+        assert_eq!("v2/models/resnet/versions/2", model_path("resnet", "2"));
+    }
+}
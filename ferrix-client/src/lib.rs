@@ -0,0 +1,362 @@
+//! Typed client for ferrix and other KServe v2 inference servers.
+//!
+//! ```no_run
+//! # async fn example() -> ferrix_client::ClientResult<()> {
+//! use ferrix_client::{Client, Tensor};
+//!
+//! let client = Client::builder("http://localhost:6565").retries(3).build()?;
+//! let image = ndarray::Array4::<f32>::zeros((1, 3, 224, 224));
+//! let result = client
+//!     .infer("resnet", [("input", Tensor::from_array(&image))])
+//!     .await?;
+//! let scores = result.output("output")?.to_array::<f32>()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub use ferrix_protos::{ModelMetadataResponse, ServerMetadataResponse};
+
+use crate::error::ClientError;
+use crate::grpc::GrpcTransport;
+use crate::http::HttpTransport;
+
+pub mod error;
+mod grpc;
+mod http;
+pub mod tensor;
+
+pub use error::ClientResult;
+pub use tensor::{Element, Tensor};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Protocol {
+    #[default]
+    Grpc,
+    Http,
+}
+
+/// PEM files used to verify the server and, for mutual TLS, to identify the
+/// client.
+#[derive(Clone, Debug, Default)]
+pub struct TlsConfig {
+    pub ca_certificate: Option<PathBuf>,
+    pub certificate: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+    /// Overrides the name checked against the server certificate. gRPC only.
+    pub domain: Option<String>,
+}
+
+impl TlsConfig {
+    fn read(&self, path: &Path) -> ClientResult<Vec<u8>> {
+        std::fs::read(path).map_err(|source| ClientError::Tls {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+}
+
+/// How the client identifies itself to servers that require authentication.
+#[derive(Clone, Debug)]
+pub enum Credentials {
+    /// Sent as `Authorization: Bearer <token>`.
+    BearerToken(String),
+    /// Sent in the `x-api-key` header.
+    ApiKey(String),
+}
+
+impl Credentials {
+    fn header(&self) -> (&'static str, String) {
+        match self {
+            Credentials::BearerToken(token) => ("authorization", format!("Bearer {}", token)),
+            Credentials::ApiKey(key) => ("x-api-key", key.clone()),
+        }
+    }
+}
+
+pub struct ClientBuilder {
+    endpoint: String,
+    protocol: Protocol,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    tls: Option<TlsConfig>,
+    credentials: Option<Credentials>,
+    compression: bool,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl ClientBuilder {
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        ClientBuilder {
+            endpoint: endpoint.into(),
+            protocol: Protocol::Grpc,
+            connect_timeout: None,
+            timeout: None,
+            tls: None,
+            credentials: None,
+            compression: false,
+            retries: 0,
+            backoff: Duration::from_millis(100),
+        }
+    }
+
+    pub fn protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Deadline for each individual call, including retried attempts.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    pub fn bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.credentials = Some(Credentials::BearerToken(token.into()));
+        self
+    }
+
+    pub fn api_key(mut self, key: impl Into<String>) -> Self {
+        self.credentials = Some(Credentials::ApiKey(key.into()));
+        self
+    }
+
+    /// Accept gzipped responses. Over gRPC, requests are gzipped too; HTTP
+    /// request bodies are always sent uncompressed.
+    pub fn compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Number of times a call is retried when the server is unreachable or
+    /// unavailable. Other errors, timeouts included, are returned
+    /// immediately.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Delay before the first retry, doubled for each one after.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Builds the client without connecting. gRPC clients connect on first
+    /// use and must be built inside a Tokio runtime.
+    pub fn build(self) -> ClientResult<Client> {
+        let transport = match self.protocol {
+            Protocol::Grpc => Transport::Grpc(Box::new(GrpcTransport::new(&self)?)),
+            Protocol::Http => Transport::Http(HttpTransport::new(&self)?),
+        };
+
+        Ok(Client {
+            transport,
+            retries: self.retries,
+            backoff: self.backoff,
+        })
+    }
+}
+
+enum Transport {
+    Grpc(Box<GrpcTransport>),
+    Http(HttpTransport),
+}
+
+pub struct Client {
+    transport: Transport,
+    retries: u32,
+    backoff: Duration,
+}
+
+/// A request parameter value, as allowed by the KServe v2 protocol.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Parameter {
+    Bool(bool),
+    Int64(i64),
+    String(String),
+}
+
+/// An inference call. Inputs are sent in the order they were added.
+#[derive(Clone, Debug, Default)]
+pub struct InferRequest {
+    pub model_name: String,
+    pub model_version: String,
+    pub id: String,
+    pub parameters: HashMap<String, Parameter>,
+    pub inputs: Vec<(String, Tensor)>,
+    /// Outputs to return; all of them when empty.
+    pub outputs: Vec<String>,
+}
+
+impl InferRequest {
+    pub fn new(model_name: impl Into<String>) -> Self {
+        InferRequest {
+            model_name: model_name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.model_version = version.into();
+        self
+    }
+
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    pub fn parameter(mut self, name: impl Into<String>, value: Parameter) -> Self {
+        self.parameters.insert(name.into(), value);
+        self
+    }
+
+    pub fn input(mut self, name: impl Into<String>, tensor: Tensor) -> Self {
+        self.inputs.push((name.into(), tensor));
+        self
+    }
+
+    pub fn output(mut self, name: impl Into<String>) -> Self {
+        self.outputs.push(name.into());
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct InferResult {
+    pub model_name: String,
+    pub model_version: String,
+    pub id: String,
+    pub outputs: Vec<(String, Tensor)>,
+}
+
+impl InferResult {
+    pub fn output(&self, name: &str) -> ClientResult<&Tensor> {
+        self.outputs
+            .iter()
+            .find(|(output, _)| output == name)
+            .map(|(_, tensor)| tensor)
+            .ok_or_else(|| ClientError::MissingOutput(name.to_string()))
+    }
+}
+
+impl Client {
+    pub fn builder(endpoint: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(endpoint)
+    }
+
+    pub async fn live(&self) -> ClientResult<bool> {
+        self.retrying(|| async {
+            match &self.transport {
+                Transport::Grpc(grpc) => grpc.live().await,
+                Transport::Http(http) => http.live().await,
+            }
+        })
+        .await
+    }
+
+    pub async fn ready(&self) -> ClientResult<bool> {
+        self.retrying(|| async {
+            match &self.transport {
+                Transport::Grpc(grpc) => grpc.ready().await,
+                Transport::Http(http) => http.ready().await,
+            }
+        })
+        .await
+    }
+
+    /// Pass an empty `version` to let the server choose.
+    pub async fn model_ready(&self, name: &str, version: &str) -> ClientResult<bool> {
+        self.retrying(|| async {
+            match &self.transport {
+                Transport::Grpc(grpc) => grpc.model_ready(name, version).await,
+                Transport::Http(http) => http.model_ready(name, version).await,
+            }
+        })
+        .await
+    }
+
+    pub async fn server_metadata(&self) -> ClientResult<ServerMetadataResponse> {
+        self.retrying(|| async {
+            match &self.transport {
+                Transport::Grpc(grpc) => grpc.server_metadata().await,
+                Transport::Http(http) => http.server_metadata().await,
+            }
+        })
+        .await
+    }
+
+    /// Pass an empty `version` to let the server choose.
+    pub async fn model_metadata(
+        &self,
+        name: &str,
+        version: &str,
+    ) -> ClientResult<ModelMetadataResponse> {
+        self.retrying(|| async {
+            match &self.transport {
+                Transport::Grpc(grpc) => grpc.model_metadata(name, version).await,
+                Transport::Http(http) => http.model_metadata(name, version).await,
+            }
+        })
+        .await
+    }
+
+    /// Runs `model` on the given named inputs and returns every output.
+    pub async fn infer<I, S>(&self, model: &str, inputs: I) -> ClientResult<InferResult>
+    where
+        I: IntoIterator<Item = (S, Tensor)>,
+        S: Into<String>,
+    {
+        let request = inputs
+            .into_iter()
+            .fold(InferRequest::new(model), |request, (name, tensor)| {
+                request.input(name, tensor)
+            });
+
+        self.infer_request(&request).await
+    }
+
+    pub async fn infer_request(&self, request: &InferRequest) -> ClientResult<InferResult> {
+        self.retrying(|| async {
+            match &self.transport {
+                Transport::Grpc(grpc) => grpc.infer(request).await,
+                Transport::Http(http) => http.infer(request).await,
+            }
+        })
+        .await
+    }
+
+    async fn retrying<T, F, Fut>(&self, call: F) -> ClientResult<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        let mut attempt = 0;
+
+        loop {
+            match call().await {
+                Err(error) if error.is_retryable() && attempt < self.retries => {
+                    tokio::time::sleep(self.backoff * 2u32.saturating_pow(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
@@ -0,0 +1,477 @@
+use ferrix_protos::InferTensorContents;
+use ndarray::{ArrayBase, ArrayD, Data, Dimension, IxDyn};
+use serde_json::{json, Value};
+
+use crate::error::{ClientError, ClientResult};
+
+/// A Rust type with a KServe v2 datatype and a fixed-size little-endian
+/// encoding.
+pub trait Element: Copy + Sized {
+    const DATATYPE: &'static str;
+    const SIZE: usize;
+
+    fn write(self, bytes: &mut Vec<u8>);
+    fn read(bytes: &[u8]) -> Self;
+}
+
+macro_rules! element {
+    ($type:ty, $datatype:expr) => {
+        impl Element for $type {
+            const DATATYPE: &'static str = $datatype;
+            const SIZE: usize = std::mem::size_of::<$type>();
+
+            fn write(self, bytes: &mut Vec<u8>) {
+                bytes.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn read(bytes: &[u8]) -> Self {
+                <$type>::from_le_bytes(bytes.try_into().unwrap())
+            }
+        }
+    };
+}
+
+element!(u8, "UINT8");
+element!(u16, "UINT16");
+element!(u32, "UINT32");
+element!(u64, "UINT64");
+element!(i8, "INT8");
+element!(i16, "INT16");
+element!(i32, "INT32");
+element!(i64, "INT64");
+element!(f32, "FP32");
+element!(f64, "FP64");
+
+impl Element for bool {
+    const DATATYPE: &'static str = "BOOL";
+    const SIZE: usize = 1;
+
+    fn write(self, bytes: &mut Vec<u8>) {
+        bytes.push(self as u8);
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+}
+
+/// A named input or output of an inference call. Data is always held in the
+/// KServe raw representation: flattened, row-major, little-endian, with each
+/// `BYTES` element prefixed by its 4-byte length.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tensor {
+    datatype: String,
+    shape: Vec<i64>,
+    data: Vec<u8>,
+}
+
+impl Tensor {
+    pub fn new<T: Element>(shape: &[usize], values: &[T]) -> ClientResult<Self> {
+        let elements: usize = shape.iter().product();
+
+        if elements != values.len() {
+            return Err(ClientError::Tensor(format!(
+                "shape {:?} needs {} elements, got {}",
+                shape,
+                elements,
+                values.len()
+            )));
+        }
+
+        Ok(Self::from_iter(shape, values.iter().copied()))
+    }
+
+    pub fn from_array<T: Element, S: Data<Elem = T>, D: Dimension>(
+        array: &ArrayBase<S, D>,
+    ) -> Self {
+        // `iter` walks logical row-major order regardless of memory layout.
+        Self::from_iter(array.shape(), array.iter().copied())
+    }
+
+    pub fn from_bytes<B: AsRef<[u8]>>(shape: &[usize], values: &[B]) -> ClientResult<Self> {
+        let elements: usize = shape.iter().product();
+
+        if elements != values.len() {
+            return Err(ClientError::Tensor(format!(
+                "shape {:?} needs {} elements, got {}",
+                shape,
+                elements,
+                values.len()
+            )));
+        }
+
+        let mut data = Vec::new();
+
+        for value in values {
+            let value = value.as_ref();
+
+            data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            data.extend_from_slice(value);
+        }
+
+        Ok(Tensor {
+            datatype: "BYTES".to_string(),
+            shape: shape.iter().map(|dim| *dim as i64).collect(),
+            data,
+        })
+    }
+
+    fn from_iter<T: Element>(shape: &[usize], values: impl Iterator<Item = T>) -> Self {
+        let mut data = Vec::with_capacity(shape.iter().product::<usize>() * T::SIZE);
+
+        values.for_each(|value| value.write(&mut data));
+
+        Tensor {
+            datatype: T::DATATYPE.to_string(),
+            shape: shape.iter().map(|dim| *dim as i64).collect(),
+            data,
+        }
+    }
+
+    pub(crate) fn from_raw(datatype: String, shape: Vec<i64>, data: Vec<u8>) -> Self {
+        Tensor {
+            datatype,
+            shape,
+            data,
+        }
+    }
+
+    /// Re-encodes typed protobuf contents as raw bytes.
+    pub(crate) fn from_contents(
+        datatype: String,
+        shape: Vec<i64>,
+        contents: InferTensorContents,
+    ) -> ClientResult<Self> {
+        let mut data = Vec::new();
+
+        match datatype.as_str() {
+            "BOOL" => encode(contents.bool_contents, &mut data),
+            "UINT8" => encode(narrow::<u32, u8>(contents.uint_contents)?, &mut data),
+            "UINT16" => encode(narrow::<u32, u16>(contents.uint_contents)?, &mut data),
+            "UINT32" => encode(contents.uint_contents, &mut data),
+            "UINT64" => encode(contents.uint64_contents, &mut data),
+            "INT8" => encode(narrow::<i32, i8>(contents.int_contents)?, &mut data),
+            "INT16" => encode(narrow::<i32, i16>(contents.int_contents)?, &mut data),
+            "INT32" => encode(contents.int_contents, &mut data),
+            "INT64" => encode(contents.int64_contents, &mut data),
+            "FP32" => encode(contents.fp32_contents, &mut data),
+            "FP64" => encode(contents.fp64_contents, &mut data),
+            "BYTES" => {
+                for value in contents.bytes_contents {
+                    data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                    data.extend_from_slice(&value);
+                }
+            }
+            other => {
+                return Err(ClientError::Tensor(format!(
+                    "datatype {} has no typed contents",
+                    other
+                )))
+            }
+        }
+
+        Ok(Tensor {
+            datatype,
+            shape,
+            data,
+        })
+    }
+
+    pub fn datatype(&self) -> &str {
+        &self.datatype
+    }
+
+    pub fn shape(&self) -> &[i64] {
+        &self.shape
+    }
+
+    /// The raw KServe encoding of the tensor.
+    pub fn raw(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub(crate) fn into_raw(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn to_vec<T: Element>(&self) -> ClientResult<Vec<T>> {
+        if self.datatype != T::DATATYPE {
+            return Err(ClientError::Tensor(format!(
+                "tensor is {}, not {}",
+                self.datatype,
+                T::DATATYPE
+            )));
+        }
+
+        if !self.data.chunks_exact(T::SIZE).remainder().is_empty() {
+            return Err(ClientError::Tensor(format!(
+                "{} bytes is not a whole number of {} elements",
+                self.data.len(),
+                T::DATATYPE
+            )));
+        }
+
+        Ok(self.data.chunks_exact(T::SIZE).map(T::read).collect())
+    }
+
+    pub fn to_array<T: Element>(&self) -> ClientResult<ArrayD<T>> {
+        let shape = self
+            .shape
+            .iter()
+            .map(|dim| usize::try_from(*dim))
+            .collect::<Result<Vec<usize>, _>>()
+            .map_err(|_| ClientError::Tensor(format!("invalid shape {:?}", self.shape)))?;
+
+        ArrayD::from_shape_vec(IxDyn(&shape), self.to_vec()?)
+            .map_err(|error| ClientError::Tensor(error.to_string()))
+    }
+
+    /// Splits a `BYTES` tensor into its elements.
+    pub fn to_bytes(&self) -> ClientResult<Vec<Vec<u8>>> {
+        if self.datatype != "BYTES" {
+            return Err(ClientError::Tensor(format!(
+                "tensor is {}, not BYTES",
+                self.datatype
+            )));
+        }
+
+        let mut values = Vec::new();
+        let mut rest = self.data.as_slice();
+
+        while !rest.is_empty() {
+            if rest.len() < 4 {
+                return Err(ClientError::Tensor("truncated BYTES length".to_string()));
+            }
+
+            let length = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+
+            if rest.len() < 4 + length {
+                return Err(ClientError::Tensor("truncated BYTES element".to_string()));
+            }
+
+            values.push(rest[4..4 + length].to_vec());
+            rest = &rest[4 + length..];
+        }
+
+        Ok(values)
+    }
+
+    pub fn to_strings(&self) -> ClientResult<Vec<String>> {
+        self.to_bytes()?
+            .into_iter()
+            .map(|value| {
+                String::from_utf8(value).map_err(|error| ClientError::Tensor(error.to_string()))
+            })
+            .collect()
+    }
+
+    /// Builds a tensor from KServe REST `data`, which may be flat or nested
+    /// following the shape.
+    pub fn from_json(datatype: String, shape: Vec<i64>, data: Value) -> ClientResult<Self> {
+        let mut values = Vec::new();
+        flatten(data, &mut values);
+
+        let raw = match datatype.as_str() {
+            "BOOL" => parse(&values, Value::as_bool)?,
+            "UINT8" => parse::<u8>(&values, |value| value.as_u64()?.try_into().ok())?,
+            "UINT16" => parse::<u16>(&values, |value| value.as_u64()?.try_into().ok())?,
+            "UINT32" => parse::<u32>(&values, |value| value.as_u64()?.try_into().ok())?,
+            "UINT64" => parse(&values, Value::as_u64)?,
+            "INT8" => parse::<i8>(&values, |value| value.as_i64()?.try_into().ok())?,
+            "INT16" => parse::<i16>(&values, |value| value.as_i64()?.try_into().ok())?,
+            "INT32" => parse::<i32>(&values, |value| value.as_i64()?.try_into().ok())?,
+            "INT64" => parse(&values, Value::as_i64)?,
+            "FP32" => parse(&values, |value| value.as_f64().map(|value| value as f32))?,
+            "FP64" => parse(&values, Value::as_f64)?,
+            "BYTES" => {
+                let strings = values
+                    .iter()
+                    .map(|value| value.as_str().map(str::as_bytes))
+                    .collect::<Option<Vec<&[u8]>>>()
+                    .ok_or_else(|| ClientError::Tensor("BYTES data must be strings".to_string()))?;
+
+                Tensor::from_bytes(&[strings.len()], &strings)?.data
+            }
+            other => {
+                return Err(ClientError::Tensor(format!(
+                    "datatype {} can't be read from JSON",
+                    other
+                )))
+            }
+        };
+
+        Ok(Tensor {
+            datatype,
+            shape,
+            data: raw,
+        })
+    }
+
+    /// The tensor's elements as a flat JSON array, as used by the KServe REST
+    /// protocol.
+    pub fn to_json(&self) -> ClientResult<Value> {
+        Ok(match self.datatype.as_str() {
+            "BOOL" => json!(self.to_vec::<bool>()?),
+            "UINT8" => json!(self.to_vec::<u8>()?),
+            "UINT16" => json!(self.to_vec::<u16>()?),
+            "UINT32" => json!(self.to_vec::<u32>()?),
+            "UINT64" => json!(self.to_vec::<u64>()?),
+            "INT8" => json!(self.to_vec::<i8>()?),
+            "INT16" => json!(self.to_vec::<i16>()?),
+            "INT32" => json!(self.to_vec::<i32>()?),
+            "INT64" => json!(self.to_vec::<i64>()?),
+            // Widening to f64 directly would print 0.1 as 0.10000000149011612.
+            "FP32" => Value::Array(
+                self.to_vec::<f32>()?
+                    .into_iter()
+                    .map(|value| match value.to_string().parse::<f64>() {
+                        Ok(value) => json!(value),
+                        Err(_) => Value::Null,
+                    })
+                    .collect(),
+            ),
+            "FP64" => json!(self.to_vec::<f64>()?),
+            "BYTES" => json!(self.to_strings()?),
+            other => {
+                return Err(ClientError::Tensor(format!(
+                    "datatype {} can't be written as JSON",
+                    other
+                )))
+            }
+        })
+    }
+}
+
+fn flatten(value: Value, values: &mut Vec<Value>) {
+    match value {
+        Value::Array(items) => items.into_iter().for_each(|item| flatten(item, values)),
+        value => values.push(value),
+    }
+}
+
+fn parse<T: Element>(
+    values: &[Value],
+    convert: impl Fn(&Value) -> Option<T>,
+) -> ClientResult<Vec<u8>> {
+    let mut raw = Vec::with_capacity(values.len() * T::SIZE);
+
+    for value in values {
+        convert(value)
+            .ok_or_else(|| {
+                ClientError::Tensor(format!("{} is not a valid {}", value, T::DATATYPE))
+            })?
+            .write(&mut raw);
+    }
+
+    Ok(raw)
+}
+
+fn encode<T: Element>(values: Vec<T>, data: &mut Vec<u8>) {
+    values.into_iter().for_each(|value| value.write(data));
+}
+
+fn narrow<From, To>(values: Vec<From>) -> ClientResult<Vec<To>>
+where
+    From: Copy + std::fmt::Display,
+    To: TryFrom<From>,
+{
+    values
+        .into_iter()
+        .map(|value| {
+            To::try_from(value)
+                .map_err(|_| ClientError::Tensor(format!("{} is out of range", value)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip_array() {
+        let array = array![[1.0f32, 2.0, 3.0], [4.0, 5.0, 6.0]];
+        let tensor = Tensor::from_array(&array);
+
+        assert_eq!("FP32", tensor.datatype());
+        assert_eq!(&[2, 3], tensor.shape());
+        assert_eq!(array.into_dyn(), tensor.to_array::<f32>().unwrap());
+    }
+
+    #[test]
+    fn test_transposed_array_is_row_major() {
+        let array = array![[1i32, 2], [3, 4]];
+        let tensor = Tensor::from_array(&array.t());
+
+        assert_eq!(vec![1, 3, 2, 4], tensor.to_vec::<i32>().unwrap());
+    }
+
+    #[test]
+    fn test_rejects_mismatched_shape() {
+        assert!(Tensor::new(&[2, 2], &[1u8, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_wrong_element_type() {
+        let tensor = Tensor::new(&[2], &[1i64, 2]).unwrap();
+
+        assert!(tensor.to_vec::<f64>().is_err());
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let tensor = Tensor::from_bytes(&[2], &["cat", "dog"]).unwrap();
+
+        assert_eq!("BYTES", tensor.datatype());
+        assert_eq!(vec!["cat", "dog"], tensor.to_strings().unwrap());
+    }
+
+    #[test]
+    fn test_from_typed_contents() {
+        let contents = InferTensorContents {
+            int_contents: vec![-1, 2],
+            ..Default::default()
+        };
+        let tensor = Tensor::from_contents("INT8".to_string(), vec![2], contents).unwrap();
+
+        assert_eq!(vec![-1i8, 2], tensor.to_vec::<i8>().unwrap());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let tensor = Tensor::new(&[2, 2], &[1i16, -2, 3, -4]).unwrap();
+        let json = tensor.to_json().unwrap();
+
+        assert_eq!(json!([1, -2, 3, -4]), json);
+        assert_eq!(
+            tensor,
+            Tensor::from_json("INT16".to_string(), vec![2, 2], json).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_nested_json_data() {
+        let tensor = Tensor::from_json(
+            "FP32".to_string(),
+            vec![2, 2],
+            json!([[1.0, 2.0], [3.0, 4.0]]),
+        )
+        .unwrap();
+
+        assert_eq!(vec![1.0f32, 2.0, 3.0, 4.0], tensor.to_vec::<f32>().unwrap());
+    }
+
+    #[test]
+    fn test_fp32_json_is_not_widened() {
+        let tensor = Tensor::new(&[1], &[0.1f32]).unwrap();
+
+        assert_eq!("[0.1]", tensor.to_json().unwrap().to_string());
+    }
+
+    #[test]
+    fn test_out_of_range_json_data() {
+        assert!(Tensor::from_json("UINT8".to_string(), vec![1], json!([256])).is_err());
+    }
+}
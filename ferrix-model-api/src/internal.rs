@@ -20,18 +20,29 @@ use crate::python::PyInferOutput;
 use crate::python::PyInferRequest;
 use crate::python::PyInferResponse;
 use crate::python::PyParameter;
+use crate::ModelError;
 
 macro_rules! to_bytevec {
     ($e:expr,$type:ty) => {
         $e.iter()
-            .flat_map({ |i| i.clone().to_ne_bytes() })
+            .flat_map({ |i| i.clone().to_le_bytes() })
             .collect::<Vec<u8>>()
     };
 }
 
 macro_rules! from_bytevec {
     ($e:expr,$type:ty,$size:expr) => {
-        $e.chunks($size).map(|item| <$type>::from_ne_bytes(item.try_into().unwrap())).collect::<Vec<$type>>()
+        match $e.len() % $size {
+            0 => Ok($e
+                .chunks_exact($size)
+                .map(|item| <$type>::from_le_bytes(item.try_into().unwrap()))
+                .collect::<Vec<$type>>()),
+            _ => Err(ModelError::InvalidInput(format!(
+                "{} bytes is not a whole number of {}-byte elements",
+                $e.len(),
+                $size
+            ))),
+        }
     };
 }
 
@@ -72,8 +83,11 @@ impl ToPyObject for InferRequest {
 }
 
 impl InferRequest {
-    pub fn from_proto(request: ModelInferRequest) -> Self {
-        InferRequest {
+    /// Fails on raw inputs that don't decode as their datatype.
+    pub fn from_proto(request: ModelInferRequest) -> Result<Self, ModelError> {
+        let raw_input_contents = request.raw_input_contents;
+
+        Ok(InferRequest {
             model_name: request.model_name,
             model_version: request.model_version,
             id: request.id,
@@ -81,11 +95,15 @@ impl InferRequest {
             inputs: request
                 .inputs
                 .iter()
-                .map(|input| InputTensor::from_proto(input))
-                .collect::<Vec<InputTensor>>(),
+                .enumerate()
+                .map(|(index, input)| match raw_input_contents.get(index) {
+                    Some(raw) => InputTensor::from_raw_proto(input, raw),
+                    None => Ok(InputTensor::from_proto(input)),
+                })
+                .collect::<Result<Vec<InputTensor>, ModelError>>()?,
             outputs: vec![],
-            raw_input_contents: vec![vec![]],
-        }
+            raw_input_contents,
+        })
     }
 }
 
@@ -130,6 +148,7 @@ impl InferResponse {
         let mut response = ModelInferResponse::default();
 
         response.id = self.id.to_string();
+        response.model_name = self.model_name.to_string();
         response.outputs = self
             .outputs
             .iter()
@@ -201,11 +220,14 @@ pub struct InputTensor {
 
 impl InputTensor {
     pub fn as_bytes(self) -> Vec<u8> {
-        match self.datatype.as_str() {
-            "INT32" => to_bytevec!(self.data.int_contents, i32),
-            "FP32" => to_bytevec!(self.data.fp32_contents, f32),
-            _ => panic!(""),
-        }
+        self.data.to_bytes(&self.datatype)
+    }
+
+    fn from_raw_proto(request: &InferInputTensor, raw: &[u8]) -> Result<Self, ModelError> {
+        Ok(InputTensor {
+            data: TensorData::from_bytes(request.datatype.to_string(), raw)?,
+            ..InputTensor::from_proto(request)
+        })
     }
 
     fn from_proto(request: &InferInputTensor) -> Self {
@@ -218,9 +240,11 @@ impl InputTensor {
                 .iter()
                 .map(|(key, value)| (key.to_string(), Parameter::from_proto(value)))
                 .collect::<HashMap<String, Parameter>>(),
-            data: TensorData::from_proto(
-                request.contents.as_ref().unwrap()
-            ),
+            data: request
+                .contents
+                .as_ref()
+                .map(TensorData::from_proto)
+                .unwrap_or_default(),
         }
     }
 }
@@ -289,9 +313,11 @@ impl OutputTensor {
                 .iter()
                 .map(|(key, value)| (key.to_string(), Parameter::from_proto(value)))
                 .collect::<HashMap<String, Parameter>>(),
-            data: TensorData::from_proto(
-                response.contents.as_ref().unwrap(),
-            ),
+            data: response
+                .contents
+                .as_ref()
+                .map(TensorData::from_proto)
+                .unwrap_or_default(),
         }
     }
 
@@ -301,6 +327,7 @@ impl OutputTensor {
         tensor.datatype = self.datatype.to_string();
         tensor.name = self.name.to_string();
         tensor.shape = self.shape.clone();
+        tensor.contents = Some(self.data.to_proto());
         tensor.parameters = self
             .parameters
             .iter()
@@ -355,8 +382,8 @@ pub struct TensorData {
     pub bytes_contents: Vec<Vec<u8>>,
 }
 
-impl TensorData {
-    pub fn default() -> Self {
+impl Default for TensorData {
+    fn default() -> Self {
         TensorData {
             bool_contents: vec![],
             int_contents: vec![],
@@ -368,7 +395,9 @@ impl TensorData {
             bytes_contents: vec![],
         }
     }
+}
 
+impl TensorData {
     fn from_proto(contents: &InferTensorContents) -> Self {
         let data = contents.clone();
         TensorData {
@@ -383,16 +412,130 @@ impl TensorData {
         }
     }
 
-    pub fn from_bytes(datatype: String, bytes: &[u8]) -> Self {
+    fn to_proto(&self) -> InferTensorContents {
+        InferTensorContents {
+            bool_contents: self.bool_contents.clone(),
+            int_contents: self.int_contents.clone(),
+            int64_contents: self.int64_contents.clone(),
+            uint_contents: self.uint_contents.clone(),
+            uint64_contents: self.uint64_contents.clone(),
+            fp32_contents: self.fp32_contents.clone(),
+            fp64_contents: self.fp64_contents.clone(),
+            bytes_contents: self.bytes_contents.clone(),
+        }
+    }
+
+    /// Decodes the KServe raw representation: flattened little-endian
+    /// elements, or 4-byte little-endian length prefixed items for BYTES.
+    /// Half precision floats aren't supported.
+    pub fn from_bytes(datatype: String, bytes: &[u8]) -> Result<Self, ModelError> {
         let mut data = TensorData::default();
 
         match datatype.as_str() {
-            "INT32" => data.int_contents = from_bytevec!(bytes, i32, 4),
-            "FP32" => data.fp32_contents = from_bytevec!(bytes, f32, 4),
-            _ => todo!(),
+            "BOOL" => data.bool_contents = bytes.iter().map(|byte| *byte != 0).collect(),
+            "UINT8" => data.uint_contents = bytes.iter().map(|byte| *byte as u32).collect(),
+            "UINT16" => {
+                data.uint_contents = from_bytevec!(bytes, u16, 2)?
+                    .into_iter()
+                    .map(u32::from)
+                    .collect()
+            }
+            "UINT32" => data.uint_contents = from_bytevec!(bytes, u32, 4)?,
+            "UINT64" => data.uint64_contents = from_bytevec!(bytes, u64, 8)?,
+            "INT8" => data.int_contents = bytes.iter().map(|byte| *byte as i8 as i32).collect(),
+            "INT16" => {
+                data.int_contents = from_bytevec!(bytes, i16, 2)?
+                    .into_iter()
+                    .map(i32::from)
+                    .collect()
+            }
+            "INT32" => data.int_contents = from_bytevec!(bytes, i32, 4)?,
+            "INT64" => data.int64_contents = from_bytevec!(bytes, i64, 8)?,
+            "FP32" => data.fp32_contents = from_bytevec!(bytes, f32, 4)?,
+            "FP64" => data.fp64_contents = from_bytevec!(bytes, f64, 8)?,
+            "BYTES" => {
+                let mut rest = bytes;
+
+                while !rest.is_empty() {
+                    let truncated = || ModelError::InvalidInput("truncated BYTES element".into());
+                    let prefix = rest.get(..4).ok_or_else(truncated)?;
+                    let end = 4 + u32::from_le_bytes(prefix.try_into().unwrap()) as usize;
+
+                    data.bytes_contents
+                        .push(rest.get(4..end).ok_or_else(truncated)?.to_vec());
+                    rest = &rest[end..];
+                }
+            }
+            "FP16" | "BF16" => {
+                return Err(ModelError::InvalidInput(format!(
+                    "{} tensors are not supported",
+                    datatype
+                )))
+            }
+            _ => {
+                return Err(ModelError::InvalidInput(format!(
+                    "unknown datatype {}",
+                    datatype
+                )))
+            }
         }
 
-        data
+        Ok(data)
+    }
+
+    /// Encodes the tensor in the KServe raw representation, the inverse of
+    /// `from_bytes`.
+    pub fn to_bytes(&self, datatype: &str) -> Vec<u8> {
+        match datatype {
+            "BOOL" => self
+                .bool_contents
+                .iter()
+                .map(|value| *value as u8)
+                .collect(),
+            "UINT8" => self
+                .uint_contents
+                .iter()
+                .map(|value| *value as u8)
+                .collect(),
+            "UINT16" => to_bytevec!(
+                self.uint_contents
+                    .iter()
+                    .map(|value| *value as u16)
+                    .collect::<Vec<u16>>(),
+                u16
+            ),
+            "UINT32" => to_bytevec!(self.uint_contents, u32),
+            "UINT64" => to_bytevec!(self.uint64_contents, u64),
+            "INT8" => to_bytevec!(
+                self.int_contents
+                    .iter()
+                    .map(|value| *value as i8)
+                    .collect::<Vec<i8>>(),
+                i8
+            ),
+            "INT16" => to_bytevec!(
+                self.int_contents
+                    .iter()
+                    .map(|value| *value as i16)
+                    .collect::<Vec<i16>>(),
+                i16
+            ),
+            "INT32" => to_bytevec!(self.int_contents, i32),
+            "INT64" => to_bytevec!(self.int64_contents, i64),
+            "FP32" => to_bytevec!(self.fp32_contents, f32),
+            "FP64" => to_bytevec!(self.fp64_contents, f64),
+            "BYTES" => self
+                .bytes_contents
+                .iter()
+                .flat_map(|item| {
+                    (item.len() as u32)
+                        .to_le_bytes()
+                        .into_iter()
+                        .chain(item.iter().copied())
+                })
+                .collect(),
+            _ => panic!(""),
+        }
     }
 }
 
@@ -417,8 +560,12 @@ mod tests {
     use pyo3::{prepare_freethreaded_python, Py, Python, ToPyObject};
 
     use crate::python::{PyInferInput, PyParameter};
+    use crate::ModelError;
 
-    use super::{InputTensor, Parameter, TensorData};
+    use ferrix_protos::model_infer_request::InferInputTensor;
+    use ferrix_protos::ModelInferRequest;
+
+    use super::{InferRequest, InputTensor, Parameter, TensorData};
 
     fn setup() {
         prepare_freethreaded_python();
@@ -461,6 +608,80 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_tensor_data_raw_round_trip() {
+        let mut data = TensorData::default();
+
+        data.int_contents = vec![-3, 0, 7];
+        data.bytes_contents = vec![b"cat".to_vec(), vec![]];
+
+        for datatype in ["INT8", "INT16", "INT32"] {
+            let bytes = data.to_bytes(datatype);
+
+            assert_eq!(
+                data.int_contents,
+                TensorData::from_bytes(datatype.to_string(), &bytes)
+                    .unwrap()
+                    .int_contents
+            );
+        }
+
+        let bytes = data.to_bytes("BYTES");
+
+        assert_eq!(
+            data.bytes_contents,
+            TensorData::from_bytes("BYTES".to_string(), &bytes)
+                .unwrap()
+                .bytes_contents
+        );
+    }
+
+    #[test]
+    fn test_tensor_data_rejects_malformed_raw() {
+        let invalid = |datatype: &str, bytes: &[u8]| {
+            matches!(
+                TensorData::from_bytes(datatype.to_string(), bytes),
+                Err(ModelError::InvalidInput(_))
+            )
+        };
+
+        assert!(invalid("FP32", &[0, 0, 128]));
+        assert!(invalid("INT64", &[0; 12]));
+        assert!(invalid("FP16", &[0, 60]));
+        assert!(invalid("BF16", &[0, 60]));
+        assert!(invalid("COMPLEX64", &[]));
+        assert!(invalid("BYTES", &[3, 0, 0, 0, b'c', b'a']));
+        assert!(invalid("BYTES", &[0, 0]));
+        assert_eq!(
+            vec![1.0_f32],
+            TensorData::from_bytes("FP32".to_string(), &[0, 0, 128, 63])
+                .unwrap()
+                .fp32_contents
+        );
+    }
+
+    #[test]
+    fn test_infer_request_decodes_raw_inputs() {
+        let mut request = ModelInferRequest::default();
+        let mut input = InferInputTensor::default();
+
+        input.name = "x".to_string();
+        input.datatype = "FP32".to_string();
+        input.shape = vec![2];
+        request.inputs = vec![input];
+        request.raw_input_contents = vec![[1.5_f32, -2.0_f32]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()];
+
+        let decoded = InferRequest::from_proto(request).unwrap();
+
+        assert_eq!(
+            vec![1.5_f32, -2.0_f32],
+            decoded.inputs[0].data.fp32_contents
+        );
+    }
+
     #[test]
     fn test_input_tensor_py_conversion() {
         setup();
@@ -1,22 +1,83 @@
+use std::collections::BTreeMap;
+
 use internal::{InferRequest, InferResponse};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use toml::Value;
 
 pub mod internal;
+pub mod platform;
 pub mod python;
 
 pub trait Model: Send + Sync {
     fn load(&mut self) -> ModelResult<()>;
+    /// Frees whatever `load` acquired. Called once the model is no longer
+    /// served and no request is using it.
+    fn unload(&mut self) -> ModelResult<()>;
+    /// Whether the model can answer predictions. Checked by health probes at
+    /// any time, so it must not change the model.
     fn loaded(&self) -> bool;
     fn predict(&self, request: &InferRequest) -> ModelResult<InferResponse>;
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ModelConfig {
     pub model_name: String,
     pub base_path: String,
+    /// Expected SHA-256 of the artifact as hex, checked before the model
+    /// loads. Only valid when `base_path` names a single file; directories
+    /// are rejected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Backend serving the model, e.g. `pytorch_libtorch`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    /// Declared input signature, reported through model metadata.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<TensorSpec>,
+    /// Declared output signature, reported through model metadata.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<TensorSpec>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub extended_config: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub python: Option<PythonConfig>,
+}
+
+/// Name, KServe datatype and shape of a model input or output. Variable
+/// dimensions are -1.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TensorSpec {
+    pub name: String,
+    pub datatype: String,
+    pub shape: Vec<i64>,
+}
+
+/// What a backend can read from a model artifact without serving it.
+#[derive(Clone, Debug, Default)]
+pub struct ArtifactInfo {
+    pub platform: String,
+    pub inputs: Vec<TensorSpec>,
+    pub outputs: Vec<TensorSpec>,
+    pub parameter_count: Option<u64>,
+    /// Backend specific details such as opset versions or the producer.
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Interpreter environment used to run Python handlers. The interpreter is
+/// shared by the whole process, so `python_home` only takes effect for the
+/// first model that starts Python.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PythonConfig {
+    /// Virtualenv whose site-packages are put at the front of `sys.path`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub virtualenv: Option<String>,
+    /// Extra entries prepended to `sys.path`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sys_path: Vec<String>,
+    /// Value for `PYTHONHOME` when starting the interpreter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub python_home: Option<String>,
 }
 
 pub type ModelResult<T> = std::result::Result<T, anyhow::Error>;
@@ -27,6 +88,11 @@ pub enum ModelError {
     Load(String),
     #[error("prediction error: {0}")]
     Prediction(String),
+    #[error("hook error: {0}")]
+    Hook(String),
+    /// A request the model can't make sense of, such as a malformed tensor.
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
     #[error(transparent)]
     Wrapped(Box<dyn std::error::Error + Send + Sync>),
 }
@@ -0,0 +1,7 @@
+//! KServe platform names of the model backends, as used in
+//! `ModelConfig::platform`. They live here rather than in the backend crates
+//! so they're known even when a backend isn't compiled in.
+
+pub const PYTORCH: &str = "pytorch_libtorch";
+pub const ONNX: &str = "onnxruntime_onnx";
+pub const CANDLE: &str = "candle_safetensors";
@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Read;
+
+use ferrix_model_api::{ArtifactInfo, ModelError, ModelResult};
+use serde::Deserialize;
+
+pub const PLATFORM: &str = ferrix_model_api::platform::CANDLE;
+
+/// Largest header accepted, matching the safetensors reference implementation.
+const MAX_HEADER_SIZE: u64 = 100_000_000;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HeaderEntry {
+    Tensor { dtype: String, shape: Vec<u64> },
+    Metadata(HashMap<String, String>),
+}
+
+/// Reads tensor names, dtypes and shapes from a safetensors header without
+/// loading the weights. Weight files carry no input or output signature, so
+/// only the parameter count and metadata are filled in.
+pub fn inspect(path: &str) -> ModelResult<ArtifactInfo> {
+    let load_error =
+        |error: String| ModelError::Load(format!("failed to read {}: {}", path, error));
+    let mut file = File::open(path).map_err(|error| load_error(error.to_string()))?;
+    let mut length = [0u8; 8];
+
+    file.read_exact(&mut length)
+        .map_err(|error| load_error(error.to_string()))?;
+
+    let length = u64::from_le_bytes(length);
+
+    if length > MAX_HEADER_SIZE {
+        return Err(load_error(format!("header of {} bytes is too large", length)).into());
+    }
+
+    let mut header = vec![0u8; length as usize];
+
+    file.read_exact(&mut header)
+        .map_err(|error| load_error(error.to_string()))?;
+
+    describe(&header).map_err(|error| load_error(error).into())
+}
+
+fn describe(header: &[u8]) -> Result<ArtifactInfo, String> {
+    let entries: HashMap<String, HeaderEntry> =
+        serde_json::from_slice(header).map_err(|error| error.to_string())?;
+    let mut metadata = BTreeMap::new();
+    let mut dtypes: BTreeMap<String, usize> = BTreeMap::new();
+    let mut parameter_count = 0;
+    let mut tensors = 0;
+
+    for (name, entry) in entries {
+        match entry {
+            HeaderEntry::Tensor { dtype, shape } => {
+                parameter_count += shape.iter().product::<u64>();
+                tensors += 1;
+                *dtypes.entry(dtype).or_default() += 1;
+            }
+            HeaderEntry::Metadata(values) if name == "__metadata__" => metadata.extend(values),
+            HeaderEntry::Metadata(_) => return Err(format!("{} is not a tensor", name)),
+        }
+    }
+
+    metadata.insert("tensors".to_string(), tensors.to_string());
+    metadata.insert(
+        "dtypes".to_string(),
+        dtypes
+            .iter()
+            .map(|(dtype, count)| format!("{}={}", dtype, count))
+            .collect::<Vec<String>>()
+            .join(", "),
+    );
+
+    Ok(ArtifactInfo {
+        platform: PLATFORM.to_string(),
+        inputs: vec![],
+        outputs: vec![],
+        parameter_count: Some(parameter_count),
+        metadata,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_header() {
+        let header = br#"{
+            "__metadata__": {"format": "pt"},
+            "fc.weight": {"dtype": "F32", "shape": [10, 4], "data_offsets": [0, 160]},
+            "fc.bias": {"dtype": "F32", "shape": [10], "data_offsets": [160, 200]}
+        }"#;
+        let info = describe(header).unwrap();
+
+        assert_eq!(Some(50), info.parameter_count);
+        assert_eq!("pt", info.metadata["format"]);
+        assert_eq!("F32=2", info.metadata["dtypes"]);
+    }
+}
@@ -6,6 +6,10 @@ use ferrix_model_api::{
     Model,
 };
 
+mod inspect;
+
+pub use inspect::{inspect, PLATFORM};
+
 struct CandleModel {
     module: Arc<dyn Module + Send + Sync>,
 }
@@ -21,6 +25,11 @@ impl Model for CandleModel {
         todo!()
     }
 
+    fn unload(&mut self) -> ferrix_model_api::ModelResult<()> {
+        // The module is only freed once the model itself is dropped.
+        Ok(())
+    }
+
     fn loaded(&self) -> bool {
         todo!()
     }
@@ -0,0 +1,196 @@
+use std::collections::{BTreeMap, HashSet};
+
+use ferrix_model_api::{ArtifactInfo, ModelError, ModelResult, TensorSpec};
+use prost::Message;
+
+use crate::proto::{ModelProto, ValueInfoProto};
+
+pub const PLATFORM: &str = ferrix_model_api::platform::ONNX;
+
+/// Reads the signature, parameter count and opsets of an ONNX model by
+/// decoding its protobuf directly, so no runtime session is created.
+pub fn inspect(path: &str) -> ModelResult<ArtifactInfo> {
+    let bytes = std::fs::read(path)
+        .map_err(|error| ModelError::Load(format!("failed to read {}: {}", path, error)))?;
+
+    decode(&bytes)
+}
+
+fn decode(bytes: &[u8]) -> ModelResult<ArtifactInfo> {
+    let model = ModelProto::decode(bytes)
+        .map_err(|error| ModelError::Load(format!("not an ONNX model: {}", error)))?;
+    let graph = model.graph.unwrap_or_default();
+    // Older exporters list weights as graph inputs too.
+    let initializers: HashSet<&str> = graph
+        .initializer
+        .iter()
+        .map(|tensor| tensor.name.as_str())
+        .collect();
+    let parameter_count = graph
+        .initializer
+        .iter()
+        .map(|tensor| tensor.dims.iter().product::<i64>().max(0) as u64)
+        .sum();
+    let mut metadata = BTreeMap::new();
+
+    metadata.insert(
+        "opset".to_string(),
+        model
+            .opset_import
+            .iter()
+            .map(|opset| match opset.domain.as_str() {
+                "" => format!("ai.onnx={}", opset.version),
+                domain => format!("{}={}", domain, opset.version),
+            })
+            .collect::<Vec<String>>()
+            .join(", "),
+    );
+    metadata.insert("ir_version".to_string(), model.ir_version.to_string());
+
+    if !model.producer_name.is_empty() {
+        metadata.insert(
+            "producer".to_string(),
+            format!("{} {}", model.producer_name, model.producer_version)
+                .trim()
+                .to_string(),
+        );
+    }
+
+    if !graph.name.is_empty() {
+        metadata.insert("graph".to_string(), graph.name.clone());
+    }
+
+    for entry in model.metadata_props {
+        metadata.insert(entry.key, entry.value);
+    }
+
+    Ok(ArtifactInfo {
+        platform: PLATFORM.to_string(),
+        inputs: graph
+            .input
+            .iter()
+            .filter(|input| !initializers.contains(input.name.as_str()))
+            .map(tensor_spec)
+            .collect(),
+        outputs: graph.output.iter().map(tensor_spec).collect(),
+        parameter_count: Some(parameter_count),
+        metadata,
+    })
+}
+
+fn tensor_spec(value: &ValueInfoProto) -> TensorSpec {
+    let tensor_type = value
+        .r#type
+        .as_ref()
+        .and_then(|value_type| value_type.tensor_type.as_ref());
+
+    TensorSpec {
+        name: value.name.clone(),
+        datatype: tensor_type
+            .map(|tensor_type| onnx_type_to_kserve(tensor_type.elem_type))
+            .unwrap_or("UNKNOWN")
+            .to_string(),
+        shape: tensor_type
+            .and_then(|tensor_type| tensor_type.shape.as_ref())
+            .map(|shape| {
+                shape
+                    .dim
+                    .iter()
+                    .map(|dim| dim.dim_value.unwrap_or(-1))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+fn onnx_type_to_kserve(elem_type: i32) -> &'static str {
+    match elem_type {
+        1 => "FP32",
+        2 => "UINT8",
+        3 => "INT8",
+        4 => "UINT16",
+        5 => "INT16",
+        6 => "INT32",
+        7 => "INT64",
+        8 => "BYTES",
+        9 => "BOOL",
+        10 => "FP16",
+        11 => "FP64",
+        12 => "UINT32",
+        13 => "UINT64",
+        16 => "BF16",
+        _ => "UNKNOWN",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::*;
+
+    fn value_info(name: &str, elem_type: i32, dims: &[Option<i64>]) -> ValueInfoProto {
+        ValueInfoProto {
+            name: name.to_string(),
+            r#type: Some(TypeProto {
+                tensor_type: Some(TypeProtoTensor {
+                    elem_type,
+                    shape: Some(TensorShapeProto {
+                        dim: dims
+                            .iter()
+                            .map(|dim| Dimension {
+                                dim_value: *dim,
+                                dim_param: dim.is_none().then(|| "batch".to_string()),
+                            })
+                            .collect(),
+                    }),
+                }),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_decode_signature() {
+        let model = ModelProto {
+            ir_version: 8,
+            producer_name: "pytorch".to_string(),
+            producer_version: "2.1.0".to_string(),
+            opset_import: vec![OperatorSetIdProto {
+                domain: "".to_string(),
+                version: 17,
+            }],
+            graph: Some(GraphProto {
+                name: "main_graph".to_string(),
+                initializer: vec![TensorProto {
+                    dims: vec![10, 4],
+                    data_type: 1,
+                    name: "fc.weight".to_string(),
+                }],
+                input: vec![
+                    value_info("input", 1, &[None, Some(4)]),
+                    value_info("fc.weight", 1, &[Some(10), Some(4)]),
+                ],
+                output: vec![value_info("logits", 1, &[None, Some(10)])],
+            }),
+            ..Default::default()
+        };
+        let info = decode(&model.encode_to_vec()).unwrap();
+
+        assert_eq!(
+            vec![TensorSpec {
+                name: "input".to_string(),
+                datatype: "FP32".to_string(),
+                shape: vec![-1, 4],
+            }],
+            info.inputs
+        );
+        assert_eq!(vec![-1, 10], info.outputs[0].shape);
+        assert_eq!(Some(40), info.parameter_count);
+        assert_eq!("ai.onnx=17", info.metadata["opset"]);
+        assert_eq!("pytorch 2.1.0", info.metadata["producer"]);
+    }
+
+    #[test]
+    fn test_rejects_non_onnx_file() {
+        assert!(decode(b"definitely not protobuf \xff\xff").is_err());
+    }
+}
@@ -1,6 +1,11 @@
 use ferrix_model_api::Model;
 use ort::*;
 
+mod inspect;
+mod proto;
+
+pub use inspect::{inspect, PLATFORM};
+
 struct OnnxModel;
 
 impl OnnxModel {
@@ -14,6 +19,11 @@ impl Model for OnnxModel {
         todo!()
     }
 
+    fn unload(&mut self) -> ferrix_model_api::ModelResult<()> {
+        // Sessions are built per prediction, so nothing outlives a request.
+        Ok(())
+    }
+
     fn loaded(&self) -> bool {
         todo!()
     }
@@ -1,10 +1,9 @@
-use std::collections::HashMap;
-use std::sync::atomic::Ordering::*;
+use std::collections::{BTreeMap, HashMap};
 use std::vec;
 
 use anyhow::bail;
-use atomic_option::AtomicOption;
 use ferrix_model_api::internal::*;
+use ferrix_model_api::ArtifactInfo;
 use ferrix_model_api::Model;
 use ferrix_model_api::ModelConfig;
 use ferrix_model_api::ModelError;
@@ -13,15 +12,17 @@ use tch::CModule;
 use tch::Kind;
 use tch::Tensor as PyTorchTensor;
 
+pub const PLATFORM: &str = ferrix_model_api::platform::PYTORCH;
+
 pub struct PyTorchModel {
-    module: AtomicOption<CModule>,
+    module: Option<CModule>,
     model_config: ModelConfig,
 }
 
 impl PyTorchModel {
     pub fn new(config: ferrix_model_api::ModelConfig) -> Self {
         PyTorchModel {
-            module: AtomicOption::empty(),
+            module: None,
             model_config: config,
         }
     }
@@ -31,23 +32,32 @@ impl Model for PyTorchModel {
     fn load(&mut self) -> ModelResult<()> {
         let file_name = self.model_config.base_path.to_string();
 
-        println!("Loading file {}", file_name);
+        tracing::info!(path = %file_name, "Loading TorchScript module");
         let result = tch::CModule::load(self.model_config.base_path.to_string());
         let model = match result {
             Ok(module) => module,
             Err(error) => bail!(ModelError::Load(error.to_string())),
         };
 
-        self.module.replace(Some(Box::new(model)), SeqCst);
+        self.module = Some(model);
 
         return Ok(());
     }
 
+    fn unload(&mut self) -> ModelResult<()> {
+        self.module = None;
+
+        Ok(())
+    }
+
     fn loaded(&self) -> bool {
-        return self.module.take(SeqCst).is_some();
+        self.module.is_some()
     }
 
     fn predict(&self, request: &InferRequest) -> ModelResult<InferResponse> {
+        let Some(model) = &self.module else {
+            bail!(ModelError::Prediction("model is not loaded".to_string()));
+        };
         let pt_tensors: Vec<PyTorchTensor> = request
             .inputs
             .iter()
@@ -59,8 +69,7 @@ impl Model for PyTorchModel {
                 PyTorchTensor::from_data_size(bytes, &shape, datatype)
             })
             .collect::<Vec<PyTorchTensor>>();
-        let model = self.module.take(SeqCst).unwrap();
-        let output_tensor = pt_tensors[0].apply(model.as_ref());
+        let output_tensor = pt_tensors[0].apply(model);
         let numel = output_tensor.numel();
         let output_bytes = &mut vec![0u8; numel * output_tensor.kind().elt_size_in_bytes()][..];
         let datatype = Self::pt_type_to_kserve(output_tensor.kind());
@@ -78,12 +87,51 @@ impl Model for PyTorchModel {
                 datatype: datatype.to_string(),
                 shape,
                 parameters: HashMap::new(),
-                data: TensorData::from_bytes(datatype, output_bytes),
+                data: TensorData::from_bytes(datatype, output_bytes)?,
             }],
         })
     }
 }
 
+/// Loads a TorchScript module to count its parameters. TorchScript doesn't
+/// record input or output shapes, so the signature is left empty.
+pub fn inspect(path: &str) -> ModelResult<ArtifactInfo> {
+    let module = CModule::load(path).map_err(|error| ModelError::Load(error.to_string()))?;
+    let parameters = module
+        .named_parameters()
+        .map_err(|error| ModelError::Load(error.to_string()))?;
+    let mut dtypes: BTreeMap<String, usize> = BTreeMap::new();
+
+    for (_, tensor) in &parameters {
+        *dtypes.entry(format!("{:?}", tensor.kind())).or_default() += 1;
+    }
+
+    let mut metadata = BTreeMap::new();
+
+    metadata.insert("tensors".to_string(), parameters.len().to_string());
+    metadata.insert(
+        "dtypes".to_string(),
+        dtypes
+            .iter()
+            .map(|(dtype, count)| format!("{}={}", dtype, count))
+            .collect::<Vec<String>>()
+            .join(", "),
+    );
+
+    Ok(ArtifactInfo {
+        platform: PLATFORM.to_string(),
+        inputs: vec![],
+        outputs: vec![],
+        parameter_count: Some(
+            parameters
+                .iter()
+                .map(|(_, tensor)| tensor.numel() as u64)
+                .sum(),
+        ),
+        metadata,
+    })
+}
+
 impl PyTorchModel {
     fn kserve_type_to_pt(datatype: String) -> Kind {
         match datatype.as_str() {
@@ -140,8 +188,15 @@ mod tests {
         let mut model = PyTorchModel::new(ModelConfig {
             model_name: String::from(""),
             base_path: saved_model_filename,
+            sha256: None,
+            platform: None,
+            inputs: vec![],
+            outputs: vec![],
             extended_config: None,
+            python: None,
         });
+        assert!(!model.loaded());
+
         let load_result = model.load();
 
         match load_result {
@@ -149,6 +204,9 @@ mod tests {
             Err(error) => assert_eq!("", error.to_string()),
         }
 
+        assert!(model.loaded());
+        assert!(model.loaded());
+
         let cat_image = format!("{}/cat.jpeg", resource_dir);
         let image_tensor = imagenet::load_image_and_resize224(cat_image).unwrap();
         let numel = image_tensor.numel();
@@ -175,11 +233,18 @@ mod tests {
                 data: tensor_data,
             }],
         };
-        let result = model.predict(&request);
+        for _ in 0..2 {
+            let result = model.predict(&request);
 
-        match result {
-            Ok(response) => assert!(response.outputs.first().is_some()),
-            Err(error) => assert_eq!("", error.to_string()),
+            match result {
+                Ok(response) => assert!(response.outputs.first().is_some()),
+                Err(error) => assert_eq!("", error.to_string()),
+            }
         }
+
+        assert!(model.loaded());
+        model.unload().unwrap();
+        assert!(!model.loaded());
+        assert!(model.predict(&request).is_err());
     }
 }
@@ -0,0 +1,86 @@
+use std::sync::Mutex;
+
+use once_cell::sync::OnceCell;
+use pyo3::types::{PyCFunction, PyDict, PyTuple};
+use pyo3::{IntoPy, PyAny, PyObject, PyResult, Python};
+use tokio::sync::oneshot;
+
+/// Event loop that runs every coroutine returned by an `async def` hook. It
+/// lives on its own thread so hooks awaiting I/O never occupy a Tokio worker.
+static EVENT_LOOP: OnceCell<PyObject> = OnceCell::new();
+
+fn event_loop<'py>(py: Python<'py>) -> PyResult<&'py PyAny> {
+    let event_loop = EVENT_LOOP.get_or_try_init(|| -> PyResult<PyObject> {
+        let event_loop: PyObject = py
+            .import("asyncio")?
+            .call_method0("new_event_loop")?
+            .into_py(py);
+        let runner = event_loop.clone_ref(py);
+
+        std::thread::Builder::new()
+            .name("ferrix-asyncio".to_string())
+            .spawn(move || {
+                Python::with_gil(|py| {
+                    if let Err(error) = runner.call_method0(py, "run_forever") {
+                        error.print(py);
+                    }
+                })
+            })
+            .map_err(|error| pyo3::exceptions::PyRuntimeError::new_err(error.to_string()))?;
+
+        Ok(event_loop)
+    })?;
+
+    Ok(event_loop.as_ref(py))
+}
+
+pub(crate) fn is_coroutine_function(function: &PyAny) -> bool {
+    function
+        .py()
+        .import("inspect")
+        .and_then(|inspect| inspect.call_method1("iscoroutinefunction", (function,)))
+        .and_then(|result| result.extract())
+        .unwrap_or(false)
+}
+
+fn schedule<'py>(py: Python<'py>, coroutine: &PyAny) -> PyResult<&'py PyAny> {
+    py.import("asyncio")?
+        .call_method1("run_coroutine_threadsafe", (coroutine, event_loop(py)?))
+}
+
+/// Schedules `coroutine` on the hook event loop and returns a receiver that
+/// resolves with its result, so the caller can await it without the GIL.
+pub(crate) fn spawn(
+    py: Python,
+    coroutine: &PyAny,
+) -> PyResult<oneshot::Receiver<PyResult<PyObject>>> {
+    let (sender, receiver) = oneshot::channel();
+    let sender = Mutex::new(Some(sender));
+    let callback = PyCFunction::new_closure(
+        py,
+        None,
+        None,
+        move |args: &PyTuple, _: Option<&PyDict>| -> PyResult<()> {
+            let future = args.get_item(0)?;
+            let result = future
+                .call_method0("result")
+                .map(|result| result.into_py(future.py()));
+
+            if let Some(sender) = sender.lock().unwrap().take() {
+                let _ = sender.send(result);
+            }
+
+            Ok(())
+        },
+    )?;
+
+    schedule(py, coroutine)?.call_method1("add_done_callback", (callback,))?;
+
+    Ok(receiver)
+}
+
+/// Runs `coroutine` to completion from synchronous code. Waiting on the
+/// concurrent future releases the GIL so the event loop thread can progress.
+pub(crate) fn block_on(py: Python, coroutine: &PyAny) -> PyResult<PyObject> {
+    Ok(schedule(py, coroutine)?.call_method0("result")?.into_py(py))
+}
@@ -0,0 +1,143 @@
+use pyo3::exceptions::PySyntaxError;
+use pyo3::types::PyList;
+use pyo3::{PyErr, Python};
+use thiserror::Error;
+
+use crate::interpreter::InterpreterReport;
+
+#[derive(Error, Debug)]
+pub enum HookError {
+    #[error("failed to load handler {}: {}", location(path, line), exception.message)]
+    Load {
+        path: String,
+        line: Option<usize>,
+        exception: PythonException,
+    },
+    #[error("{stage} hook raised {}: {}", exception.kind, exception.message)]
+    Raised {
+        stage: &'static str,
+        exception: PythonException,
+    },
+    #[error("{stage} hook returned an invalid value: {}", exception.message)]
+    InvalidReturn {
+        stage: &'static str,
+        exception: PythonException,
+    },
+    #[error("python interpreter misconfigured: {0}")]
+    Interpreter(String),
+    #[error("handler {path} imports modules that can't be found: {}; {report}", modules.join(", "))]
+    MissingImports {
+        path: String,
+        modules: Vec<String>,
+        report: InterpreterReport,
+    },
+}
+
+impl HookError {
+    pub fn traceback(&self) -> Option<&str> {
+        match self {
+            HookError::Load { exception, .. } => Some(&exception.traceback),
+            HookError::Raised { exception, .. } => Some(&exception.traceback),
+            HookError::InvalidReturn { exception, .. } => Some(&exception.traceback),
+            HookError::Interpreter(_) | HookError::MissingImports { .. } => None,
+        }
+    }
+
+    /// The error message followed by the Python traceback, if there is one.
+    pub fn describe(&self) -> String {
+        match self.traceback() {
+            Some(traceback) => format!("{}\n{}", self, traceback),
+            None => self.to_string(),
+        }
+    }
+
+    pub(crate) fn load(py: Python<'_>, path: &str, error: PyErr) -> Self {
+        let line = handler_line(py, path, &error);
+
+        HookError::Load {
+            path: path.to_string(),
+            line,
+            exception: PythonException::capture(py, error),
+        }
+    }
+}
+
+/// A Python exception flattened into strings so it can leave the GIL.
+#[derive(Debug, Clone)]
+pub struct PythonException {
+    pub kind: String,
+    pub message: String,
+    pub traceback: String,
+}
+
+impl PythonException {
+    pub fn capture(py: Python<'_>, error: PyErr) -> Self {
+        let kind = error
+            .get_type(py)
+            .name()
+            .map(|name| name.to_string())
+            .unwrap_or_else(|_| "Exception".to_string());
+        let message = error.value(py).to_string();
+        let traceback =
+            format_traceback(py, &error).unwrap_or_else(|| format!("{}: {}", kind, message));
+
+        PythonException {
+            kind,
+            message,
+            traceback,
+        }
+    }
+}
+
+fn format_traceback(py: Python<'_>, error: &PyErr) -> Option<String> {
+    let lines = py
+        .import("traceback")
+        .and_then(|module| {
+            module.call_method1(
+                "format_exception",
+                (error.get_type(py), error.value(py), error.traceback(py)),
+            )
+        })
+        .ok()?;
+    let lines: &PyList = lines.downcast().ok()?;
+
+    Some(
+        lines
+            .iter()
+            .map(|line| line.to_string())
+            .collect::<String>(),
+    )
+}
+
+/// Finds the line in the handler file that raised, either from the `SyntaxError`
+/// itself or from the innermost traceback frame that belongs to the handler.
+fn handler_line(py: Python<'_>, path: &str, error: &PyErr) -> Option<usize> {
+    if error.is_instance_of::<PySyntaxError>(py) {
+        return error.value(py).getattr("lineno").ok()?.extract().ok();
+    }
+
+    let frames = py
+        .import("traceback")
+        .and_then(|module| module.call_method1("extract_tb", (error.traceback(py),)))
+        .ok()?;
+    let frames: &PyList = frames.downcast().ok()?;
+
+    frames
+        .iter()
+        .filter(|frame| {
+            frame
+                .getattr("filename")
+                .and_then(|filename| filename.extract::<String>())
+                .map(|filename| filename == path)
+                .unwrap_or(false)
+        })
+        .last()
+        .and_then(|frame| frame.getattr("lineno").ok()?.extract().ok())
+}
+
+fn location(path: &str, line: &Option<usize>) -> String {
+    match line {
+        Some(line) => format!("{}:{}", path, line),
+        None => path.to_string(),
+    }
+}
@@ -0,0 +1,288 @@
+use std::ffi::{CStr, CString};
+use std::fmt::{Display, Formatter};
+use std::mem::MaybeUninit;
+use std::path::{Path, PathBuf};
+
+use ferrix_model_api::PythonConfig;
+use pyo3::types::{PyList, PyModule};
+use pyo3::{ffi, PyResult, Python};
+
+use crate::error::{HookError, PythonException};
+use crate::{init, initialized};
+
+const IMPORT_CHECK: &str = r#"
+import ast
+import importlib.util
+
+def missing_imports(source, filename):
+    try:
+        tree = ast.parse(source, filename)
+    except SyntaxError:
+        return []
+
+    names = []
+
+    for node in tree.body:
+        if isinstance(node, ast.Import):
+            names.extend(alias.name for alias in node.names)
+        elif isinstance(node, ast.ImportFrom) and node.level == 0 and node.module:
+            names.append(node.module)
+
+    missing = []
+
+    for root in dict.fromkeys(name.split(".")[0] for name in names):
+        try:
+            if importlib.util.find_spec(root) is None:
+                missing.append(root)
+        except (ImportError, ValueError):
+            missing.append(root)
+
+    return missing
+"#;
+
+/// What the embedded interpreter looks like once configured.
+#[derive(Debug, Clone)]
+pub struct InterpreterReport {
+    pub version: String,
+    pub prefix: String,
+    pub sys_path: Vec<String>,
+    pub missing_imports: Vec<String>,
+}
+
+impl Display for InterpreterReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Python {} (prefix {}), sys.path: [{}]",
+            self.version,
+            self.prefix,
+            self.sys_path.join(", ")
+        )
+    }
+}
+
+/// Points the embedded interpreter at the configured virtualenv and extra
+/// `sys.path` entries, starting it with `python_home` if it isn't running yet.
+/// Called for every model that loads, so entries already on `sys.path` are
+/// left where they are.
+pub fn configure(config: &PythonConfig) -> Result<(), HookError> {
+    if let Some(python_home) = &config.python_home {
+        if initialized() {
+            tracing::warn!(
+                python_home = %python_home,
+                "Python is already running, ignoring python_home"
+            );
+        }
+    }
+
+    init(config.python_home.as_deref())?;
+
+    Python::with_gil(|py| {
+        let sys_path: &PyList = py.import("sys")?.getattr("path")?.downcast()?;
+
+        for entry in config.sys_path.iter().rev() {
+            if !sys_path.contains(entry)? {
+                sys_path.insert(0, entry)?;
+            }
+        }
+
+        if let Some(virtualenv) = &config.virtualenv {
+            let site_packages = site_packages(py, Path::new(virtualenv))?
+                .display()
+                .to_string();
+
+            if sys_path.contains(&site_packages)? {
+                return Ok(());
+            }
+
+            sys_path.insert(0, &site_packages)?;
+            // Processes .pth files; the directory itself is already on sys.path.
+            py.import("site")?
+                .call_method1("addsitedir", (site_packages,))?;
+        }
+
+        Ok(())
+    })
+    .map_err(|error: pyo3::PyErr| {
+        Python::with_gil(|py| HookError::Interpreter(PythonException::capture(py, error).message))
+    })
+}
+
+/// Reports the interpreter in use and fails if any top-level import of the
+/// handler can't be resolved, so missing packages surface at startup rather
+/// than as an `ImportError` traceback.
+pub fn validate(code: &str, path: &str) -> Result<InterpreterReport, HookError> {
+    init(None)?;
+
+    let report = Python::with_gil(|py| -> PyResult<InterpreterReport> {
+        let sys = py.import("sys")?;
+        let check = PyModule::from_code(
+            py,
+            IMPORT_CHECK,
+            "ferrix_import_check.py",
+            "ferrix_import_check",
+        )?;
+
+        Ok(InterpreterReport {
+            version: py
+                .version()
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            prefix: sys.getattr("prefix")?.extract()?,
+            sys_path: sys.getattr("path")?.extract()?,
+            missing_imports: check
+                .getattr("missing_imports")?
+                .call1((code, path))?
+                .extract()?,
+        })
+    })
+    .map_err(|error| {
+        Python::with_gil(|py| HookError::Interpreter(PythonException::capture(py, error).message))
+    })?;
+
+    if report.missing_imports.is_empty() {
+        Ok(report)
+    } else {
+        Err(HookError::MissingImports {
+            path: path.to_string(),
+            modules: report.missing_imports.clone(),
+            report,
+        })
+    }
+}
+
+fn site_packages(py: Python, virtualenv: &Path) -> PyResult<PathBuf> {
+    let version_info = py.version_info();
+    let candidates = [
+        virtualenv
+            .join("lib")
+            .join(format!(
+                "python{}.{}",
+                version_info.major, version_info.minor
+            ))
+            .join("site-packages"),
+        virtualenv.join("Lib").join("site-packages"),
+    ];
+
+    if let Some(found) = candidates.iter().find(|candidate| candidate.is_dir()) {
+        return Ok(found.clone());
+    }
+
+    let available = std::fs::read_dir(virtualenv.join("lib"))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|name| name.starts_with("python"))
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+
+    Err(pyo3::exceptions::PyFileNotFoundError::new_err(format!(
+        "virtualenv {} has no site-packages for Python {}.{} (found: [{}])",
+        virtualenv.display(),
+        version_info.major,
+        version_info.minor,
+        available.join(", ")
+    )))
+}
+
+/// Initializes the interpreter with `home` as its `PYTHONHOME`. The home is
+/// passed in the interpreter's config rather than the environment, since
+/// setting environment variables is unsound once other threads, such as the
+/// Tokio workers, are running.
+pub(crate) fn start(home: &str) -> Result<(), String> {
+    let home = CString::new(home).map_err(|_| format!("invalid python_home {:?}", home))?;
+
+    unsafe {
+        let mut config = MaybeUninit::<ffi::PyConfig>::uninit();
+
+        ffi::PyConfig_InitPythonConfig(config.as_mut_ptr());
+
+        let mut config = config.assume_init();
+        // Like `Py_InitializeEx(0)`: the server handles signals itself.
+        config.install_signal_handlers = 0;
+
+        let mut status = ffi::PyConfig_SetBytesString(&mut config, &mut config.home, home.as_ptr());
+
+        if ffi::PyStatus_Exception(status) == 0 {
+            status = ffi::Py_InitializeFromConfig(&config);
+        }
+
+        ffi::PyConfig_Clear(&mut config);
+
+        if ffi::PyStatus_Exception(status) != 0 {
+            let message = match status.err_msg.is_null() {
+                true => "unknown error".into(),
+                false => CStr::from_ptr(status.err_msg).to_string_lossy(),
+            };
+
+            return Err(format!(
+                "failed to start Python with python_home {}: {}",
+                home.to_string_lossy(),
+                message
+            ));
+        }
+
+        // Release the GIL, as `prepare_freethreaded_python` would have.
+        ffi::PyEval_SaveThread();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_reports_missing_imports() {
+        let code = "import ferrix\nimport os.path\nfrom definitely_not_installed import thing\n\ndef later():\n    import also_missing_but_lazy\n";
+        let error = validate(code, "handler.py").unwrap_err();
+
+        match error {
+            HookError::MissingImports {
+                modules, report, ..
+            } => {
+                assert_eq!(vec!["definitely_not_installed".to_string()], modules);
+                assert!(!report.version.is_empty());
+            }
+            other => panic!("unexpected error {}", other),
+        }
+    }
+
+    #[test]
+    fn test_configure_prepends_sys_path() {
+        let config = PythonConfig {
+            sys_path: vec!["/opt/ferrix/handlers".to_string()],
+            ..PythonConfig::default()
+        };
+
+        configure(&config).unwrap();
+        configure(&config).unwrap();
+
+        let report = validate("import ferrix\n", "handler.py").unwrap();
+
+        assert_eq!(
+            1,
+            report
+                .sys_path
+                .iter()
+                .filter(|entry| *entry == "/opt/ferrix/handlers")
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_configure_rejects_virtualenv_without_site_packages() {
+        let config = PythonConfig {
+            virtualenv: Some("/nonexistent/venv".to_string()),
+            ..PythonConfig::default()
+        };
+        let error = configure(&config).unwrap_err();
+
+        assert!(error.to_string().contains("/nonexistent/venv"));
+    }
+}
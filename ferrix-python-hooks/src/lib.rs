@@ -1,14 +1,33 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use error::{HookError, PythonException};
 use ferrix_model_api::internal::*;
 use ferrix_model_api::python::{
     PyInferInput, PyInferOutput, PyInferRequest, PyInferResponse, PyParameter,
 };
-use once_cell::sync::OnceCell;
-use pyo3::types::{PyDict, PyModule, PyTuple};
-use pyo3::ToPyObject;
-use pyo3::{pymodule, wrap_pyfunction, Py, PyAny, PyResult, Python};
+use ferrix_model_api::ModelConfig;
+use once_cell::sync::{Lazy, OnceCell};
+use pyo3::types::{PyDict, PyList, PyModule, PyTuple};
+use pyo3::{pymodule, wrap_pyfunction, Py, PyAny, PyObject, PyResult, Python};
+use pyo3::{IntoPy, ToPyObject};
+use toml::Value;
+
+mod asyncio;
+pub mod error;
+pub mod interpreter;
+
+/// Whether the interpreter started, or why it couldn't.
+static STARTED: OnceCell<Result<(), String>> = OnceCell::new();
 
-static PREPROCESSOR: OnceCell<Py<PyAny>> = OnceCell::new();
-static POSTPROCESSOR: OnceCell<Py<PyAny>> = OnceCell::new();
+/// Hooks registered by a handler that is still being evaluated. They only
+/// become a [`Handler`]'s once the whole handler module has imported
+/// successfully.
+static PENDING: Lazy<Mutex<Hooks>> = Lazy::new(|| Mutex::new(Hooks::default()));
+/// Serializes handler evaluation so two reloads can't interleave registrations.
+static LOADING: Mutex<()> = Mutex::new(());
+
+const HANDLER_MODULE: &str = "handler";
 
 const CODE: &str = "import ferrix
 
@@ -21,20 +40,80 @@ def test_out(infer_output: ferrix.InferResponse) -> ferrix.InferResponse:
     return infer_output
 ";
 
+#[derive(Default)]
+struct Hooks {
+    preprocessor: Option<Hook>,
+    postprocessor: Option<Hook>,
+    setup: Option<Hook>,
+    teardown: Option<Hook>,
+    /// Object handed to every hook that asks for it, populated by `setup`.
+    state: Option<PyObject>,
+}
+
+struct Hook {
+    function: Py<PyAny>,
+    /// Number of positional arguments the function accepts, so handlers that
+    /// don't take the state object keep working.
+    arity: usize,
+    /// `async def` hooks return a coroutine that runs on the hook event loop.
+    is_async: bool,
+}
+
+impl Hook {
+    fn new(py: Python, function: &Py<PyAny>) -> Self {
+        Hook {
+            function: function.clone_ref(py),
+            arity: positional_arity(function.as_ref(py)).unwrap_or(1),
+            is_async: asyncio::is_coroutine_function(function.as_ref(py)),
+        }
+    }
+}
+
 #[pyo3::pyfunction]
-fn preprocessor(function: Py<PyAny>) {
-    let _ = PREPROCESSOR.set(function);
+fn preprocessor(py: Python, function: Py<PyAny>) -> Py<PyAny> {
+    PENDING.lock().unwrap().preprocessor = Some(Hook::new(py, &function));
+
+    function
+}
+
+#[pyo3::pyfunction]
+fn postprocessor(py: Python, function: Py<PyAny>) -> Py<PyAny> {
+    PENDING.lock().unwrap().postprocessor = Some(Hook::new(py, &function));
+
+    function
 }
 
 #[pyo3::pyfunction]
-fn postprocessor(function: Py<PyAny>) {
-    let _ = POSTPROCESSOR.set(function);
+fn setup(py: Python, function: Py<PyAny>) -> Py<PyAny> {
+    PENDING.lock().unwrap().setup = Some(Hook::new(py, &function));
+
+    function
+}
+
+#[pyo3::pyfunction]
+fn teardown(py: Python, function: Py<PyAny>) -> Py<PyAny> {
+    PENDING.lock().unwrap().teardown = Some(Hook::new(py, &function));
+
+    function
 }
 
+/// The `ferrix` module seen by handlers running inside the server. It is
+/// registered under another name so its init symbol doesn't clash with the
+/// `ferrix` wheel, which links this crate too, and aliased on startup.
 #[pymodule]
-fn ferrix(_py: Python, module: &PyModule) -> PyResult<()> {
+fn _ferrix(py: Python, module: &PyModule) -> PyResult<()> {
+    register_module(py, module)
+}
+
+/// Adds the hook decorators and request/response classes to `module`. Shared
+/// by the interpreter embedded in the server and the `ferrix` wheel, so
+/// handlers see the same API in both.
+pub fn register_module(py: Python, module: &PyModule) -> PyResult<()> {
     module.add_function(wrap_pyfunction!(preprocessor, module)?)?;
     module.add_function(wrap_pyfunction!(postprocessor, module)?)?;
+    module.add_function(wrap_pyfunction!(setup, module)?)?;
+    module.add_function(wrap_pyfunction!(teardown, module)?)?;
+    module.add("State", py_state_type(py)?)?;
     module.add_class::<PyInferRequest>()?;
     module.add_class::<PyInferResponse>()?;
     module.add_class::<PyInferInput>()?;
@@ -44,54 +123,377 @@ fn ferrix(_py: Python, module: &PyModule) -> PyResult<()> {
     Ok(())
 }
 
-pub fn eval(code: String) {
-    pyo3::append_to_inittab!(ferrix);
-    pyo3::prepare_freethreaded_python();
+/// Starts the interpreter, with `home` as its `PYTHONHOME`, unless it is
+/// already running. Only the first call's `home` is used.
+pub(crate) fn init(home: Option<&str>) -> Result<(), HookError> {
+    STARTED
+        .get_or_init(|| {
+            // Inside the `ferrix` wheel the interpreter is already running and
+            // `import ferrix` resolves to the wheel itself.
+            if initialized() {
+                return Ok(());
+            }
 
-    Python::with_gil(|py| {
-        println!("Python version: {}", py.version());
+            pyo3::append_to_inittab!(_ferrix);
 
-        py.run(&code, None, None).unwrap()
-    })
+            if let Some(home) = home {
+                interpreter::start(home)?;
+            }
+
+            pyo3::prepare_freethreaded_python();
+            Python::with_gil(|py| {
+                py.import("sys")?
+                    .getattr("modules")?
+                    .set_item("ferrix", py.import("_ferrix")?)
+            })
+            .expect("failed to register the embedded ferrix module");
+
+            Ok(())
+        })
+        .clone()
+        .map_err(HookError::Interpreter)
+}
+
+pub(crate) fn initialized() -> bool {
+    unsafe { pyo3::ffi::Py_IsInitialized() != 0 }
+}
+
+/// The hooks and `setup` state of one evaluation of a handler file, owned by
+/// the model serving them. Every model version evaluates the handler with its
+/// own config, so models never share state or see each other's hooks.
+pub struct Handler {
+    hooks: RwLock<Arc<Hooks>>,
+    /// Set by `shutdown`, after which reloads are ignored.
+    closed: AtomicBool,
+}
+
+impl Handler {
+    /// Evaluates handler code in a fresh module namespace and runs its `setup`
+    /// hook with `config`. `path` is only used to attribute tracebacks and
+    /// load errors to the handler file.
+    pub fn load(code: String, path: &str, config: &ModelConfig) -> Result<Self, HookError> {
+        let _loading = LOADING.lock().unwrap();
+
+        Ok(Handler {
+            hooks: RwLock::new(Arc::new(evaluate(code, path, config)?)),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    /// Evaluates the handler again and, if it imports cleanly and its `setup`
+    /// hook succeeds, atomically replaces the hooks before tearing the old ones
+    /// down. On failure the previous hooks keep serving.
+    pub fn reload(&self, code: String, path: &str, config: &ModelConfig) -> Result<(), HookError> {
+        let _loading = LOADING.lock().unwrap();
+
+        if self.closed.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let registered = evaluate(code, path, config)?;
+        let previous = std::mem::replace(&mut *self.hooks.write().unwrap(), Arc::new(registered));
+
+        if let Err(error) = Python::with_gil(|py| run_teardown(py, &previous)) {
+            tracing::error!("{}", error.describe());
+        }
+
+        Ok(())
+    }
+
+    /// Runs the `teardown` hook and unregisters all hooks.
+    pub fn shutdown(&self) -> Result<(), HookError> {
+        let _loading = LOADING.lock().unwrap();
+
+        self.closed.store(true, Ordering::SeqCst);
+
+        let previous = std::mem::take(&mut *self.hooks.write().unwrap());
+
+        Python::with_gil(|py| run_teardown(py, &previous))
+    }
+
+    pub async fn preprocess(&self, input: InferRequest) -> Result<InferRequest, HookError> {
+        let hooks = self.hooks();
+
+        match &hooks.preprocessor {
+            Some(hook) => call("preprocess", hook, &hooks.state, input).await,
+            None => Ok(input),
+        }
+    }
+
+    pub async fn postprocess(&self, input: InferResponse) -> Result<InferResponse, HookError> {
+        let hooks = self.hooks();
+
+        match &hooks.postprocessor {
+            Some(hook) => call("postprocess", hook, &hooks.state, input).await,
+            None => Ok(input),
+        }
+    }
+
+    fn hooks(&self) -> Arc<Hooks> {
+        self.hooks.read().unwrap().clone()
+    }
 }
 
-pub fn preprocess(input: InferRequest) -> PyResult<InferRequest> {
+/// Imports handler code and runs its `setup` hook against a new state object.
+/// Callers hold `LOADING`, so registrations from two handlers can't interleave.
+fn evaluate(code: String, path: &str, config: &ModelConfig) -> Result<Hooks, HookError> {
+    init(None)?;
+
     Python::with_gil(|py| {
-        let input = input.to_object(py);
-        let args = PyTuple::new(py, &[input]);
-        let response = PREPROCESSOR
-            .get()
-            .unwrap()
-            .call(py, args, Some(PyDict::new(py)))?;
+        *PENDING.lock().unwrap() = Hooks::default();
+
+        let result = forget_handler_module(py)
+            .and_then(|_| PyModule::from_code(py, &code, path, HANDLER_MODULE))
+            .map_err(|error| HookError::load(py, path, error));
+        let mut registered = std::mem::take(&mut *PENDING.lock().unwrap());
+
+        result?;
 
-        response.extract::<InferRequest>(py)
+        let state = new_state(py).map_err(|error| HookError::load(py, path, error))?;
+
+        if let Some(hook) = &registered.setup {
+            let config =
+                config_to_py(py, config).map_err(|error| HookError::load(py, path, error))?;
+
+            invoke_to_completion(py, "setup", hook, &[config, state.clone_ref(py)])?;
+        }
+
+        registered.state = Some(state);
+
+        Ok(registered)
     })
 }
 
-pub fn postprocess(input: InferResponse) -> PyResult<InferResponse> {
-    Python::with_gil(|py| {
-        let input = input.to_object(py);
-        let args = PyTuple::new(py, &[input]);
-        let response = PREPROCESSOR
-            .get()
-            .unwrap()
-            .call(py, args, Some(PyDict::new(py)))?;
+fn run_teardown(py: Python, hooks: &Hooks) -> Result<(), HookError> {
+    match (&hooks.teardown, &hooks.state) {
+        (Some(hook), Some(state)) => {
+            invoke_to_completion(py, "teardown", hook, &[state.clone_ref(py)]).map(|_| ())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Drops any previously imported handler from `sys.modules` so re-evaluating
+/// starts from an empty namespace instead of layering over the old globals.
+fn forget_handler_module(py: Python) -> PyResult<()> {
+    let modules: &PyDict = py.import("sys")?.getattr("modules")?.downcast()?;
+
+    if modules.contains(HANDLER_MODULE)? {
+        modules.del_item(HANDLER_MODULE)?;
+    }
+
+    Ok(())
+}
+
+enum Pending {
+    Returned(PyObject),
+    Scheduled(tokio::sync::oneshot::Receiver<PyResult<PyObject>>),
+}
 
-        response.extract::<InferResponse>(py)
+async fn call<T>(
+    stage: &'static str,
+    hook: &Hook,
+    state: &Option<PyObject>,
+    input: T,
+) -> Result<T, HookError>
+where
+    T: ToPyObject + for<'a> pyo3::FromPyObject<'a>,
+{
+    let pending = Python::with_gil(|py| {
+        let mut args = vec![input.to_object(py)];
+
+        if let Some(state) = state {
+            args.push(state.clone_ref(py));
+        }
+
+        let returned = invoke(py, stage, hook, &args)?;
+
+        match hook.is_async {
+            true => asyncio::spawn(py, returned.as_ref(py))
+                .map(Pending::Scheduled)
+                .map_err(|error| raised(py, stage, error)),
+            false => Ok(Pending::Returned(returned)),
+        }
+    })?;
+    let response = match pending {
+        Pending::Returned(returned) => Ok(returned),
+        Pending::Scheduled(receiver) => receiver.await.unwrap_or_else(|_| {
+            Err(pyo3::exceptions::PyRuntimeError::new_err(
+                "hook event loop stopped",
+            ))
+        }),
+    };
+
+    Python::with_gil(|py| {
+        response
+            .map_err(|error| raised(py, stage, error))?
+            .extract::<T>(py)
+            .map_err(|error| HookError::InvalidReturn {
+                stage,
+                exception: PythonException::capture(py, error),
+            })
     })
 }
 
+/// Like `invoke`, but also waits for `async def` hooks to finish.
+fn invoke_to_completion(
+    py: Python,
+    stage: &'static str,
+    hook: &Hook,
+    args: &[PyObject],
+) -> Result<PyObject, HookError> {
+    let returned = invoke(py, stage, hook, args)?;
+
+    match hook.is_async {
+        true => {
+            asyncio::block_on(py, returned.as_ref(py)).map_err(|error| raised(py, stage, error))
+        }
+        false => Ok(returned),
+    }
+}
+
+fn raised(py: Python, stage: &'static str, error: pyo3::PyErr) -> HookError {
+    HookError::Raised {
+        stage,
+        exception: PythonException::capture(py, error),
+    }
+}
+
+/// Calls a hook with as many of `args` as its signature accepts.
+fn invoke(
+    py: Python,
+    stage: &'static str,
+    hook: &Hook,
+    args: &[PyObject],
+) -> Result<PyObject, HookError> {
+    let args = PyTuple::new(py, args.iter().take(hook.arity));
+
+    hook.function
+        .call(py, args, Some(PyDict::new(py)))
+        .map_err(|error| raised(py, stage, error))
+}
+
+fn positional_arity(function: &PyAny) -> PyResult<usize> {
+    let py = function.py();
+    let inspect = py.import("inspect")?;
+    let kinds = inspect.getattr("Parameter")?;
+    let positional = [
+        kinds.getattr("POSITIONAL_ONLY")?,
+        kinds.getattr("POSITIONAL_OR_KEYWORD")?,
+    ];
+    let variadic = kinds.getattr("VAR_POSITIONAL")?;
+    let parameters = inspect
+        .call_method1("signature", (function,))?
+        .getattr("parameters")?
+        .call_method0("values")?;
+    let mut arity = 0;
+
+    for parameter in parameters.iter()? {
+        let kind = parameter?.getattr("kind")?;
+
+        if kind.eq(variadic)? {
+            return Ok(usize::MAX);
+        }
+
+        if kind.eq(positional[0])? || kind.eq(positional[1])? {
+            arity += 1;
+        }
+    }
+
+    Ok(arity)
+}
+
+fn py_state_type<'py>(py: Python<'py>) -> PyResult<&'py PyAny> {
+    py.import("types")?.getattr("SimpleNamespace")
+}
+
+fn new_state(py: Python) -> PyResult<PyObject> {
+    Ok(py_state_type(py)?.call0()?.into_py(py))
+}
+
+fn config_to_py(py: Python, config: &ModelConfig) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+    let extended_config = match &config.extended_config {
+        Some(value) => toml_to_py(py, value),
+        None => PyDict::new(py).into_py(py),
+    };
+
+    dict.set_item("model_name", &config.model_name)?;
+    dict.set_item("base_path", &config.base_path)?;
+    dict.set_item("extended_config", extended_config)?;
+
+    Ok(dict.into_py(py))
+}
+
+fn toml_to_py(py: Python, value: &Value) -> PyObject {
+    match value {
+        Value::String(string) => string.into_py(py),
+        Value::Integer(integer) => integer.into_py(py),
+        Value::Float(float) => float.into_py(py),
+        Value::Boolean(boolean) => boolean.into_py(py),
+        Value::Datetime(datetime) => datetime.to_string().into_py(py),
+        Value::Array(array) => {
+            PyList::new(py, array.iter().map(|item| toml_to_py(py, item))).into_py(py)
+        }
+        Value::Table(table) => {
+            let dict = PyDict::new(py);
+
+            for (key, item) in table {
+                let _ = dict.set_item(key, toml_to_py(py, item));
+            }
+
+            dict.into_py(py)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
     use super::*;
 
+    fn request(id: &str) -> InferRequest {
+        InferRequest {
+            model_name: "".to_string(),
+            model_version: "".to_string(),
+            id: id.to_string(),
+            parameters: HashMap::new(),
+            inputs: vec![],
+            outputs: vec![],
+            raw_input_contents: vec![],
+        }
+    }
+
+    fn run<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn config() -> ModelConfig {
+        toml::from_str(
+            r#"
+            model_name = "resnet"
+            base_path = "model.pt"
+
+            [extended_config]
+            labels = ["cat", "dog"]
+        "#,
+        )
+        .unwrap()
+    }
+
+    fn load(code: &str) -> Handler {
+        Handler::load(code.to_string(), "handler.py", &config()).unwrap()
+    }
+
     #[test]
     fn test() {
-        eval(CODE.to_string());
+        let handler = Handler::load(CODE.to_string(), "test.py", &config()).unwrap();
 
-        PREPROCESSOR.get().unwrap();
+        assert!(handler.hooks().preprocessor.is_some());
 
         let infer_request: InferRequest = InferRequest {
             model_name: "".to_string(),
@@ -119,8 +521,160 @@ mod tests {
             raw_input_contents: vec![vec![1_u8]],
         };
 
-        let response = preprocess(infer_request);
+        let response = run(handler.preprocess(infer_request));
 
         assert_eq!("1".to_string(), response.unwrap().id)
     }
+
+    #[test]
+    fn test_failed_reload_keeps_previous_hooks() {
+        let working = "import ferrix\n\n@ferrix.preprocessor\ndef pre(request):\n    request.id = 'v1'\n    return request\n";
+        let broken = "import ferrix\n\n@ferrix.preprocessor\ndef pre(request):\n    request.id = 'v2'\n    return request\n\nimport does_not_exist\n";
+        let handler = load(working);
+
+        assert!(handler
+            .reload(broken.to_string(), "handler.py", &config())
+            .is_err());
+
+        assert_eq!("v1", run(handler.preprocess(request("0"))).unwrap().id);
+        assert!(handler.hooks().postprocessor.is_none());
+    }
+
+    #[test]
+    fn test_reload_starts_from_fresh_namespace() {
+        let first = "import ferrix\n\nSUFFIX = '-first'\n\n@ferrix.preprocessor\ndef pre(request):\n    request.id += SUFFIX\n    return request\n";
+        let second = "import ferrix\n\n@ferrix.preprocessor\ndef pre(request):\n    request.id += globals().get('SUFFIX', '-fresh')\n    return request\n";
+        let handler = load(first);
+
+        assert_eq!("0-first", run(handler.preprocess(request("0"))).unwrap().id);
+
+        handler
+            .reload(second.to_string(), "handler.py", &config())
+            .unwrap();
+        assert_eq!("0-fresh", run(handler.preprocess(request("0"))).unwrap().id);
+    }
+
+    #[test]
+    fn test_setup_state_reaches_hooks_and_teardown() {
+        let code = "import ferrix\nimport sys\n\n@ferrix.setup\ndef setup(config, state):\n    state.labels = config['extended_config']['labels']\n    state.name = config['model_name']\n\n@ferrix.teardown\ndef teardown(state):\n    sys.torn_down = state.name\n\n@ferrix.preprocessor\ndef pre(request, state):\n    request.id = state.name + ':' + ','.join(state.labels)\n    return request\n";
+        let handler = load(code);
+
+        assert_eq!(
+            "resnet:cat,dog",
+            run(handler.preprocess(request("0"))).unwrap().id
+        );
+
+        handler.shutdown().unwrap();
+
+        Python::with_gil(|py| {
+            let torn_down: String = py
+                .import("sys")
+                .unwrap()
+                .getattr("torn_down")
+                .unwrap()
+                .extract()
+                .unwrap();
+
+            assert_eq!("resnet", torn_down);
+        });
+        assert_eq!("0", run(handler.preprocess(request("0"))).unwrap().id);
+
+        // A reload racing the shutdown doesn't bring the hooks back.
+        handler
+            .reload(code.to_string(), "handler.py", &config())
+            .unwrap();
+        assert_eq!("0", run(handler.preprocess(request("0"))).unwrap().id);
+    }
+
+    #[test]
+    fn test_models_keep_separate_state() {
+        let code = "import ferrix\n\n@ferrix.setup\ndef setup(config, state):\n    state.name = config['model_name']\n\n@ferrix.preprocessor\ndef pre(request, state):\n    request.id = state.name\n    return request\n";
+        let resnet = load(code);
+        let bert = Handler::load(
+            code.to_string(),
+            "handler.py",
+            &ModelConfig {
+                model_name: "bert".to_string(),
+                ..config()
+            },
+        )
+        .unwrap();
+
+        assert_eq!("resnet", run(resnet.preprocess(request("0"))).unwrap().id);
+        assert_eq!("bert", run(bert.preprocess(request("0"))).unwrap().id);
+
+        bert.shutdown().unwrap();
+
+        assert_eq!("resnet", run(resnet.preprocess(request("0"))).unwrap().id);
+    }
+
+    #[test]
+    fn test_failed_setup_keeps_previous_hooks() {
+        let working = "import ferrix\n\n@ferrix.preprocessor\ndef pre(request):\n    request.id = 'v1'\n    return request\n";
+        let failing = "import ferrix\n\n@ferrix.setup\ndef setup(config):\n    raise RuntimeError('no tokenizer')\n\n@ferrix.preprocessor\ndef pre(request):\n    request.id = 'v2'\n    return request\n";
+        let handler = load(working);
+        let error = handler
+            .reload(failing.to_string(), "handler.py", &config())
+            .unwrap_err();
+
+        assert!(error.to_string().contains("setup hook raised RuntimeError"));
+        assert_eq!("v1", run(handler.preprocess(request("0"))).unwrap().id);
+    }
+
+    #[test]
+    fn test_async_hooks_run_on_event_loop() {
+        let code = "import asyncio\nimport ferrix\n\n@ferrix.setup\nasync def setup(config, state):\n    await asyncio.sleep(0)\n    state.prefix = 'async'\n\n@ferrix.preprocessor\nasync def pre(request, state):\n    await asyncio.sleep(0.01)\n    request.id = state.prefix + '-' + request.id\n    return request\n";
+        let handler = load(code);
+        let responses = run(async {
+            tokio::join!(
+                handler.preprocess(request("1")),
+                handler.preprocess(request("2"))
+            )
+        });
+
+        assert_eq!("async-1", responses.0.unwrap().id);
+        assert_eq!("async-2", responses.1.unwrap().id);
+    }
+
+    #[test]
+    fn test_async_hook_exception_is_captured() {
+        let code = "import ferrix\n\n@ferrix.preprocessor\nasync def pre(request):\n    raise KeyError('feature')\n";
+        let handler = load(code);
+        let error = run(handler.preprocess(request("1"))).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("preprocess hook raised KeyError"));
+    }
+
+    #[test]
+    fn test_load_error_reports_line() {
+        let code = "import ferrix\n\ndef broken(:\n    pass\n";
+        let error = Handler::load(code.to_string(), "broken.py", &config())
+            .err()
+            .unwrap();
+
+        match error {
+            HookError::Load { path, line, .. } => {
+                assert_eq!("broken.py", path);
+                assert_eq!(Some(3), line);
+            }
+            other => panic!("unexpected error {}", other),
+        }
+    }
+
+    #[test]
+    fn test_import_error_carries_traceback() {
+        let code = "x = 1\nraise ValueError('bad handler')\n";
+        let error = Handler::load(code.to_string(), "raises.py", &config())
+            .err()
+            .unwrap();
+
+        assert!(error.to_string().contains("raises.py:2"));
+        assert!(error.to_string().contains("bad handler"));
+        assert!(error
+            .traceback()
+            .unwrap()
+            .contains("ValueError: bad handler"));
+    }
 }
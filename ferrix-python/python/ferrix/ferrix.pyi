@@ -1,4 +1,4 @@
-from typing import Callable, Dict, Optional, List, Union
+from typing import Any, Awaitable, Callable, Dict, Optional, List, Union
 import numpy as np
 import pandas as pd
 import polars as pl
@@ -80,5 +80,58 @@ class InferOutput:
     def as_numpy(self) -> np.ndarray: ...
 
 
-def preprocessor(preprocessor_fn: Callable[[InferRequest], InferRequest]) -> Callable[[InferRequest], InferRequest]: ...
-def postprocessor(postprocessor_fn: Callable[[InferResponse], InferResponse]) -> Callable[[InferResponse], InferResponse]: ...
\ No newline at end of file
+class State:
+    """Attribute bag created for each handler load, filled by `setup` and
+    passed to hooks that accept a trailing `state` argument."""
+    def __getattr__(self, name: str) -> Any: ...
+    def __setattr__(self, name: str, value: Any) -> None: ...
+
+
+# Hooks may also be `async def`; coroutines run on a dedicated asyncio loop.
+Preprocessor = Union[
+    Callable[[InferRequest], Union[InferRequest, Awaitable[InferRequest]]],
+    Callable[[InferRequest, State], Union[InferRequest, Awaitable[InferRequest]]],
+]
+Postprocessor = Union[
+    Callable[[InferResponse], Union[InferResponse, Awaitable[InferResponse]]],
+    Callable[[InferResponse, State], Union[InferResponse, Awaitable[InferResponse]]],
+]
+Setup = Union[
+    Callable[[Dict[str, Any]], Union[None, Awaitable[None]]],
+    Callable[[Dict[str, Any], State], Union[None, Awaitable[None]]],
+]
+Teardown = Union[Callable[[], Union[None, Awaitable[None]]], Callable[[State], Union[None, Awaitable[None]]]]
+
+
+def preprocessor(preprocessor_fn: Preprocessor) -> Preprocessor: ...
+def postprocessor(postprocessor_fn: Postprocessor) -> Postprocessor: ...
+def setup(setup_fn: Setup) -> Setup: ...
+def teardown(teardown_fn: Teardown) -> Teardown: ...
+
+class InferenceServerError(Exception): ...
+
+
+class Client:
+    """Blocking KServe v2 gRPC client. Tensors travel as raw bytes by default."""
+    def __new__(cls, address: str, timeout: Optional[float] = None) -> Client: ...
+    def live(self) -> bool: ...
+    def ready(self, model: Optional[str] = None, version: str = "") -> bool: ...
+    def metadata(self, model: Optional[str] = None, version: str = "") -> Dict[str, Any]: ...
+    def infer(
+        self,
+        model: str,
+        inputs: Dict[str, np.ndarray],
+        outputs: Optional[List[str]] = None,
+        version: str = "",
+        id: str = "",
+        binary: bool = True,
+    ) -> Dict[str, np.ndarray]: ...
+
+
+# Backing functions for ferrix.testing
+class LoadedHandler: ...
+
+def _load_handler(path: str, model_name: str, base_path: str, extended_config: Optional[Dict[str, Any]]) -> LoadedHandler: ...
+def _preprocess(handler: LoadedHandler, request: InferRequest) -> InferRequest: ...
+def _postprocess(handler: LoadedHandler, response: InferResponse) -> InferResponse: ...
+def _unload_handler(handler: LoadedHandler) -> None: ...
@@ -0,0 +1,66 @@
+"""Run a handler's hooks locally, without a model server.
+
+    from ferrix import InferRequest, InferResponse
+    from ferrix.testing import Handler
+
+    with Handler.load("handler.py", extended_config={"labels": ["cat"]}) as handler:
+        request = handler.preprocess(InferRequest(...))
+        response = handler.run(request, model=lambda request: InferResponse(...))
+"""
+from typing import Any, Callable, Dict, Optional
+
+from .ferrix import (
+    InferRequest,
+    InferResponse,
+    LoadedHandler,
+    _load_handler,
+    _postprocess,
+    _preprocess,
+    _unload_handler,
+)
+
+
+class Handler:
+    """A loaded handler file. Each load has its own hooks and state, like
+    each model served by the server."""
+
+    def __init__(self, path: str, handler: LoadedHandler):
+        self.path = path
+        self._handler = handler
+
+    @classmethod
+    def load(
+        cls,
+        path: str,
+        model_name: str = "",
+        base_path: str = "",
+        extended_config: Optional[Dict[str, Any]] = None,
+    ) -> "Handler":
+        """Imports `path`, registering its hooks and calling its `setup` hook
+        with the given model config."""
+        return cls(path, _load_handler(path, model_name, base_path, extended_config))
+
+    def preprocess(self, request: InferRequest) -> InferRequest:
+        return _preprocess(self._handler, request)
+
+    def postprocess(self, response: InferResponse) -> InferResponse:
+        return _postprocess(self._handler, response)
+
+    def run(
+        self,
+        request: InferRequest,
+        model: Callable[[InferRequest], InferResponse],
+    ) -> InferResponse:
+        """Runs `request` through the preprocessor, `model` standing in for
+        the server's backend, and the postprocessor."""
+        return self.postprocess(model(self.preprocess(request)))
+
+    def close(self) -> None:
+        """Calls the handler's `teardown` hook and unregisters its hooks."""
+        _unload_handler(self._handler)
+
+    def __enter__(self) -> "Handler":
+        return self
+
+    def __exit__(self, *exc_info) -> None:
+        self.close()
@@ -0,0 +1,386 @@
+use std::time::Duration;
+
+use ferrix_protos::grpc_inference_service_client::GrpcInferenceServiceClient;
+use ferrix_protos::model_infer_request::{InferInputTensor, InferRequestedOutputTensor};
+use ferrix_protos::model_infer_response::InferOutputTensor;
+use ferrix_protos::model_metadata_response::TensorMetadata;
+use ferrix_protos::*;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
+use tokio::runtime::Runtime;
+use tonic::transport::{Channel, Endpoint};
+
+create_exception!(ferrix, InferenceServerError, PyException);
+
+/// numpy dtype names and the KServe datatypes they travel as.
+const DATATYPES: [(&str, &str); 13] = [
+    ("bool", "BOOL"),
+    ("uint8", "UINT8"),
+    ("uint16", "UINT16"),
+    ("uint32", "UINT32"),
+    ("uint64", "UINT64"),
+    ("int8", "INT8"),
+    ("int16", "INT16"),
+    ("int32", "INT32"),
+    ("int64", "INT64"),
+    ("float16", "FP16"),
+    ("float32", "FP32"),
+    ("float64", "FP64"),
+    ("object", "BYTES"),
+];
+
+pub fn register_module(py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PyClient>()?;
+    module.add(
+        "InferenceServerError",
+        py.get_type::<InferenceServerError>(),
+    )?;
+
+    Ok(())
+}
+
+/// Blocking client for a KServe v2 gRPC endpoint such as ferrix.
+#[pyclass(name = "Client")]
+pub struct PyClient {
+    runtime: Runtime,
+    client: GrpcInferenceServiceClient<Channel>,
+    timeout: Option<Duration>,
+}
+
+#[pymethods]
+impl PyClient {
+    #[new]
+    #[pyo3(signature = (address, timeout = None))]
+    pub fn new(address: &str, timeout: Option<f64>) -> PyResult<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let address = match address.contains("://") {
+            true => address.to_string(),
+            false => format!("http://{}", address),
+        };
+        let endpoint = Endpoint::from_shared(address)
+            .map_err(|error| PyValueError::new_err(error.to_string()))?;
+        let channel = runtime.block_on(async { endpoint.connect_lazy() });
+
+        Ok(PyClient {
+            runtime,
+            client: GrpcInferenceServiceClient::new(channel),
+            timeout: timeout.map(Duration::from_secs_f64),
+        })
+    }
+
+    pub fn live(&self, py: Python) -> PyResult<bool> {
+        let mut client = self.client.clone();
+        let request = self.request(ServerLiveRequest {});
+
+        self.call(py, async move { client.server_live(request).await })
+            .map(|response| response.live)
+    }
+
+    /// Whether the server, or `model` if given, is ready for inference.
+    #[pyo3(signature = (model = None, version = ""))]
+    pub fn ready(&self, py: Python, model: Option<&str>, version: &str) -> PyResult<bool> {
+        let mut client = self.client.clone();
+
+        match model {
+            Some(model) => {
+                let request = self.request(ModelReadyRequest {
+                    name: model.to_string(),
+                    version: version.to_string(),
+                });
+
+                self.call(py, async move { client.model_ready(request).await })
+                    .map(|response| response.ready)
+            }
+            None => {
+                let request = self.request(ServerReadyRequest {});
+
+                self.call(py, async move { client.server_ready(request).await })
+                    .map(|response| response.ready)
+            }
+        }
+    }
+
+    /// Server metadata, or the metadata of `model` if given, as a dict.
+    #[pyo3(signature = (model = None, version = ""))]
+    pub fn metadata(&self, py: Python, model: Option<&str>, version: &str) -> PyResult<PyObject> {
+        let mut client = self.client.clone();
+        let dict = PyDict::new(py);
+
+        match model {
+            Some(model) => {
+                let request = self.request(ModelMetadataRequest {
+                    name: model.to_string(),
+                    version: version.to_string(),
+                });
+                let response =
+                    self.call(py, async move { client.model_metadata(request).await })?;
+
+                dict.set_item("name", response.name)?;
+                dict.set_item("versions", response.versions)?;
+                dict.set_item("platform", response.platform)?;
+                dict.set_item("inputs", tensor_metadata(py, &response.inputs)?)?;
+                dict.set_item("outputs", tensor_metadata(py, &response.outputs)?)?;
+            }
+            None => {
+                let request = self.request(ServerMetadataRequest {});
+                let response =
+                    self.call(py, async move { client.server_metadata(request).await })?;
+
+                dict.set_item("name", response.name)?;
+                dict.set_item("version", response.version)?;
+                dict.set_item("extensions", response.extensions)?;
+            }
+        }
+
+        Ok(dict.into())
+    }
+
+    /// Runs `model` on `inputs`, a dict of input name to numpy array, and
+    /// returns a dict of output name to numpy array. Tensors are sent as raw
+    /// bytes unless `binary` is false.
+    #[pyo3(signature = (model, inputs, outputs = None, version = "", id = "", binary = true))]
+    pub fn infer(
+        &self,
+        py: Python,
+        model: &str,
+        inputs: &PyDict,
+        outputs: Option<Vec<String>>,
+        version: &str,
+        id: &str,
+        binary: bool,
+    ) -> PyResult<PyObject> {
+        let numpy = py.import("numpy")?;
+        let mut request = ModelInferRequest {
+            model_name: model.to_string(),
+            model_version: version.to_string(),
+            id: id.to_string(),
+            outputs: outputs
+                .unwrap_or_default()
+                .into_iter()
+                .map(|name| InferRequestedOutputTensor {
+                    name,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        for (name, array) in inputs.iter() {
+            let array = numpy.call_method1("ascontiguousarray", (array,))?;
+            let datatype = to_datatype(array.getattr("dtype")?.getattr("name")?.extract()?)?;
+            let mut tensor = InferInputTensor {
+                name: name.extract()?,
+                datatype: datatype.to_string(),
+                shape: array.getattr("shape")?.extract()?,
+                ..Default::default()
+            };
+
+            if binary {
+                request
+                    .raw_input_contents
+                    .push(raw_contents(array, datatype)?);
+            } else {
+                tensor.contents = Some(typed_contents(array, datatype)?);
+            }
+
+            request.inputs.push(tensor);
+        }
+
+        let mut client = self.client.clone();
+        let request = self.request(request);
+        let response = self.call(py, async move { client.model_infer(request).await })?;
+        let result = PyDict::new(py);
+
+        for (index, output) in response.outputs.iter().enumerate() {
+            let array = match response.raw_output_contents.get(index) {
+                Some(raw) => from_raw(py, output, raw)?,
+                None => from_typed(py, output)?,
+            };
+
+            result.set_item(&output.name, array)?;
+        }
+
+        Ok(result.into())
+    }
+
+    fn __repr__(&self) -> String {
+        "Client()".to_string()
+    }
+}
+
+impl PyClient {
+    fn request<T>(&self, message: T) -> tonic::Request<T> {
+        let mut request = tonic::Request::new(message);
+
+        if let Some(timeout) = self.timeout {
+            request.set_timeout(timeout);
+        }
+
+        request
+    }
+
+    /// Runs an RPC on the client's runtime with the GIL released.
+    fn call<T, F>(&self, py: Python, future: F) -> PyResult<T>
+    where
+        T: Send,
+        F: std::future::Future<Output = Result<tonic::Response<T>, tonic::Status>> + Send,
+    {
+        py.allow_threads(|| self.runtime.block_on(future))
+            .map(|response| response.into_inner())
+            .map_err(|status| {
+                InferenceServerError::new_err(format!("{:?}: {}", status.code(), status.message()))
+            })
+    }
+}
+
+fn to_datatype(dtype: &str) -> PyResult<&'static str> {
+    DATATYPES
+        .iter()
+        .find(|(name, _)| *name == dtype)
+        .map(|(_, datatype)| *datatype)
+        .ok_or_else(|| PyValueError::new_err(format!("unsupported dtype {}", dtype)))
+}
+
+fn to_dtype(datatype: &str) -> PyResult<&'static str> {
+    DATATYPES
+        .iter()
+        .find(|(_, name)| *name == datatype)
+        .map(|(dtype, _)| *dtype)
+        .ok_or_else(|| PyValueError::new_err(format!("unsupported datatype {}", datatype)))
+}
+
+fn raw_contents(array: &PyAny, datatype: &str) -> PyResult<Vec<u8>> {
+    match datatype {
+        "BYTES" => Ok(byte_items(array)?
+            .iter()
+            .flat_map(|item| {
+                (item.len() as u32)
+                    .to_le_bytes()
+                    .into_iter()
+                    .chain(item.iter().copied())
+            })
+            .collect()),
+        _ => array.call_method0("tobytes")?.extract(),
+    }
+}
+
+fn typed_contents(array: &PyAny, datatype: &str) -> PyResult<InferTensorContents> {
+    let flat = array.call_method0("ravel")?;
+    let values = flat.call_method0("tolist")?;
+    let mut contents = InferTensorContents::default();
+
+    match datatype {
+        "BOOL" => contents.bool_contents = values.extract()?,
+        "UINT8" | "UINT16" | "UINT32" => contents.uint_contents = values.extract()?,
+        "UINT64" => contents.uint64_contents = values.extract()?,
+        "INT8" | "INT16" | "INT32" => contents.int_contents = values.extract()?,
+        "INT64" => contents.int64_contents = values.extract()?,
+        "FP32" => contents.fp32_contents = values.extract()?,
+        "FP64" => contents.fp64_contents = values.extract()?,
+        "BYTES" => contents.bytes_contents = byte_items(array)?,
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "{} tensors can only be sent with binary=True",
+                datatype
+            )))
+        }
+    }
+
+    Ok(contents)
+}
+
+fn byte_items(array: &PyAny) -> PyResult<Vec<Vec<u8>>> {
+    array
+        .call_method0("ravel")?
+        .iter()?
+        .map(|item| {
+            let item = item?;
+
+            match item.downcast::<PyBytes>() {
+                Ok(bytes) => Ok(bytes.as_bytes().to_vec()),
+                Err(_) => Ok(item.str()?.to_string().into_bytes()),
+            }
+        })
+        .collect()
+}
+
+fn from_raw(py: Python, output: &InferOutputTensor, raw: &[u8]) -> PyResult<PyObject> {
+    let numpy = py.import("numpy")?;
+
+    if output.datatype == "BYTES" {
+        let mut items = vec![];
+        let mut rest = raw;
+
+        while rest.len() >= 4 {
+            let length = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+            let end = (4 + length).min(rest.len());
+
+            items.push(PyBytes::new(py, &rest[4..end]));
+            rest = &rest[end..];
+        }
+
+        return reshape(
+            numpy.call_method1("array", (PyList::new(py, items), "object"))?,
+            output,
+        );
+    }
+
+    let array = numpy.call_method1(
+        "frombuffer",
+        (PyBytes::new(py, raw), to_dtype(&output.datatype)?),
+    )?;
+
+    // frombuffer views the immutable bytes object; copy so callers can write.
+    reshape(array.call_method0("copy")?, output)
+}
+
+fn from_typed(py: Python, output: &InferOutputTensor) -> PyResult<PyObject> {
+    let numpy = py.import("numpy")?;
+    let contents = output.contents.clone().unwrap_or_default();
+    let dtype = to_dtype(&output.datatype)?;
+    let values: PyObject = match output.datatype.as_str() {
+        "BOOL" => contents.bool_contents.into_py(py),
+        "UINT8" | "UINT16" | "UINT32" => contents.uint_contents.into_py(py),
+        "UINT64" => contents.uint64_contents.into_py(py),
+        "INT8" | "INT16" | "INT32" => contents.int_contents.into_py(py),
+        "INT64" => contents.int64_contents.into_py(py),
+        "FP16" | "FP32" => contents.fp32_contents.into_py(py),
+        "FP64" => contents.fp64_contents.into_py(py),
+        _ => PyList::new(
+            py,
+            contents
+                .bytes_contents
+                .iter()
+                .map(|item| PyBytes::new(py, item)),
+        )
+        .into_py(py),
+    };
+
+    reshape(numpy.call_method1("array", (values, dtype))?, output)
+}
+
+fn reshape(array: &PyAny, output: &InferOutputTensor) -> PyResult<PyObject> {
+    Ok(array
+        .call_method1("reshape", (output.shape.clone(),))?
+        .into_py(array.py()))
+}
+
+fn tensor_metadata(py: Python, tensors: &[TensorMetadata]) -> PyResult<PyObject> {
+    let list = PyList::empty(py);
+
+    for tensor in tensors {
+        let dict = PyDict::new(py);
+
+        dict.set_item("name", &tensor.name)?;
+        dict.set_item("datatype", &tensor.datatype)?;
+        dict.set_item("shape", &tensor.shape)?;
+        list.append(dict)?;
+    }
+
+    Ok(list.into())
+}
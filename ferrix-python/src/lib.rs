@@ -1,19 +1,13 @@
 use pyo3::prelude::*;
 
-#[pyfunction]
-fn preprocessor(_func: &PyAny) -> PyResult<()> {
-    Ok(())
-}
-
-#[pyfunction]
-fn postprocessor(_func: &PyAny) -> PyResult<()> {
-    Ok(())
-}
+mod client;
+mod testing;
 
 /// A Python module implemented in Rust.
 #[pymodule]
-fn ferrix(_py: Python, module: &PyModule) -> PyResult<()> {
-    module.add_function(wrap_pyfunction!(preprocessor, module)?)?;
-    module.add_function(wrap_pyfunction!(postprocessor, module)?)?;
+fn ferrix(py: Python, module: &PyModule) -> PyResult<()> {
+    ferrix_python_hooks::register_module(py, module)?;
+    testing::register_module(py, module)?;
+    client::register_module(py, module)?;
     Ok(())
 }
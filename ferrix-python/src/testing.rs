@@ -0,0 +1,272 @@
+use ferrix_model_api::internal::{InferRequest, InferResponse};
+use ferrix_model_api::ModelConfig;
+use ferrix_python_hooks::error::HookError;
+use ferrix_python_hooks::Handler;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyList};
+use toml::Value;
+
+/// Backs `ferrix.testing`: the same hook machinery the server uses, driven
+/// from a Python test instead of a gRPC request.
+pub fn register_module(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(_load_handler, module)?)?;
+    module.add_function(wrap_pyfunction!(_preprocess, module)?)?;
+    module.add_function(wrap_pyfunction!(_postprocess, module)?)?;
+    module.add_function(wrap_pyfunction!(_unload_handler, module)?)?;
+    module.add_class::<LoadedHandler>()?;
+
+    Ok(())
+}
+
+/// A handler loaded by `_load_handler`, with its own hooks and state.
+#[pyclass]
+struct LoadedHandler(Handler);
+
+#[pyfunction]
+fn _load_handler(
+    path: &str,
+    model_name: String,
+    base_path: String,
+    extended_config: Option<&PyDict>,
+) -> PyResult<LoadedHandler> {
+    let code = std::fs::read_to_string(path)?;
+    let config = ModelConfig {
+        model_name,
+        base_path,
+        sha256: None,
+        platform: None,
+        inputs: vec![],
+        outputs: vec![],
+        extended_config: extended_config
+            .map(|config| py_to_toml(config))
+            .transpose()?,
+        python: None,
+    };
+
+    Handler::load(code, path, &config)
+        .map(LoadedHandler)
+        .map_err(to_py_err)
+}
+
+#[pyfunction]
+fn _preprocess(
+    py: Python,
+    handler: PyRef<LoadedHandler>,
+    request: InferRequest,
+) -> PyResult<PyObject> {
+    let handler = &handler.0;
+    let request = py
+        .allow_threads(|| block_on(handler.preprocess(request)))?
+        .map_err(to_py_err)?;
+
+    Ok(request.to_object(py))
+}
+
+#[pyfunction]
+fn _postprocess(
+    py: Python,
+    handler: PyRef<LoadedHandler>,
+    response: InferResponse,
+) -> PyResult<PyObject> {
+    let handler = &handler.0;
+    let response = py
+        .allow_threads(|| block_on(handler.postprocess(response)))?
+        .map_err(to_py_err)?;
+
+    Ok(response.to_object(py))
+}
+
+#[pyfunction]
+fn _unload_handler(handler: PyRef<LoadedHandler>) -> PyResult<()> {
+    handler.0.shutdown().map_err(to_py_err)
+}
+
+/// Hooks may be `async def`, whose coroutines run on the hook event loop
+/// thread; the GIL is released while waiting so that thread can make progress.
+fn block_on<F: std::future::Future>(future: F) -> PyResult<F::Output> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .map_err(|error| PyRuntimeError::new_err(error.to_string()))?;
+
+    Ok(runtime.block_on(future))
+}
+
+fn to_py_err(error: HookError) -> PyErr {
+    PyRuntimeError::new_err(error.describe())
+}
+
+fn py_to_toml(object: &PyAny) -> PyResult<Value> {
+    if let Ok(boolean) = object.downcast::<PyBool>() {
+        return Ok(Value::Boolean(boolean.is_true()));
+    }
+    if let Ok(integer) = object.extract::<i64>() {
+        return Ok(Value::Integer(integer));
+    }
+    if let Ok(float) = object.extract::<f64>() {
+        return Ok(Value::Float(float));
+    }
+    if let Ok(string) = object.extract::<String>() {
+        return Ok(Value::String(string));
+    }
+    if let Ok(list) = object.downcast::<PyList>() {
+        return list
+            .iter()
+            .map(py_to_toml)
+            .collect::<PyResult<Vec<Value>>>()
+            .map(Value::Array);
+    }
+    if let Ok(dict) = object.downcast::<PyDict>() {
+        return dict
+            .iter()
+            .map(|(key, value)| Ok((key.str()?.to_string(), py_to_toml(value)?)))
+            .collect::<PyResult<toml::Table>>()
+            .map(Value::Table);
+    }
+
+    Ok(Value::String(object.str()?.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::Once;
+
+    use pyo3::types::IntoPyDict;
+
+    use super::*;
+
+    static INIT: Once = Once::new();
+
+    const HANDLER: &str = "import ferrix\n\n@ferrix.setup\ndef setup(config, state):\n    state.labels = config['extended_config']['labels']\n\n@ferrix.preprocessor\ndef pre(request, state):\n    request.id += ':' + state.labels[0]\n    return request\n\n@ferrix.postprocessor\ndef post(response, state):\n    response.model_name += ':' + ','.join(state.labels)\n    return response\n";
+
+    /// Starts an interpreter where `import ferrix` finds the package in
+    /// `python/` backed by this crate's module, as in the installed wheel.
+    fn init() {
+        INIT.call_once(|| {
+            pyo3::prepare_freethreaded_python();
+            Python::with_gil(|py| {
+                let extension = PyModule::new(py, "ferrix.ferrix")?;
+
+                crate::ferrix(py, extension)?;
+
+                let locals = [
+                    ("extension", extension.to_object(py)),
+                    (
+                        "path",
+                        concat!(env!("CARGO_MANIFEST_DIR"), "/python/ferrix").to_object(py),
+                    ),
+                ]
+                .into_py_dict(py);
+
+                py.run(
+                    "import importlib.util, sys\n\
+                     spec = importlib.util.spec_from_file_location(\n    \
+                         'ferrix', path + '/__init__.py', submodule_search_locations=[path])\n\
+                     package = importlib.util.module_from_spec(spec)\n\
+                     sys.modules['ferrix'] = package\n\
+                     sys.modules['ferrix.ferrix'] = package.ferrix = extension\n\
+                     spec.loader.exec_module(package)\n",
+                    None,
+                    Some(locals),
+                )
+            })
+            .unwrap();
+        });
+    }
+
+    fn handler_file(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("ferrix-python-{}-{}.py", name, std::process::id()));
+
+        std::fs::write(&path, HANDLER).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_hooks() {
+        init();
+
+        let path = handler_file("hooks");
+
+        Python::with_gil(|py| {
+            let extended_config = [("labels", vec!["cat", "dog"])].into_py_dict(py);
+            let handler = _load_handler(
+                path.to_str().unwrap(),
+                "resnet".to_string(),
+                "model.pt".to_string(),
+                Some(extended_config),
+            )
+            .unwrap();
+            let handler = Py::new(py, handler).unwrap();
+            let request = InferRequest {
+                model_name: "resnet".to_string(),
+                model_version: "".to_string(),
+                id: "1".to_string(),
+                parameters: HashMap::new(),
+                inputs: vec![],
+                outputs: vec![],
+                raw_input_contents: vec![],
+            };
+            let response = InferResponse {
+                model_name: "resnet".to_string(),
+                id: "1".to_string(),
+                parameters: HashMap::new(),
+                outputs: vec![],
+            };
+
+            let preprocessed: InferRequest = _preprocess(py, handler.borrow(py), request)
+                .unwrap()
+                .extract(py)
+                .unwrap();
+            let postprocessed: InferResponse = _postprocess(py, handler.borrow(py), response)
+                .unwrap()
+                .extract(py)
+                .unwrap();
+
+            assert_eq!("1:cat", preprocessed.id);
+            assert_eq!("resnet:cat,dog", postprocessed.model_name);
+
+            _unload_handler(handler.borrow(py)).unwrap();
+        });
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_testing_module() {
+        init();
+
+        let path = handler_file("testing");
+
+        Python::with_gil(|py| {
+            let globals = [("path", path.to_str().unwrap())].into_py_dict(py);
+
+            py.run(
+                r#"
+from ferrix import InferRequest, InferResponse
+from ferrix.testing import Handler
+
+def model(request):
+    assert request.id == "1:cat", request.id
+    return InferResponse("1", request.model_name, {}, [])
+
+def request(id):
+    return InferRequest("resnet", "", id, {}, [], [], [])
+
+with Handler.load(path, model_name="resnet", extended_config={"labels": ["cat", "dog"]}) as handler:
+    response = handler.run(request("1"), model=model)
+
+assert response.model_name == "resnet:cat,dog", response.model_name
+assert handler.preprocess(request("2")).id == "2"
+"#,
+                Some(globals),
+                None,
+            )
+            .unwrap();
+        });
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
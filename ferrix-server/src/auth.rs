@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::bail;
+use serde::Deserialize;
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Status};
+
+use crate::jwt::{JwtConfig, JwtValidator};
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Who may call the server. Callers authenticate with a static token or a JWT,
+/// either as `Authorization: Bearer <token>` or in the `x-api-key` header.
+///
+/// ```toml
+/// exempt_health = true
+/// admins = ["deployer"]
+///
+/// [[tokens]]
+/// principal = "batch-jobs"
+/// token = "s3cret"
+///
+/// [jwt]
+/// jwks = "/etc/ferrix/jwks.json"
+/// issuer = "https://auth.example.com"
+/// audience = "ferrix"
+///
+/// [allow]
+/// resnet = ["batch-jobs", "alice"]
+/// ```
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Let liveness and readiness probes through without credentials.
+    #[serde(default)]
+    pub exempt_health: bool,
+    #[serde(default)]
+    pub tokens: Vec<TokenConfig>,
+    pub jwt: Option<JwtConfig>,
+    /// Principals allowed to use each model. Models that aren't listed are
+    /// open to every authenticated principal.
+    #[serde(default)]
+    pub allow: HashMap<String, Vec<String>>,
+    /// Principals allowed to list, load and unload models and change their
+    /// labels. Nobody can when empty.
+    #[serde(default)]
+    pub admins: Vec<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TokenConfig {
+    pub principal: String,
+    pub token: String,
+}
+
+/// The authenticated caller, stored in the request extensions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Principal(pub String);
+
+/// What a request is trying to reach.
+#[derive(Clone, Copy, Debug)]
+pub enum Access<'a> {
+    Health,
+    Server,
+    Model(&'a str),
+    /// Changing which models are served or their labels, or listing every
+    /// model.
+    Admin,
+}
+
+pub struct Auth {
+    config: AuthConfig,
+    jwt: Option<JwtValidator>,
+}
+
+// Statuses go straight back to tonic, which is what they're sized for.
+#[allow(clippy::result_large_err)]
+impl Auth {
+    pub fn new(config: AuthConfig) -> anyhow::Result<Self> {
+        if config.tokens.is_empty() && config.jwt.is_none() {
+            bail!("auth config has neither tokens nor a JWKS, so nobody could authenticate");
+        }
+
+        let jwt = config.jwt.as_ref().map(JwtValidator::new).transpose()?;
+
+        Ok(Auth { config, jwt })
+    }
+
+    /// Missing credentials aren't an error here, since health checks may be
+    /// exempt. Credentials that are present have to be valid.
+    pub fn authenticate(
+        &self,
+        authorization: Option<&str>,
+        api_key: Option<&str>,
+    ) -> Result<Option<Principal>, Status> {
+        let credential = match (authorization, api_key) {
+            (Some(authorization), _) => match authorization.strip_prefix("Bearer ") {
+                Some(token) => token.trim(),
+                None => {
+                    return Err(Status::unauthenticated(
+                        "unsupported authorization scheme, expected Bearer",
+                    ))
+                }
+            },
+            (None, Some(api_key)) => api_key.trim(),
+            (None, None) => return Ok(None),
+        };
+
+        let token = self
+            .config
+            .tokens
+            .iter()
+            .find(|token| constant_time_eq(token.token.as_bytes(), credential.as_bytes()));
+
+        if let Some(token) = token {
+            return Ok(Some(Principal(token.principal.clone())));
+        }
+
+        match &self.jwt {
+            Some(jwt) => jwt
+                .validate(credential)
+                .map(|subject| Some(Principal(subject)))
+                .map_err(|error| Status::unauthenticated(format!("invalid token: {}", error))),
+            None => Err(Status::unauthenticated("invalid token")),
+        }
+    }
+
+    pub fn authenticate_metadata(
+        &self,
+        metadata: &MetadataMap,
+    ) -> Result<Option<Principal>, Status> {
+        let header = |name| metadata.get(name).and_then(|value| value.to_str().ok());
+
+        self.authenticate(header("authorization"), header(API_KEY_HEADER))
+    }
+
+    pub fn authorize(&self, principal: Option<&Principal>, access: Access) -> Result<(), Status> {
+        let principal = match (principal, access) {
+            (_, Access::Health) if self.config.exempt_health => return Ok(()),
+            (Some(principal), _) => principal,
+            (None, _) => return Err(Status::unauthenticated("missing credentials")),
+        };
+
+        let model = match access {
+            Access::Model(model) => model,
+            Access::Admin if !self.config.admins.contains(&principal.0) => {
+                return Err(Status::permission_denied(format!(
+                    "{} may not manage the model repository",
+                    principal.0
+                )))
+            }
+            _ => return Ok(()),
+        };
+
+        match self.config.allow.get(model) {
+            Some(allowed) if !allowed.contains(&principal.0) => Err(Status::permission_denied(
+                format!("{} may not use model {}", principal.0, model),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Authenticates gRPC calls before they reach the service. Interceptors
+    /// don't see which method or model is called, so authorization happens in
+    /// the service against the principal left in the extensions.
+    pub fn interceptor(
+        self: &Arc<Self>,
+    ) -> impl FnMut(Request<()>) -> Result<Request<()>, Status> + Clone {
+        let auth = self.clone();
+
+        move |mut request: Request<()>| {
+            if let Some(principal) = auth.authenticate_metadata(request.metadata())? {
+                request.extensions_mut().insert(principal);
+            }
+
+            Ok(request)
+        }
+    }
+}
+
+/// Compares every byte regardless of where the first difference is, so the
+/// response time doesn't reveal how much of a token was guessed right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::jwt::tests::Signer;
+
+    fn auth(exempt_health: bool) -> Auth {
+        let config: AuthConfig = toml::from_str(&format!(
+            r#"
+            exempt_health = {}
+            admins = ["dashboards"]
+
+            [[tokens]]
+            principal = "batch-jobs"
+            token = "s3cret"
+
+            [[tokens]]
+            principal = "dashboards"
+            token = "0ther"
+
+            [allow]
+            resnet = ["batch-jobs"]
+            "#,
+            exempt_health
+        ))
+        .unwrap();
+
+        Auth::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_authenticate_static_tokens() {
+        let auth = auth(false);
+        let batch_jobs = Some(Principal("batch-jobs".to_string()));
+
+        assert_eq!(
+            batch_jobs,
+            auth.authenticate(Some("Bearer s3cret"), None).unwrap()
+        );
+        assert_eq!(batch_jobs, auth.authenticate(None, Some("s3cret")).unwrap());
+        assert_eq!(None, auth.authenticate(None, None).unwrap());
+        assert!(auth.authenticate(Some("Bearer s3cre"), None).is_err());
+        assert!(auth.authenticate(Some("Basic s3cret"), None).is_err());
+    }
+
+    #[test]
+    fn test_authorize() {
+        let auth = auth(false);
+        let batch_jobs = Principal("batch-jobs".to_string());
+        let dashboards = Principal("dashboards".to_string());
+        let code = |result: Result<(), Status>| result.err().map(|status| status.code());
+
+        assert_eq!(
+            None,
+            code(auth.authorize(Some(&batch_jobs), Access::Model("resnet")))
+        );
+        assert_eq!(
+            Some(tonic::Code::PermissionDenied),
+            code(auth.authorize(Some(&dashboards), Access::Model("resnet")))
+        );
+        assert_eq!(
+            None,
+            code(auth.authorize(Some(&dashboards), Access::Model("bert")))
+        );
+        assert_eq!(
+            Some(tonic::Code::Unauthenticated),
+            code(auth.authorize(None, Access::Health))
+        );
+        assert_eq!(None, code(self::auth(true).authorize(None, Access::Health)));
+        assert_eq!(
+            Some(tonic::Code::Unauthenticated),
+            code(self::auth(true).authorize(None, Access::Server))
+        );
+        assert_eq!(
+            Some(tonic::Code::PermissionDenied),
+            code(auth.authorize(Some(&batch_jobs), Access::Admin))
+        );
+        assert_eq!(None, code(auth.authorize(Some(&dashboards), Access::Admin)));
+    }
+
+    #[test]
+    fn test_authenticate_jwt() {
+        let signer = Signer::new("auth");
+        let auth = Auth::new(AuthConfig {
+            jwt: Some(JwtConfig {
+                jwks: signer.jwks.clone(),
+                issuer: None,
+                audience: None,
+            }),
+            ..AuthConfig::default()
+        })
+        .unwrap();
+        let token = signer.sign(json!({"sub": "alice", "exp": u64::MAX / 2}));
+
+        assert_eq!(
+            Some(Principal("alice".to_string())),
+            auth.authenticate(Some(&format!("Bearer {}", token)), None)
+                .unwrap()
+        );
+        assert!(Auth::new(AuthConfig::default()).is_err());
+    }
+}
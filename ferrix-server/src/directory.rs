@@ -0,0 +1,523 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context};
+use ferrix_model_api::ModelConfig;
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+use toml::{Table, Value};
+use tonic::Code;
+use tracing::warn;
+
+use crate::repository::Repository;
+
+/// Per-model config file, next to the version directories.
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Which of a model's versions to serve, set with `version_policy` in its
+/// config file:
+///
+/// ```toml
+/// version_policy = { latest = 2 }
+/// version_policy = { specific = [1, 3] }
+/// version_policy = "all"
+/// ```
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionPolicy {
+    Latest(usize),
+    Specific(Vec<u64>),
+    All,
+}
+
+impl Default for VersionPolicy {
+    fn default() -> Self {
+        VersionPolicy::Latest(1)
+    }
+}
+
+impl VersionPolicy {
+    fn select(&self, versions: &BTreeSet<u64>) -> BTreeSet<u64> {
+        match self {
+            VersionPolicy::Latest(count) => versions.iter().rev().take(*count).copied().collect(),
+            VersionPolicy::Specific(wanted) => wanted
+                .iter()
+                .filter(|version| versions.contains(version))
+                .copied()
+                .collect(),
+            VersionPolicy::All => versions.clone(),
+        }
+    }
+}
+
+/// A model repository on disk, with a directory per model and a numbered
+/// directory per version:
+///
+/// ```text
+/// models/
+///   resnet/
+///     config.toml
+///     1/model.pt
+///     2/model.pt
+/// ```
+///
+/// `config.toml` is optional and holds the model's config. `model_name`
+/// defaults to the directory name and a relative `base_path` is resolved in
+/// each version directory. Without a `base_path`, a version directory must
+/// hold exactly one file.
+pub struct ModelDirectory {
+    root: PathBuf,
+    /// What the repository last loaded or failed to load, so only changes
+    /// are reloaded.
+    synced: BTreeMap<String, BTreeMap<u64, Fingerprint>>,
+    /// The last error per model, so a broken config is only reported once.
+    errors: BTreeMap<String, String>,
+}
+
+/// Size and modification time of everything a version is loaded from.
+type Fingerprint = Vec<(PathBuf, u64, Option<SystemTime>)>;
+
+struct Found {
+    config: ModelConfig,
+    fingerprint: Fingerprint,
+}
+
+impl ModelDirectory {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        ModelDirectory {
+            root: root.into(),
+            synced: BTreeMap::new(),
+            errors: BTreeMap::new(),
+        }
+    }
+
+    /// Brings the repository in line with the directory: versions that
+    /// appeared or changed are loaded and versions that disappeared or fell
+    /// out of the version policy are removed. A model whose directory can't be
+    /// read keeps serving what it served before. Versions the repository no
+    /// longer knows, such as ones replaced by a load with an explicit config,
+    /// are added and loaded again.
+    pub async fn sync(&mut self, repository: &Repository) {
+        let root = self.root.clone();
+
+        let scanned = match tokio::task::spawn_blocking(move || scan(&root)).await {
+            Ok(Ok(scanned)) => scanned,
+            Ok(Err(error)) => {
+                warn!(root = %self.root.display(), "Failed to read model repository: {:#}", error);
+                return;
+            }
+            Err(error) => {
+                warn!(root = %self.root.display(), "Failed to read model repository: {}", error);
+                return;
+            }
+        };
+
+        let known: BTreeSet<(String, String)> = repository
+            .index(false)
+            .into_iter()
+            .map(|model| (model.name, model.version))
+            .collect();
+
+        for (name, versions) in &mut self.synced {
+            versions.retain(|version, _| known.contains(&(name.clone(), version.to_string())));
+        }
+
+        let removed: Vec<String> = self
+            .synced
+            .keys()
+            .filter(|name| !scanned.contains_key(*name))
+            .cloned()
+            .collect();
+
+        for name in removed {
+            self.errors.remove(&name);
+
+            for version in self.synced.remove(&name).unwrap_or_default().keys() {
+                repository.remove(&name, &version.to_string());
+            }
+        }
+
+        for (name, result) in scanned {
+            let versions = match result {
+                Ok(versions) => {
+                    self.errors.remove(&name);
+                    versions
+                }
+                Err(error) => {
+                    let error = format!("{:#}", error);
+
+                    if self.errors.get(&name) != Some(&error) {
+                        warn!(model = %name, "Skipping model: {}", error);
+                        self.errors.insert(name, error);
+                    }
+
+                    continue;
+                }
+            };
+
+            let synced = self.synced.entry(name.clone()).or_default();
+
+            let removed: Vec<u64> = synced
+                .keys()
+                .filter(|version| !versions.contains_key(version))
+                .copied()
+                .collect();
+
+            for version in removed {
+                synced.remove(&version);
+                repository.remove(&name, &version.to_string());
+            }
+
+            for (version, found) in versions {
+                if synced.get(&version) == Some(&found.fingerprint) {
+                    continue;
+                }
+
+                repository.add(found.config, &version.to_string());
+
+                // Failures are logged and reported through the repository
+                // index, and retried once the version's files change. Loads
+                // that didn't run, such as when the version was already
+                // loading, are retried on the next sync.
+                match repository.load_version(&name, &version.to_string()).await {
+                    Err(status) if status.code() != Code::Internal => {}
+                    _ => {
+                        synced.insert(version, found.fingerprint);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Syncs every `interval` until the returned task is aborted. Call
+    /// [`ModelDirectory::sync`] first to load models before serving.
+    pub fn watch(mut self, repository: Arc<Repository>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+
+            // The first tick completes immediately, right after the initial sync.
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                self.sync(&repository).await;
+            }
+        })
+    }
+}
+
+type Scanned = BTreeMap<String, anyhow::Result<BTreeMap<u64, Found>>>;
+
+fn scan(root: &Path) -> anyhow::Result<Scanned> {
+    let mut scanned = BTreeMap::new();
+
+    for entry in std::fs::read_dir(root)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if name.starts_with('.') || !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let versions = scan_model(&entry.path(), &name);
+        scanned.insert(name, versions);
+    }
+
+    Ok(scanned)
+}
+
+fn scan_model(dir: &Path, name: &str) -> anyhow::Result<BTreeMap<u64, Found>> {
+    let config_path = dir.join(CONFIG_FILE);
+
+    let (mut table, config_fingerprint) = if config_path.exists() {
+        let contents = std::fs::read_to_string(&config_path)?;
+        let table: Table = toml::from_str(&contents).context("invalid config.toml")?;
+
+        (table, fingerprint(&config_path)?)
+    } else {
+        (Table::new(), Fingerprint::new())
+    };
+
+    let policy: VersionPolicy = match table.remove("version_policy") {
+        Some(policy) => policy.try_into().context("invalid version_policy")?,
+        None => VersionPolicy::default(),
+    };
+
+    match table.get("model_name") {
+        Some(Value::String(model_name)) if model_name != name => {
+            bail!("model_name {} doesn't match the directory name", model_name)
+        }
+        Some(Value::String(_)) => {}
+        Some(_) => bail!("model_name must be a string"),
+        None => {
+            table.insert("model_name".to_string(), Value::String(name.to_string()));
+        }
+    }
+
+    let base_path = match table.remove("base_path") {
+        Some(Value::String(base_path)) => Some(base_path),
+        Some(_) => bail!("base_path must be a string"),
+        None => None,
+    };
+
+    let mut versions = BTreeSet::new();
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        if let Ok(version) = entry.file_name().to_string_lossy().parse::<u64>() {
+            versions.insert(version);
+        }
+    }
+
+    let mut found = BTreeMap::new();
+
+    for version in policy.select(&versions) {
+        let version_dir = dir.join(version.to_string());
+
+        let artifact = match &base_path {
+            Some(base_path) => version_dir.join(base_path),
+            None => single_file(&version_dir).with_context(|| format!("version {}", version))?,
+        };
+
+        let mut table = table.clone();
+        table.insert(
+            "base_path".to_string(),
+            Value::String(artifact.to_string_lossy().to_string()),
+        );
+
+        let config: ModelConfig = Value::Table(table)
+            .try_into()
+            .context("invalid config.toml")?;
+
+        let mut fingerprint = fingerprint(&version_dir)?;
+        fingerprint.extend(config_fingerprint.iter().cloned());
+
+        found.insert(
+            version,
+            Found {
+                config,
+                fingerprint,
+            },
+        );
+    }
+
+    Ok(found)
+}
+
+fn single_file(dir: &Path) -> anyhow::Result<PathBuf> {
+    let mut files = Vec::new();
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+
+        if entry.file_type()?.is_file() && !entry.file_name().to_string_lossy().starts_with('.') {
+            files.push(entry.path());
+        }
+    }
+
+    match files.len() {
+        1 => Ok(files.remove(0)),
+        0 => bail!("no model artifact in {}", dir.display()),
+        _ => bail!(
+            "more than one file in {}, set base_path in config.toml",
+            dir.display()
+        ),
+    }
+}
+
+/// Walks `path`, collecting every file below it in a stable order.
+fn fingerprint(path: &Path) -> anyhow::Result<Fingerprint> {
+    let metadata = std::fs::metadata(path)?;
+
+    if !metadata.is_dir() {
+        return Ok(vec![(
+            path.to_path_buf(),
+            metadata.len(),
+            metadata.modified().ok(),
+        )]);
+    }
+
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+
+    let mut fingerprint = Fingerprint::new();
+
+    for entry in entries {
+        fingerprint.extend(self::fingerprint(&entry)?);
+    }
+
+    Ok(fingerprint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::tests::{model_config, repository};
+    use std::sync::atomic::AtomicBool;
+
+    fn root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("ferrix-directory-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn write(path: PathBuf, contents: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    fn served(repository: &Repository) -> Vec<(String, String, String)> {
+        repository
+            .index(false)
+            .into_iter()
+            .map(|model| (model.name, model.version, model.state))
+            .collect()
+    }
+
+    fn ready(name: &str, version: &str) -> (String, String, String) {
+        (name.to_string(), version.to_string(), "READY".to_string())
+    }
+
+    #[test]
+    fn test_version_policy() {
+        let versions = BTreeSet::from([1, 2, 3]);
+        let policy = |toml: &str| {
+            let table: Table = toml::from_str(toml).unwrap();
+            table["version_policy"]
+                .clone()
+                .try_into::<VersionPolicy>()
+                .unwrap()
+        };
+
+        assert_eq!(
+            BTreeSet::from([2, 3]),
+            policy("version_policy = { latest = 2 }").select(&versions)
+        );
+        assert_eq!(
+            BTreeSet::from([1]),
+            policy("version_policy = { specific = [1, 4] }").select(&versions)
+        );
+        assert_eq!(
+            versions,
+            policy("version_policy = \"all\"").select(&versions)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sync() {
+        let root = root("sync");
+        let repository = repository(Arc::new(AtomicBool::new(false)));
+        let mut directory = ModelDirectory::new(&root);
+
+        write(root.join("resnet/1/model.pt"), "1");
+        write(root.join("resnet/2/model.pt"), "2");
+        write(root.join("bert/3/model.onnx"), "3");
+        write(root.join("notes.txt"), "not a model");
+        directory.sync(&repository).await;
+
+        assert_eq!(
+            vec![ready("bert", "3"), ready("resnet", "2")],
+            served(&repository)
+        );
+        assert!(repository
+            .model("resnet")
+            .unwrap()
+            .model_config()
+            .base_path
+            .ends_with("resnet/2/model.pt"));
+
+        write(root.join("resnet/3/model.pt"), "3");
+        write(root.join("resnet/config.toml"), "version_policy = \"all\"");
+        std::fs::remove_dir_all(root.join("bert")).unwrap();
+        directory.sync(&repository).await;
+
+        assert_eq!(
+            vec![
+                ready("resnet", "1"),
+                ready("resnet", "2"),
+                ready("resnet", "3")
+            ],
+            served(&repository)
+        );
+
+        // A broken config keeps the model serving as before.
+        write(root.join("resnet/config.toml"), "version_policy = 7");
+        directory.sync(&repository).await;
+
+        assert_eq!(3, served(&repository).len());
+
+        write(
+            root.join("resnet/config.toml"),
+            "version_policy = { specific = [1] }",
+        );
+        directory.sync(&repository).await;
+
+        assert_eq!(vec![ready("resnet", "1")], served(&repository));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sync_restores_replaced_versions() {
+        let root = root("replaced");
+        let repository = repository(Arc::new(AtomicBool::new(false)));
+        let mut directory = ModelDirectory::new(&root);
+
+        write(root.join("resnet/1/model.pt"), "1");
+        directory.sync(&repository).await;
+
+        repository
+            .load("resnet", Some(model_config("resnet", "other.pt")))
+            .await
+            .unwrap();
+
+        assert_eq!(vec![ready("resnet", "")], served(&repository));
+
+        directory.sync(&repository).await;
+
+        assert_eq!(
+            vec![ready("resnet", ""), ready("resnet", "1")],
+            served(&repository)
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_scan_errors() {
+        let root = root("errors");
+
+        write(root.join("resnet/1/model.pt"), "1");
+        write(root.join("resnet/1/labels.txt"), "cat");
+        write(root.join("bert/config.toml"), "model_name = \"other\"");
+        write(root.join("bert/1/model.onnx"), "1");
+        write(
+            root.join("gpt/config.toml"),
+            "base_path = \"weights/model.pt\"",
+        );
+        write(root.join("gpt/1/weights/model.pt"), "1");
+
+        let scanned = scan(&root).unwrap();
+
+        assert!(format!("{:#}", scanned["resnet"].as_ref().err().unwrap())
+            .contains("more than one file"));
+        assert!(format!("{:#}", scanned["bert"].as_ref().err().unwrap()).contains("doesn't match"));
+        assert!(scanned["gpt"].as_ref().unwrap()[&1]
+            .config
+            .base_path
+            .ends_with("gpt/1/weights/model.pt"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,1280 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, Request as HttpRequest, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router};
+use ferrix_protos::grpc_inference_service_server::GrpcInferenceService;
+use ferrix_protos::model_metadata_response::TensorMetadata;
+use ferrix_protos::model_repository_parameter::ParameterChoice;
+use ferrix_protos::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tonic::{Code, Request, Status};
+use tracing::{info_span, Instrument};
+
+use crate::auth::{Auth, Principal, API_KEY_HEADER};
+use crate::{telemetry, GrpcInferenceServiceImpl};
+
+type Service = Arc<GrpcInferenceServiceImpl>;
+type Caller = Option<Extension<Principal>>;
+
+/// The KServe v2 REST inference, health, metadata, statistics and repository
+/// endpoints. They are answered by the gRPC service, so HTTP clients see the
+/// same state and go through the same checks as gRPC clients.
+pub fn router(service: Service) -> Router {
+    let auth = service.auth.clone();
+    let router = Router::new()
+        .route("/v2", get(server_metadata))
+        .route("/v2/health/live", get(live))
+        .route("/v2/health/ready", get(ready))
+        .route("/v2/models/stats", get(all_model_statistics))
+        .route("/v2/repository/index", post(repository_index))
+        .route("/v2/repository/models/:model/load", post(load_model))
+        .route("/v2/repository/models/:model/unload", post(unload_model))
+        .route("/v2/models/:model", get(model_metadata))
+        .route("/v2/models/:model/ready", get(model_ready))
+        .route("/v2/models/:model/stats", get(model_statistics))
+        .route("/v2/models/:model/infer", post(infer))
+        .route(
+            "/v2/models/:model/labels",
+            get(model_labels).post(set_model_labels),
+        )
+        .route(
+            "/v2/models/:model/versions/:version",
+            get(versioned_model_metadata),
+        )
+        .route(
+            "/v2/models/:model/versions/:version/ready",
+            get(versioned_model_ready),
+        )
+        .route(
+            "/v2/models/:model/versions/:version/stats",
+            get(versioned_model_statistics),
+        )
+        .route(
+            "/v2/models/:model/versions/:version/infer",
+            post(versioned_infer),
+        )
+        .with_state(service);
+
+    let router = match auth {
+        Some(auth) => router.layer(middleware::from_fn_with_state(auth, authenticate)),
+        None => router,
+    };
+
+    router.layer(middleware::from_fn(trace))
+}
+
+/// A span per request, continuing the caller's trace if it sent a W3C
+/// `traceparent` header.
+async fn trace<B>(request: HttpRequest<B>, next: Next<B>) -> Response {
+    let span = info_span!(
+        "HTTP request",
+        otel.kind = "server",
+        http.method = %request.method(),
+        http.target = %request.uri().path(),
+    );
+
+    telemetry::continue_from_headers(&span, request.headers());
+
+    next.run(request).instrument(span).await
+}
+
+/// The HTTP counterpart of [`Auth::interceptor`].
+async fn authenticate<B>(
+    State(auth): State<Arc<Auth>>,
+    mut request: HttpRequest<B>,
+    next: Next<B>,
+) -> Response {
+    let headers: &HeaderMap = request.headers();
+    let value = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    match auth.authenticate(value(header::AUTHORIZATION.as_str()), value(API_KEY_HEADER)) {
+        Ok(principal) => {
+            if let Some(principal) = principal {
+                request.extensions_mut().insert(principal);
+            }
+
+            next.run(request).await
+        }
+        Err(status) => HttpError(status).into_response(),
+    }
+}
+
+/// Hands the caller on to the gRPC service, which authorizes the request.
+fn request<T>(message: T, caller: Caller) -> Request<T> {
+    let mut request = Request::new(message);
+
+    if let Some(Extension(principal)) = caller {
+        request.extensions_mut().insert(principal);
+    }
+
+    request
+}
+
+#[derive(Serialize)]
+struct JsonServerMetadata {
+    name: String,
+    version: String,
+    extensions: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct JsonModelMetadata {
+    name: String,
+    versions: Vec<String>,
+    platform: String,
+    inputs: Vec<JsonTensorMetadata>,
+    outputs: Vec<JsonTensorMetadata>,
+}
+
+#[derive(Serialize)]
+struct JsonTensorMetadata {
+    name: String,
+    datatype: String,
+    shape: Vec<i64>,
+}
+
+impl From<TensorMetadata> for JsonTensorMetadata {
+    fn from(tensor: TensorMetadata) -> Self {
+        JsonTensorMetadata {
+            name: tensor.name,
+            datatype: tensor.datatype,
+            shape: tensor.shape,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonStatistics {
+    model_stats: Vec<JsonModelStatistics>,
+}
+
+#[derive(Serialize)]
+struct JsonModelStatistics {
+    name: String,
+    version: String,
+    last_inference: u64,
+    inference_count: u64,
+    execution_count: u64,
+    inference_stats: JsonInferStatistics,
+    batch_stats: Vec<JsonBatchStatistics>,
+}
+
+#[derive(Serialize)]
+struct JsonInferStatistics {
+    success: JsonDuration,
+    fail: JsonDuration,
+    queue: JsonDuration,
+    compute_input: JsonDuration,
+    compute_infer: JsonDuration,
+    compute_output: JsonDuration,
+}
+
+#[derive(Serialize)]
+struct JsonBatchStatistics {
+    batch_size: u64,
+    compute_input: JsonDuration,
+    compute_infer: JsonDuration,
+    compute_output: JsonDuration,
+}
+
+#[derive(Serialize, Default)]
+struct JsonDuration {
+    count: u64,
+    ns: u64,
+}
+
+impl From<ModelStatistics> for JsonModelStatistics {
+    fn from(stats: ModelStatistics) -> Self {
+        let inference_stats = stats.inference_stats.unwrap_or_default();
+
+        JsonModelStatistics {
+            name: stats.name,
+            version: stats.version,
+            last_inference: stats.last_inference,
+            inference_count: stats.inference_count,
+            execution_count: stats.execution_count,
+            inference_stats: JsonInferStatistics {
+                success: inference_stats.success.into(),
+                fail: inference_stats.fail.into(),
+                queue: inference_stats.queue.into(),
+                compute_input: inference_stats.compute_input.into(),
+                compute_infer: inference_stats.compute_infer.into(),
+                compute_output: inference_stats.compute_output.into(),
+            },
+            batch_stats: stats
+                .batch_stats
+                .into_iter()
+                .map(|batch| JsonBatchStatistics {
+                    batch_size: batch.batch_size,
+                    compute_input: batch.compute_input.into(),
+                    compute_infer: batch.compute_infer.into(),
+                    compute_output: batch.compute_output.into(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<Option<StatisticDuration>> for JsonDuration {
+    fn from(duration: Option<StatisticDuration>) -> Self {
+        duration
+            .map(|duration| JsonDuration {
+                count: duration.count,
+                ns: duration.ns,
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct JsonInferRequest {
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    parameters: HashMap<String, Value>,
+    #[serde(default)]
+    inputs: Vec<JsonInputTensor>,
+    #[serde(default)]
+    outputs: Vec<JsonRequestedOutput>,
+}
+
+#[derive(Deserialize)]
+struct JsonInputTensor {
+    name: String,
+    shape: Vec<i64>,
+    datatype: String,
+    data: Value,
+}
+
+#[derive(Deserialize)]
+struct JsonRequestedOutput {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct JsonInferResponse {
+    model_name: String,
+    model_version: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    id: String,
+    outputs: Vec<JsonOutputTensor>,
+}
+
+#[derive(Serialize)]
+struct JsonOutputTensor {
+    name: String,
+    shape: Vec<i64>,
+    datatype: String,
+    data: JsonTensorData,
+}
+
+/// Output data as a flat array. Serialized from the typed contents, so FP32
+/// values print as written rather than widened to f64.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum JsonTensorData {
+    Bool(Vec<bool>),
+    Int(Vec<i32>),
+    Int64(Vec<i64>),
+    Uint(Vec<u32>),
+    Uint64(Vec<u64>),
+    Fp32(Vec<f32>),
+    Fp64(Vec<f64>),
+    Bytes(Vec<String>),
+}
+
+#[allow(clippy::result_large_err)]
+impl JsonInferRequest {
+    fn into_proto(
+        self,
+        model_name: String,
+        model_version: String,
+    ) -> Result<ModelInferRequest, HttpError> {
+        let parameters = self
+            .parameters
+            .into_iter()
+            .map(|(name, value)| {
+                let choice = match value {
+                    Value::Bool(value) => infer_parameter::ParameterChoice::BoolParam(value),
+                    Value::Number(value) if value.is_i64() => {
+                        infer_parameter::ParameterChoice::Int64Param(
+                            value.as_i64().unwrap_or_default(),
+                        )
+                    }
+                    Value::String(value) => infer_parameter::ParameterChoice::StringParam(value),
+                    _ => {
+                        return Err(HttpError(Status::invalid_argument(format!(
+                            "parameter {} must be a boolean, integer or string",
+                            name
+                        ))))
+                    }
+                };
+                let parameter = InferParameter {
+                    parameter_choice: Some(choice),
+                };
+
+                Ok((name, parameter))
+            })
+            .collect::<Result<_, HttpError>>()?;
+        let inputs = self
+            .inputs
+            .into_iter()
+            .map(|input| {
+                Ok(model_infer_request::InferInputTensor {
+                    contents: Some(input.contents()?),
+                    name: input.name,
+                    datatype: input.datatype,
+                    shape: input.shape,
+                    parameters: HashMap::new(),
+                })
+            })
+            .collect::<Result<_, HttpError>>()?;
+
+        Ok(ModelInferRequest {
+            model_name,
+            model_version,
+            id: self.id,
+            parameters,
+            inputs,
+            outputs: self
+                .outputs
+                .into_iter()
+                .map(|output| model_infer_request::InferRequestedOutputTensor {
+                    name: output.name,
+                    parameters: HashMap::new(),
+                })
+                .collect(),
+            raw_input_contents: vec![],
+        })
+    }
+}
+
+#[allow(clippy::result_large_err)]
+impl JsonInputTensor {
+    /// The data, which may be nested like the shape, as typed contents.
+    fn contents(&self) -> Result<InferTensorContents, HttpError> {
+        let mut values = vec![];
+        let mut contents = InferTensorContents::default();
+
+        flatten(&self.data, &mut values);
+
+        match self.datatype.as_str() {
+            "BOOL" => contents.bool_contents = self.parse(&values, Value::as_bool)?,
+            "INT8" => {
+                contents.int_contents = self.parse(&values, |value| {
+                    i8::try_from(value.as_i64()?).ok().map(i32::from)
+                })?
+            }
+            "INT16" => {
+                contents.int_contents = self.parse(&values, |value| {
+                    i16::try_from(value.as_i64()?).ok().map(i32::from)
+                })?
+            }
+            "INT32" => {
+                contents.int_contents =
+                    self.parse(&values, |value| i32::try_from(value.as_i64()?).ok())?
+            }
+            "INT64" => contents.int64_contents = self.parse(&values, Value::as_i64)?,
+            "UINT8" => {
+                contents.uint_contents = self.parse(&values, |value| {
+                    u8::try_from(value.as_u64()?).ok().map(u32::from)
+                })?
+            }
+            "UINT16" => {
+                contents.uint_contents = self.parse(&values, |value| {
+                    u16::try_from(value.as_u64()?).ok().map(u32::from)
+                })?
+            }
+            "UINT32" => {
+                contents.uint_contents =
+                    self.parse(&values, |value| u32::try_from(value.as_u64()?).ok())?
+            }
+            "UINT64" => contents.uint64_contents = self.parse(&values, Value::as_u64)?,
+            "FP32" => {
+                contents.fp32_contents =
+                    self.parse(&values, |value| value.as_f64().map(|value| value as f32))?
+            }
+            "FP64" => contents.fp64_contents = self.parse(&values, Value::as_f64)?,
+            "BYTES" => {
+                contents.bytes_contents = self.parse(&values, |value| {
+                    value.as_str().map(|value| value.as_bytes().to_vec())
+                })?
+            }
+            datatype => {
+                return Err(HttpError(Status::invalid_argument(format!(
+                    "input {}: datatype {} can't be sent as JSON",
+                    self.name, datatype
+                ))))
+            }
+        }
+
+        Ok(contents)
+    }
+
+    fn parse<T>(
+        &self,
+        values: &[&Value],
+        convert: impl Fn(&Value) -> Option<T>,
+    ) -> Result<Vec<T>, HttpError> {
+        values
+            .iter()
+            .map(|value| {
+                convert(value).ok_or_else(|| {
+                    HttpError(Status::invalid_argument(format!(
+                        "input {}: {} is not a valid {}",
+                        self.name, value, self.datatype
+                    )))
+                })
+            })
+            .collect()
+    }
+}
+
+fn flatten<'a>(value: &'a Value, values: &mut Vec<&'a Value>) {
+    match value {
+        Value::Array(items) => items.iter().for_each(|item| flatten(item, values)),
+        value => values.push(value),
+    }
+}
+
+#[allow(clippy::result_large_err)]
+impl TryFrom<model_infer_response::InferOutputTensor> for JsonOutputTensor {
+    type Error = HttpError;
+
+    fn try_from(output: model_infer_response::InferOutputTensor) -> Result<Self, HttpError> {
+        let contents = output.contents.unwrap_or_default();
+        let data = match output.datatype.as_str() {
+            "BOOL" => JsonTensorData::Bool(contents.bool_contents),
+            "INT8" | "INT16" | "INT32" => JsonTensorData::Int(contents.int_contents),
+            "INT64" => JsonTensorData::Int64(contents.int64_contents),
+            "UINT8" | "UINT16" | "UINT32" => JsonTensorData::Uint(contents.uint_contents),
+            "UINT64" => JsonTensorData::Uint64(contents.uint64_contents),
+            "FP32" => JsonTensorData::Fp32(contents.fp32_contents),
+            "FP64" => JsonTensorData::Fp64(contents.fp64_contents),
+            "BYTES" => JsonTensorData::Bytes(
+                contents
+                    .bytes_contents
+                    .iter()
+                    .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+                    .collect(),
+            ),
+            datatype => {
+                return Err(HttpError(Status::internal(format!(
+                    "output {}: datatype {} can't be sent as JSON",
+                    output.name, datatype
+                ))))
+            }
+        };
+
+        Ok(JsonOutputTensor {
+            name: output.name,
+            shape: output.shape,
+            datatype: output.datatype,
+            data,
+        })
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct JsonIndexRequest {
+    #[serde(default)]
+    ready: bool,
+}
+
+#[derive(Serialize)]
+struct JsonModelIndex {
+    name: String,
+    version: String,
+    state: String,
+    reason: String,
+}
+
+#[derive(Deserialize, Default)]
+struct JsonRepositoryRequest {
+    #[serde(default)]
+    parameters: HashMap<String, Value>,
+}
+
+#[allow(clippy::result_large_err)]
+impl JsonRepositoryRequest {
+    fn parameters(self) -> Result<HashMap<String, ModelRepositoryParameter>, HttpError> {
+        self.parameters
+            .into_iter()
+            .map(|(name, value)| {
+                let choice = match value {
+                    Value::Bool(value) => ParameterChoice::BoolParam(value),
+                    Value::Number(value) if value.is_i64() => {
+                        ParameterChoice::Int64Param(value.as_i64().unwrap_or_default())
+                    }
+                    Value::String(value) => ParameterChoice::StringParam(value),
+                    _ => {
+                        return Err(HttpError(Status::invalid_argument(format!(
+                            "parameter {} must be a boolean, integer or string",
+                            name
+                        ))))
+                    }
+                };
+                let parameter = ModelRepositoryParameter {
+                    parameter_choice: Some(choice),
+                };
+
+                Ok((name, parameter))
+            })
+            .collect()
+    }
+}
+
+/// Label to version, where an empty version removes the label.
+#[derive(Serialize, Deserialize, Default)]
+struct JsonLabels {
+    #[serde(default)]
+    labels: HashMap<String, String>,
+}
+
+/// Repository requests may leave out the body.
+#[allow(clippy::result_large_err)]
+fn optional_json<T: DeserializeOwned + Default>(body: &Bytes) -> Result<T, HttpError> {
+    match body.is_empty() {
+        true => Ok(T::default()),
+        false => serde_json::from_slice(body).map_err(|error| {
+            HttpError(Status::invalid_argument(format!(
+                "invalid request body: {}",
+                error
+            )))
+        }),
+    }
+}
+
+#[derive(Serialize)]
+struct JsonError {
+    error: String,
+}
+
+struct HttpError(Status);
+
+impl From<Status> for HttpError {
+    fn from(status: Status) -> Self {
+        HttpError(status)
+    }
+}
+
+impl IntoResponse for HttpError {
+    fn into_response(self) -> Response {
+        let status = match self.0.code() {
+            Code::InvalidArgument => StatusCode::BAD_REQUEST,
+            Code::NotFound => StatusCode::NOT_FOUND,
+            Code::FailedPrecondition => StatusCode::CONFLICT,
+            Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+            Code::PermissionDenied => StatusCode::FORBIDDEN,
+            Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+            Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (
+            status,
+            Json(JsonError {
+                error: self.0.message().to_string(),
+            }),
+        )
+            .into_response()
+    }
+}
+
+/// Health endpoints answer with the status code alone.
+fn health(ok: bool) -> StatusCode {
+    match ok {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
+
+async fn live(State(service): State<Service>, caller: Caller) -> Result<StatusCode, HttpError> {
+    let response = service
+        .server_live(request(ServerLiveRequest {}, caller))
+        .await?;
+
+    Ok(health(response.into_inner().live))
+}
+
+async fn ready(State(service): State<Service>, caller: Caller) -> Result<StatusCode, HttpError> {
+    let response = service
+        .server_ready(request(ServerReadyRequest {}, caller))
+        .await?;
+
+    Ok(health(response.into_inner().ready))
+}
+
+async fn server_metadata(
+    State(service): State<Service>,
+    caller: Caller,
+) -> Result<Json<JsonServerMetadata>, HttpError> {
+    let metadata = service
+        .server_metadata(request(ServerMetadataRequest {}, caller))
+        .await?
+        .into_inner();
+
+    Ok(Json(JsonServerMetadata {
+        name: metadata.name,
+        version: metadata.version,
+        extensions: metadata.extensions,
+    }))
+}
+
+async fn model_ready(
+    State(service): State<Service>,
+    Path(name): Path<String>,
+    caller: Caller,
+) -> Result<StatusCode, HttpError> {
+    versioned_model_ready(State(service), Path((name, "".to_string())), caller).await
+}
+
+async fn versioned_model_ready(
+    State(service): State<Service>,
+    Path((name, version)): Path<(String, String)>,
+    caller: Caller,
+) -> Result<StatusCode, HttpError> {
+    let response = service
+        .model_ready(request(ModelReadyRequest { name, version }, caller))
+        .await?;
+
+    Ok(health(response.into_inner().ready))
+}
+
+async fn model_metadata(
+    State(service): State<Service>,
+    Path(name): Path<String>,
+    caller: Caller,
+) -> Result<Json<JsonModelMetadata>, HttpError> {
+    versioned_model_metadata(State(service), Path((name, "".to_string())), caller).await
+}
+
+async fn versioned_model_metadata(
+    State(service): State<Service>,
+    Path((name, version)): Path<(String, String)>,
+    caller: Caller,
+) -> Result<Json<JsonModelMetadata>, HttpError> {
+    let metadata = service
+        .model_metadata(request(ModelMetadataRequest { name, version }, caller))
+        .await?
+        .into_inner();
+
+    Ok(Json(JsonModelMetadata {
+        name: metadata.name,
+        versions: metadata.versions,
+        platform: metadata.platform,
+        inputs: metadata.inputs.into_iter().map(Into::into).collect(),
+        outputs: metadata.outputs.into_iter().map(Into::into).collect(),
+    }))
+}
+
+async fn all_model_statistics(
+    State(service): State<Service>,
+    caller: Caller,
+) -> Result<Json<JsonStatistics>, HttpError> {
+    versioned_model_statistics(
+        State(service),
+        Path(("".to_string(), "".to_string())),
+        caller,
+    )
+    .await
+}
+
+async fn model_statistics(
+    State(service): State<Service>,
+    Path(name): Path<String>,
+    caller: Caller,
+) -> Result<Json<JsonStatistics>, HttpError> {
+    versioned_model_statistics(State(service), Path((name, "".to_string())), caller).await
+}
+
+async fn versioned_model_statistics(
+    State(service): State<Service>,
+    Path((name, version)): Path<(String, String)>,
+    caller: Caller,
+) -> Result<Json<JsonStatistics>, HttpError> {
+    let statistics = service
+        .model_statistics(request(ModelStatisticsRequest { name, version }, caller))
+        .await?
+        .into_inner();
+
+    Ok(Json(JsonStatistics {
+        model_stats: statistics.model_stats.into_iter().map(Into::into).collect(),
+    }))
+}
+
+async fn infer(
+    State(service): State<Service>,
+    Path(name): Path<String>,
+    caller: Caller,
+    body: Bytes,
+) -> Result<Json<JsonInferResponse>, HttpError> {
+    versioned_infer(State(service), Path((name, "".to_string())), caller, body).await
+}
+
+async fn versioned_infer(
+    State(service): State<Service>,
+    Path((name, version)): Path<(String, String)>,
+    caller: Caller,
+    body: Bytes,
+) -> Result<Json<JsonInferResponse>, HttpError> {
+    let body: JsonInferRequest = optional_json(&body)?;
+    let response = service
+        .model_infer(request(body.into_proto(name, version)?, caller))
+        .await?
+        .into_inner();
+
+    Ok(Json(JsonInferResponse {
+        model_name: response.model_name,
+        model_version: response.model_version,
+        id: response.id,
+        outputs: response
+            .outputs
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()?,
+    }))
+}
+
+async fn repository_index(
+    State(service): State<Service>,
+    caller: Caller,
+    body: Bytes,
+) -> Result<Json<Vec<JsonModelIndex>>, HttpError> {
+    let body: JsonIndexRequest = optional_json(&body)?;
+    let index = service
+        .repository_index(request(
+            RepositoryIndexRequest {
+                repository_name: "".to_string(),
+                ready: body.ready,
+            },
+            caller,
+        ))
+        .await?
+        .into_inner();
+
+    Ok(Json(
+        index
+            .models
+            .into_iter()
+            .map(|model| JsonModelIndex {
+                name: model.name,
+                version: model.version,
+                state: model.state,
+                reason: model.reason,
+            })
+            .collect(),
+    ))
+}
+
+/// A `config` parameter replaces every numbered version of the model, see
+/// [`Repository::load`](crate::repository::Repository::load).
+async fn load_model(
+    State(service): State<Service>,
+    Path(model_name): Path<String>,
+    caller: Caller,
+    body: Bytes,
+) -> Result<StatusCode, HttpError> {
+    let body: JsonRepositoryRequest = optional_json(&body)?;
+
+    service
+        .repository_model_load(request(
+            RepositoryModelLoadRequest {
+                repository_name: "".to_string(),
+                model_name,
+                parameters: body.parameters()?,
+            },
+            caller,
+        ))
+        .await?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn unload_model(
+    State(service): State<Service>,
+    Path(model_name): Path<String>,
+    caller: Caller,
+    body: Bytes,
+) -> Result<StatusCode, HttpError> {
+    let body: JsonRepositoryRequest = optional_json(&body)?;
+
+    service
+        .repository_model_unload(request(
+            RepositoryModelUnloadRequest {
+                repository_name: "".to_string(),
+                model_name,
+                parameters: body.parameters()?,
+            },
+            caller,
+        ))
+        .await?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn model_labels(
+    State(service): State<Service>,
+    Path(name): Path<String>,
+    caller: Caller,
+) -> Result<Json<JsonLabels>, HttpError> {
+    set_model_labels(State(service), Path(name), caller, Bytes::new()).await
+}
+
+async fn set_model_labels(
+    State(service): State<Service>,
+    Path(name): Path<String>,
+    caller: Caller,
+    body: Bytes,
+) -> Result<Json<JsonLabels>, HttpError> {
+    let body: JsonLabels = optional_json(&body)?;
+    let response = service
+        .model_version_labels(request(
+            ModelVersionLabelsRequest {
+                name,
+                labels: body.labels,
+            },
+            caller,
+        ))
+        .await?
+        .into_inner();
+
+    Ok(Json(JsonLabels {
+        labels: response.labels,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::time::{Duration, Instant};
+
+    use axum::body::Body;
+    use axum::http::Request as HttpRequest;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::auth::AuthConfig;
+    use crate::repository::tests::{model_config, repository};
+
+    async fn service() -> GrpcInferenceServiceImpl {
+        let repository = repository(Arc::new(AtomicBool::new(false)));
+
+        repository
+            .load("resnet", Some(model_config("resnet", "model.pt")))
+            .await
+            .unwrap();
+
+        GrpcInferenceServiceImpl::with_repository(Arc::new(repository))
+    }
+
+    async fn send(app: Router, request: HttpRequest<Body>) -> (StatusCode, String) {
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    async fn get(uri: &str) -> (StatusCode, String) {
+        let app = router(Arc::new(service().await));
+
+        send(app, HttpRequest::get(uri).body(Body::empty()).unwrap()).await
+    }
+
+    #[tokio::test]
+    async fn test_health() {
+        assert_eq!(StatusCode::OK, get("/v2/health/live").await.0);
+        assert_eq!(StatusCode::OK, get("/v2/health/ready").await.0);
+        assert_eq!(StatusCode::OK, get("/v2/models/resnet/ready").await.0);
+        assert_eq!(
+            StatusCode::SERVICE_UNAVAILABLE,
+            get("/v2/models/resnet/versions/1/ready").await.0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_required_models() {
+        let repository = Arc::new(repository(Arc::new(AtomicBool::new(false))));
+        let service = GrpcInferenceServiceImpl::with_repository(repository.clone())
+            .with_required_models(vec!["resnet".to_string(), "bert".to_string()]);
+        let app = router(Arc::new(service));
+        let ready = || {
+            send(
+                app.clone(),
+                HttpRequest::get("/v2/health/ready")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, ready().await.0);
+
+        repository
+            .load("resnet", Some(model_config("resnet", "model.pt")))
+            .await
+            .unwrap();
+        repository
+            .load("bert", Some(model_config("bert", "cold")))
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, ready().await.0);
+
+        repository
+            .load("bert", Some(model_config("bert", "model.pt")))
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, ready().await.0);
+
+        repository.unload("resnet").unwrap();
+
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, ready().await.0);
+    }
+
+    #[tokio::test]
+    async fn test_stuck_liveness() {
+        let service = Arc::new(
+            service()
+                .await
+                .with_stuck_timeout(Duration::from_millis(50)),
+        );
+        let app = router(service.clone());
+        let live = || {
+            send(
+                app.clone(),
+                HttpRequest::get("/v2/health/live")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+        let request = service.in_flight.start(Instant::now());
+
+        assert_eq!(StatusCode::OK, live().await.0);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, live().await.0);
+
+        drop(request);
+
+        assert_eq!(StatusCode::OK, live().await.0);
+    }
+
+    #[tokio::test]
+    async fn test_model_metadata() {
+        let (status, body) = get("/v2/models/resnet").await;
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(
+            r#"{"name":"resnet","versions":[],"platform":"pytorch_libtorch","inputs":[{"name":"input","datatype":"FP32","shape":[-1,3,224,224]}],"outputs":[]}"#,
+            body
+        );
+    }
+
+    #[tokio::test]
+    async fn test_model_statistics() {
+        let (status, body) = get("/v2/models/resnet/stats").await;
+        let zero = r#"{"count":0,"ns":0}"#;
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(
+            format!(
+                r#"{{"model_stats":[{{"name":"resnet","version":"","last_inference":0,"inference_count":0,"execution_count":0,"inference_stats":{{"success":{0},"fail":{0},"queue":{0},"compute_input":{0},"compute_infer":{0},"compute_output":{0}}},"batch_stats":[]}}]}}"#,
+                zero
+            ),
+            body
+        );
+        assert_eq!(body, get("/v2/models/stats").await.1);
+        assert_eq!(StatusCode::NOT_FOUND, get("/v2/models/bert/stats").await.0);
+    }
+
+    #[tokio::test]
+    async fn test_infer() {
+        let app = router(Arc::new(service().await));
+        let post = |uri: &str, body: &str| {
+            let request = HttpRequest::post(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+
+            send(app.clone(), request)
+        };
+
+        assert_eq!(
+            (
+                StatusCode::OK,
+                r#"{"model_name":"resnet","model_version":"","id":"1","outputs":[{"name":"input","shape":[2,2],"datatype":"FP32","data":[0.1,2.0,3.0,4.0]},{"name":"tokens","shape":[1],"datatype":"BYTES","data":["cat"]}]}"#
+                    .to_string()
+            ),
+            post(
+                "/v2/models/resnet/infer",
+                r#"{"id":"1","inputs":[{"name":"input","shape":[2,2],"datatype":"FP32","data":[[0.1,2],[3,4]]},{"name":"tokens","shape":[1],"datatype":"BYTES","data":["cat"]}]}"#
+            )
+            .await
+        );
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            post(
+                "/v2/models/resnet/infer",
+                r#"{"inputs":[{"name":"input","shape":[1],"datatype":"INT8","data":[300]}]}"#
+            )
+            .await
+            .0
+        );
+        assert_eq!(
+            StatusCode::NOT_FOUND,
+            post("/v2/models/resnet/versions/1/infer", r#"{"inputs":[]}"#)
+                .await
+                .0
+        );
+        assert_eq!(
+            StatusCode::NOT_FOUND,
+            post("/v2/models/bert/infer", r#"{"inputs":[]}"#).await.0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_repository() {
+        let app = router(Arc::new(service().await));
+        let post = |uri: &str, body: &str| {
+            let request = HttpRequest::post(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+
+            send(app.clone(), request)
+        };
+        let get = |uri: &str| {
+            send(
+                app.clone(),
+                HttpRequest::get(uri).body(Body::empty()).unwrap(),
+            )
+        };
+
+        assert_eq!(
+            StatusCode::OK,
+            post("/v2/repository/models/resnet/unload", "").await.0
+        );
+        assert_eq!(
+            StatusCode::SERVICE_UNAVAILABLE,
+            get("/v2/models/resnet").await.0
+        );
+        assert_eq!(
+            StatusCode::NOT_FOUND,
+            post("/v2/repository/models/bert/load", "").await.0
+        );
+        assert_eq!(
+            StatusCode::OK,
+            post(
+                "/v2/repository/models/bert/load",
+                r#"{"parameters": {"config": "{\"base_path\": \"bert.pt\"}"}}"#
+            )
+            .await
+            .0
+        );
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            post(
+                "/v2/repository/models/bert/load",
+                r#"{"parameters": {"config": 1}}"#
+            )
+            .await
+            .0
+        );
+        assert_eq!(
+            (
+                StatusCode::OK,
+                r#"[{"name":"bert","version":"","state":"READY","reason":""},{"name":"resnet","version":"","state":"UNAVAILABLE","reason":""}]"#.to_string()
+            ),
+            post("/v2/repository/index", "").await
+        );
+        assert_eq!(
+            r#"[{"name":"bert","version":"","state":"READY","reason":""}]"#,
+            post("/v2/repository/index", r#"{"ready": true}"#).await.1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_versions_and_labels() {
+        let repository = repository(Arc::new(AtomicBool::new(false)));
+
+        for version in ["1", "2"] {
+            repository.add(model_config("resnet", "model.pt"), version);
+            repository.load_version("resnet", version).await.unwrap();
+        }
+
+        let app = router(Arc::new(GrpcInferenceServiceImpl::with_repository(
+            Arc::new(repository),
+        )));
+        let get = |uri: &str| {
+            send(
+                app.clone(),
+                HttpRequest::get(uri).body(Body::empty()).unwrap(),
+            )
+        };
+        let post = |uri: &str, body: &str| {
+            let request = HttpRequest::post(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+
+            send(app.clone(), request)
+        };
+
+        assert_eq!(
+            (StatusCode::OK, r#"{"labels":{}}"#.to_string()),
+            get("/v2/models/resnet/labels").await
+        );
+        assert_eq!(
+            (StatusCode::OK, r#"{"labels":{"stable":"1"}}"#.to_string()),
+            post("/v2/models/resnet/labels", r#"{"labels":{"stable":"1"}}"#).await
+        );
+        assert_eq!(
+            StatusCode::NOT_FOUND,
+            post("/v2/models/resnet/labels", r#"{"labels":{"canary":"3"}}"#)
+                .await
+                .0
+        );
+
+        let (status, body) = get("/v2/models/resnet/versions/stable").await;
+
+        assert_eq!(StatusCode::OK, status);
+        assert!(body.contains(r#""versions":["1","2"]"#));
+        assert_eq!(
+            r#"["1"]"#,
+            serde_json::from_str::<Value>(&get("/v2/models/resnet/versions/stable/stats").await.1)
+                .unwrap()["model_stats"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|stats| stats["version"].clone())
+                .collect::<Value>()
+                .to_string()
+        );
+        assert_eq!(
+            StatusCode::NOT_FOUND,
+            get("/v2/models/resnet/versions/3").await.0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_auth() {
+        let config: AuthConfig = toml::from_str(
+            r#"
+            exempt_health = true
+            admins = ["deployer"]
+
+            [[tokens]]
+            principal = "deployer"
+            token = "d3ploy"
+
+            [[tokens]]
+            principal = "batch-jobs"
+            token = "s3cret"
+
+            [[tokens]]
+            principal = "dashboards"
+            token = "0ther"
+
+            [allow]
+            resnet = ["batch-jobs"]
+            "#,
+        )
+        .unwrap();
+        let app = router(Arc::new(
+            service().await.with_auth(Auth::new(config).unwrap()),
+        ));
+        let get = |uri: &str, token: Option<&str>| {
+            let mut request = HttpRequest::get(uri);
+
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+
+            send(app.clone(), request.body(Body::empty()).unwrap())
+        };
+        let post = |uri: &str, token: &str| {
+            let request = HttpRequest::post(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap();
+
+            send(app.clone(), request)
+        };
+        let set_labels = |token: &str| {
+            let request = HttpRequest::post("/v2/models/resnet/labels")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"labels":{"stable":""}}"#))
+                .unwrap();
+
+            send(app.clone(), request)
+        };
+
+        assert_eq!(StatusCode::OK, get("/v2/health/live", None).await.0);
+        assert_eq!(
+            StatusCode::UNAUTHORIZED,
+            get("/v2/models/resnet", None).await.0
+        );
+        assert_eq!(
+            StatusCode::UNAUTHORIZED,
+            get("/v2/health/live", Some("wrong")).await.0
+        );
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            get("/v2/models/resnet", Some("0ther")).await.0
+        );
+        assert_eq!(
+            StatusCode::OK,
+            get("/v2/models/resnet", Some("s3cret")).await.0
+        );
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            post("/v2/models/resnet/infer", "0ther").await.0
+        );
+        assert_eq!(
+            StatusCode::OK,
+            post("/v2/models/resnet/infer", "s3cret").await.0
+        );
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            post("/v2/repository/index", "s3cret").await.0
+        );
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            post("/v2/repository/models/resnet/unload", "s3cret")
+                .await
+                .0
+        );
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            post("/v2/repository/models/bert/load", "s3cret").await.0
+        );
+        assert_eq!(
+            StatusCode::OK,
+            get("/v2/models/resnet/labels", Some("s3cret")).await.0
+        );
+        assert_eq!(StatusCode::FORBIDDEN, set_labels("s3cret").await.0);
+        assert_eq!(StatusCode::OK, set_labels("d3ploy").await.0);
+        assert_eq!(
+            StatusCode::OK,
+            post("/v2/repository/index", "d3ploy").await.0
+        );
+        assert_eq!(
+            StatusCode::OK,
+            post("/v2/repository/models/resnet/unload", "d3ploy")
+                .await
+                .0
+        );
+    }
+}
@@ -1,58 +1,288 @@
-use ferrix_model_api::{Model, ModelResult, internal::{InferRequest, InferResponse}};
-use ferrix_python_hooks::{eval, postprocess, preprocess};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use ferrix_model_api::{
+    internal::{InferRequest, InferResponse},
+    Model, ModelConfig, ModelError, ModelResult,
+};
+use ferrix_python_hooks::error::HookError;
+use ferrix_python_hooks::interpreter::{configure, validate};
+use ferrix_python_hooks::Handler;
+use tokio::task::JoinHandle;
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::metrics::{Metrics, RequestMetrics, MODEL, POSTPROCESS, PREPROCESS};
+use crate::statistics::Statistics;
+use crate::watch::watch_file;
 
 pub struct Inference {
-    hooks_enabled: bool,
+    /// This model's own evaluation of the handler.
+    handler: Option<Arc<Handler>>,
+    handler_path: Option<String>,
+    expose_hook_errors: bool,
+    model_config: ModelConfig,
+    version: String,
     model: Box<dyn Model>,
+    metrics: Option<Metrics>,
+    statistics: Statistics,
+    watcher: Option<JoinHandle<()>>,
 }
 
 pub struct InferenceConfig {
+    /// Passed to the handler's `setup` hook.
+    pub model_config: ModelConfig,
     pub handler_path: Option<String>,
+    /// Include the Python exception message in the status returned to clients.
+    /// The full traceback is only ever logged server-side.
+    pub expose_hook_errors: bool,
 }
 
 impl Inference {
-    pub fn new(config: InferenceConfig, model: Box<dyn Model>) -> Self {
-        let hooks_enabled = config.handler_path.is_some();
+    pub fn new(config: InferenceConfig, model: Box<dyn Model>) -> ModelResult<Self> {
+        let mut handler = None;
+
+        if let Some(handler_path) = &config.handler_path {
+            let code = std::fs::read_to_string(handler_path).map_err(|error| {
+                ModelError::Hook(format!(
+                    "failed to read handler {}: {}",
+                    handler_path, error
+                ))
+            })?;
+            let python_config = config.model_config.python.clone().unwrap_or_default();
+
+            configure(&python_config)?;
+            info!("{}", validate(&code, handler_path)?);
 
-        if hooks_enabled {
-            let handler_path = config.handler_path.unwrap();
-            let code = std::fs::read_to_string(handler_path).unwrap();
+            match Handler::load(code, handler_path, &config.model_config) {
+                Ok(loaded) => handler = Some(Arc::new(loaded)),
+                Err(error) => {
+                    if let Some(traceback) = error.traceback() {
+                        error!("{}", traceback);
+                    }
 
-            println!("BuTt");
-            eval(code);
-            println!("SnaKe");
+                    return Err(error.into());
+                }
+            }
         }
 
-        Inference {
-            hooks_enabled,
+        Ok(Inference {
+            handler,
+            handler_path: config.handler_path,
+            expose_hook_errors: config.expose_hook_errors,
+            model_config: config.model_config,
+            version: "".to_string(),
             model,
+            metrics: None,
+            statistics: Statistics::default(),
+            watcher: None,
+        })
+    }
+
+    /// Reported with metrics and statistics. Models without versions use "".
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = version.to_string();
+        self
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Records load and request metrics, see [`Metrics`].
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.metrics.as_ref()
+    }
+
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    /// Re-evaluates the handler whenever the file changes. Requests keep using
+    /// the previous hooks until the new handler imports successfully. The
+    /// watcher stops when the model is dropped. Must run inside a Tokio
+    /// runtime.
+    pub fn watch_handler(mut self, interval: Duration) -> Self {
+        let model_config = self.model_config.clone();
+
+        if let (Some(handler), Some(path)) = (&self.handler, &self.handler_path) {
+            let handler = Arc::downgrade(handler);
+
+            self.watcher = Some(watch_file(PathBuf::from(path), interval, move |path| {
+                reload_handler(path, &handler, &model_config)
+            }));
         }
+
+        self
+    }
+
+    pub fn model_config(&self) -> &ModelConfig {
+        &self.model_config
     }
 
     pub fn load(&mut self) -> ModelResult<()> {
-        self.model.load()
+        let started = Instant::now();
+        let result = self.model.load();
+
+        if let Some(metrics) = &self.metrics {
+            metrics.model_loaded(
+                &self.model_config.model_name,
+                &self.version,
+                started.elapsed(),
+                result.is_ok(),
+            );
+        }
+
+        result
     }
 
     pub fn loaded(&self) -> bool {
         self.model.loaded()
     }
 
-    pub async fn predict(
+    pub async fn predict(&self, request: InferRequest) -> ModelResult<InferResponse> {
+        self.predict_since(request, Instant::now()).await
+    }
+
+    /// Like [`Inference::predict`], for a request that arrived at `received`.
+    /// The time since then is reported as queue time.
+    pub async fn predict_since(
         &self,
         request: InferRequest,
+        received: Instant,
     ) -> ModelResult<InferResponse> {
-        let input = match self.hooks_enabled {
-            true => preprocess(request)?,
-            false => request,
+        let span = info_span!(
+            "infer",
+            request_id = %request.id,
+            model = %self.model_config.model_name,
+            version = %self.version,
+        );
+
+        let result = self
+            .run_stages(request, received)
+            .instrument(span.clone())
+            .await;
+
+        if let Err(error) = &result {
+            span.in_scope(|| warn!("Inference failed: {:#}", error));
+        }
+
+        result
+    }
+
+    async fn run_stages(
+        &self,
+        request: InferRequest,
+        received: Instant,
+    ) -> ModelResult<InferResponse> {
+        let mut metrics = RequestMetrics::start(
+            self.metrics.as_ref(),
+            &self.statistics,
+            &self.model_config.model_name,
+            &self.version,
+            received,
+            &request,
+        );
+
+        let input = match &self.handler {
+            Some(handler) => metrics
+                .stage(
+                    PREPROCESS,
+                    handler
+                        .preprocess(request)
+                        .instrument(info_span!(PREPROCESS)),
+                )
+                .await
+                .map_err(|error| self.hook_error(error))?,
+            None => request,
         };
 
-        let response = self.model.predict(&input)?;
+        let response = metrics
+            .stage(
+                MODEL,
+                async { self.model.predict(&input) }.instrument(info_span!(MODEL)),
+            )
+            .await?;
 
-        let output = match self.hooks_enabled {
-            true => postprocess(response)?,
-            false => response,
+        let output = match &self.handler {
+            Some(handler) => metrics
+                .stage(
+                    POSTPROCESS,
+                    handler
+                        .postprocess(response)
+                        .instrument(info_span!(POSTPROCESS)),
+                )
+                .await
+                .map_err(|error| self.hook_error(error))?,
+            None => response,
         };
 
+        metrics.succeeded();
+
         Ok(output)
     }
+
+    fn hook_error(&self, error: HookError) -> anyhow::Error {
+        error!("{}", error.describe());
+
+        let message = match (self.expose_hook_errors, &error) {
+            (true, _) => error.to_string(),
+            (false, HookError::Raised { stage, .. }) => format!("{} hook failed", stage),
+            (false, HookError::InvalidReturn { stage, .. }) => format!("{} hook failed", stage),
+            (false, _) => "handler failed to load".to_string(),
+        };
+
+        anyhow!(ModelError::Hook(message))
+    }
+}
+
+/// Runs once the last request holding the model has finished.
+impl Drop for Inference {
+    fn drop(&mut self) {
+        if let Some(watcher) = &self.watcher {
+            watcher.abort();
+        }
+
+        if let Err(error) = self.model.unload() {
+            error!(model = %self.model_config.model_name, "Unloading model failed: {:#}", error);
+        }
+
+        if let Some(handler) = &self.handler {
+            if let Err(error) = handler.shutdown() {
+                error!("{}", error.describe());
+            }
+        }
+    }
+}
+
+/// Returns false once the model, and with it the handler, is gone.
+fn reload_handler(path: &Path, handler: &Weak<Handler>, model_config: &ModelConfig) -> bool {
+    let Some(handler) = handler.upgrade() else {
+        return false;
+    };
+    let handler_path = path.display().to_string();
+    let result = std::fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|code| {
+            handler
+                .reload(code, &handler_path, model_config)
+                .map_err(|error| error.describe())
+        });
+
+    match result {
+        Ok(()) => info!(handler = %handler_path, "Reloaded handler"),
+        Err(error) => warn!(
+            handler = %handler_path,
+            "Reloading handler failed, keeping previous version: {}",
+            error
+        ),
+    }
+
+    true
 }
@@ -0,0 +1,387 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::warn;
+
+/// Allowed clock skew when checking `exp` and `nbf`.
+const LEEWAY_SECS: u64 = 60;
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct JwtConfig {
+    /// JSON Web Key Set holding the keys tokens may be signed with.
+    pub jwks: PathBuf,
+    /// Required `iss` claim, if set.
+    pub issuer: Option<String>,
+    /// Required `aud` claim, if set.
+    pub audience: Option<String>,
+}
+
+/// Checks RS256, ES256, ES384 and EdDSA signed tokens against a local JWKS
+/// and returns the `sub` claim.
+pub struct JwtValidator {
+    keys: Vec<Key>,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+struct Key {
+    kid: Option<String>,
+    material: KeyMaterial,
+}
+
+enum KeyMaterial {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    EcP256(Vec<u8>),
+    EcP384(Vec<u8>),
+    Ed25519(Vec<u8>),
+}
+
+#[derive(Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    crv: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+impl JwtValidator {
+    pub fn new(config: &JwtConfig) -> anyhow::Result<Self> {
+        Ok(JwtValidator {
+            keys: read_jwks(&config.jwks)?,
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
+        })
+    }
+
+    pub fn validate(&self, token: &str) -> anyhow::Result<String> {
+        let mut parts = token.split('.');
+        let (Some(header), Some(claims), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            bail!("malformed token");
+        };
+        let signed = &token[..header.len() + 1 + claims.len()];
+        let header: Header = serde_json::from_slice(&decode(header)?)?;
+        let signature = decode(signature)?;
+        let verified = self
+            .keys
+            .iter()
+            .filter(|key| header.kid.is_none() || key.kid == header.kid)
+            .any(|key| key.verify(&header.alg, signed.as_bytes(), &signature));
+
+        if !verified {
+            bail!("invalid signature");
+        }
+
+        let claims: Value = serde_json::from_slice(&decode(claims)?)?;
+
+        self.check_claims(&claims, now())?;
+
+        claims["sub"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("token has no subject"))
+    }
+
+    fn check_claims(&self, claims: &Value, now: u64) -> anyhow::Result<()> {
+        let expires = claims["exp"]
+            .as_u64()
+            .ok_or_else(|| anyhow!("token has no expiry"))?;
+
+        if expires.saturating_add(LEEWAY_SECS) < now {
+            bail!("token expired");
+        }
+
+        if let Some(not_before) = claims["nbf"].as_u64() {
+            if not_before > now.saturating_add(LEEWAY_SECS) {
+                bail!("token not yet valid");
+            }
+        }
+
+        if let Some(issuer) = &self.issuer {
+            if claims["iss"].as_str() != Some(issuer.as_str()) {
+                bail!("unexpected issuer");
+            }
+        }
+
+        if let Some(audience) = &self.audience {
+            let matches = match &claims["aud"] {
+                Value::String(aud) => aud == audience,
+                Value::Array(auds) => auds.iter().any(|aud| aud.as_str() == Some(audience)),
+                _ => false,
+            };
+
+            if !matches {
+                bail!("unexpected audience");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Key {
+    fn verify(&self, alg: &str, message: &[u8], signature: &[u8]) -> bool {
+        let result = match (alg, &self.material) {
+            ("RS256", KeyMaterial::Rsa { n, e }) => RsaPublicKeyComponents { n, e }.verify(
+                &signature::RSA_PKCS1_2048_8192_SHA256,
+                message,
+                signature,
+            ),
+            ("ES256", KeyMaterial::EcP256(point)) => {
+                UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                    .verify(message, signature)
+            }
+            ("ES384", KeyMaterial::EcP384(point)) => {
+                UnparsedPublicKey::new(&signature::ECDSA_P384_SHA384_FIXED, point)
+                    .verify(message, signature)
+            }
+            ("EdDSA", KeyMaterial::Ed25519(key)) => {
+                UnparsedPublicKey::new(&signature::ED25519, key).verify(message, signature)
+            }
+            _ => return false,
+        };
+
+        result.is_ok()
+    }
+}
+
+fn read_jwks(path: &Path) -> anyhow::Result<Vec<Key>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let set: JwkSet = serde_json::from_str(&contents)
+        .with_context(|| format!("{} is not a JSON Web Key Set", path.display()))?;
+    let keys: Vec<Key> = set
+        .keys
+        .into_iter()
+        .filter_map(|jwk| match key(&jwk) {
+            Ok(key) => Some(key),
+            Err(error) => {
+                warn!(
+                    kid = jwk.kid.as_deref(),
+                    jwks = %path.display(),
+                    "Skipping key: {:#}",
+                    error
+                );
+                None
+            }
+        })
+        .collect();
+
+    match keys.is_empty() {
+        true => bail!("no usable keys in {}", path.display()),
+        false => Ok(keys),
+    }
+}
+
+fn key(jwk: &Jwk) -> anyhow::Result<Key> {
+    let field = |value: &Option<String>, name: &str| {
+        value
+            .as_deref()
+            .ok_or_else(|| anyhow!("missing {}", name))
+            .and_then(decode)
+    };
+    let material = match (jwk.kty.as_str(), jwk.crv.as_deref()) {
+        ("RSA", _) => KeyMaterial::Rsa {
+            n: field(&jwk.n, "n")?,
+            e: field(&jwk.e, "e")?,
+        },
+        ("EC", Some(crv @ ("P-256" | "P-384"))) => {
+            // Uncompressed SEC1 point, as ring expects.
+            let mut point = vec![0x04];
+
+            point.extend(field(&jwk.x, "x")?);
+            point.extend(field(&jwk.y, "y")?);
+
+            match crv {
+                "P-256" => KeyMaterial::EcP256(point),
+                _ => KeyMaterial::EcP384(point),
+            }
+        }
+        ("OKP", Some("Ed25519")) => KeyMaterial::Ed25519(field(&jwk.x, "x")?),
+        (kty, crv) => bail!("unsupported key type {} {}", kty, crv.unwrap_or("")),
+    };
+
+    Ok(Key {
+        kid: jwk.kid.clone(),
+        material,
+    })
+}
+
+fn decode(value: &str) -> anyhow::Result<Vec<u8>> {
+    URL_SAFE_NO_PAD
+        .decode(value)
+        .map_err(|_| anyhow!("invalid base64url"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+    use serde_json::json;
+
+    use super::*;
+
+    /// An ES256 key pair written as a JWKS, for signing test tokens.
+    pub(crate) struct Signer {
+        key_pair: EcdsaKeyPair,
+        pub(crate) jwks: PathBuf,
+    }
+
+    impl Signer {
+        pub(crate) fn new(name: &str) -> Self {
+            let random = SystemRandom::new();
+            let pkcs8 =
+                EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &random).unwrap();
+            let key_pair =
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &random)
+                    .unwrap();
+            let point = key_pair.public_key().as_ref();
+            let jwks = std::env::temp_dir().join(format!(
+                "ferrix-{}-{}.jwks.json",
+                name,
+                std::process::id()
+            ));
+
+            std::fs::write(
+                &jwks,
+                json!({"keys": [{
+                    "kty": "EC",
+                    "kid": "test",
+                    "crv": "P-256",
+                    "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+                    "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+                }]})
+                .to_string(),
+            )
+            .unwrap();
+
+            Signer { key_pair, jwks }
+        }
+
+        pub(crate) fn sign(&self, claims: Value) -> String {
+            let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256","kid":"test"}"#);
+            let claims = URL_SAFE_NO_PAD.encode(claims.to_string());
+            let signed = format!("{}.{}", header, claims);
+            let signature = self
+                .key_pair
+                .sign(&SystemRandom::new(), signed.as_bytes())
+                .unwrap();
+
+            format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature.as_ref()))
+        }
+    }
+
+    fn validator(signer: &Signer) -> JwtValidator {
+        JwtValidator::new(&JwtConfig {
+            jwks: signer.jwks.clone(),
+            issuer: Some("https://auth.example.com".to_string()),
+            audience: Some("ferrix".to_string()),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate() {
+        let signer = Signer::new("validate");
+        let validator = validator(&signer);
+        let claims = |exp: u64| {
+            json!({
+                "sub": "alice",
+                "iss": "https://auth.example.com",
+                "aud": ["other", "ferrix"],
+                "exp": exp,
+            })
+        };
+
+        assert_eq!(
+            "alice",
+            validator
+                .validate(&signer.sign(claims(now() + 300)))
+                .unwrap()
+        );
+        assert_eq!(
+            "token expired",
+            validator
+                .validate(&signer.sign(claims(now() - 300)))
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "alice",
+            validator.validate(&signer.sign(claims(u64::MAX))).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rejects_tampered_and_foreign_tokens() {
+        let signer = Signer::new("tampered");
+        let validator = validator(&signer);
+        let token = signer.sign(json!({
+            "sub": "alice",
+            "iss": "https://auth.example.com",
+            "aud": "ferrix",
+            "exp": now() + 300,
+        }));
+        let parts: Vec<&str> = token.split('.').collect();
+        let forged_claims = URL_SAFE_NO_PAD.encode(
+            json!({
+                "sub": "admin",
+                "iss": "https://auth.example.com",
+                "aud": "ferrix",
+                "exp": now() + 300,
+            })
+            .to_string(),
+        );
+        let forged = format!("{}.{}.{}", parts[0], forged_claims, parts[2]);
+        let foreign = Signer::new("foreign").sign(json!({"sub": "alice", "exp": now() + 300}));
+
+        assert!(validator.validate(&token).is_ok());
+        assert!(validator.validate(&forged).is_err());
+        assert!(validator.validate(&foreign).is_err());
+        assert!(validator.validate("not.a-token").is_err());
+    }
+
+    #[test]
+    fn test_checks_issuer_and_audience() {
+        let signer = Signer::new("claims");
+        let validator = validator(&signer);
+        let token = |iss: &str, aud: &str| {
+            signer.sign(json!({"sub": "alice", "iss": iss, "aud": aud, "exp": now() + 300}))
+        };
+
+        assert!(validator
+            .validate(&token("https://evil.example.com", "ferrix"))
+            .is_err());
+        assert!(validator
+            .validate(&token("https://auth.example.com", "other"))
+            .is_err());
+    }
+}
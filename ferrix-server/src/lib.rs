@@ -1,52 +1,229 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use auth::{Access, Auth, Principal};
 use ferrix_model_api::internal::InferRequest;
-use inference::Inference;
-use tonic::Response;
+use repository::Repository;
+use tonic::{Response, Status};
+use tracing::{info_span, warn, Instrument};
 
-use ferrix_model_api::Model;
-use ferrix_protos::grpc_inference_service_server::{
-    GrpcInferenceService, GrpcInferenceServiceServer,
-};
+use ferrix_model_api::{Model, ModelConfig, ModelError, TensorSpec};
+use ferrix_protos::grpc_inference_service_server::GrpcInferenceService;
+use ferrix_protos::model_metadata_response::TensorMetadata;
+use ferrix_protos::model_repository_parameter::ParameterChoice;
 use ferrix_protos::*;
-use tonic::transport::Server;
 
+pub mod auth;
+pub mod directory;
+pub mod http;
 pub mod inference;
+mod jwt;
+pub mod listen;
+pub mod metrics;
+pub mod repository;
+mod server;
+pub mod statistics;
+pub mod storage;
+mod telemetry;
+pub mod tls;
+pub mod watch;
+
+pub use server::{serve, serve_with_shutdown, ServeConfig};
 
 // #[derive(Default)]
 pub struct GrpcInferenceServiceImpl {
-    model: Inference,
+    repository: Arc<Repository>,
+    auth: Option<Arc<Auth>>,
+    /// Set on shutdown, so load balancers stop sending requests.
+    draining: AtomicBool,
+    /// Inference requests being answered.
+    in_flight: InFlight,
+    /// Models that must have a ready version for the server to be ready.
+    required_models: Vec<String>,
+    /// How long an inference request may run before the server reports
+    /// itself as not live.
+    stuck_timeout: Option<Duration>,
 }
 
 impl GrpcInferenceServiceImpl {
-    pub fn with_model(model: Inference) -> Self {
-        GrpcInferenceServiceImpl { model }
+    pub fn with_repository(repository: Arc<Repository>) -> Self {
+        GrpcInferenceServiceImpl {
+            repository,
+            auth: None,
+            draining: AtomicBool::new(false),
+            in_flight: InFlight::default(),
+            required_models: vec![],
+            stuck_timeout: None,
+        }
+    }
+
+    /// Requires callers to authenticate, see [`Auth`].
+    pub fn with_auth(self, auth: Auth) -> Self {
+        GrpcInferenceServiceImpl {
+            auth: Some(Arc::new(auth)),
+            ..self
+        }
+    }
+
+    /// Only reports the server as ready while each of `names` has a loaded
+    /// version serving requests without a version.
+    pub fn with_required_models(self, names: Vec<String>) -> Self {
+        GrpcInferenceServiceImpl {
+            required_models: names,
+            ..self
+        }
+    }
+
+    /// Reports the server as not live once an inference request has run for
+    /// longer than `timeout`, so an orchestrator restarts a server whose
+    /// model is stuck.
+    pub fn with_stuck_timeout(self, timeout: Duration) -> Self {
+        GrpcInferenceServiceImpl {
+            stuck_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Reports the server as not ready from now on.
+    pub(crate) fn drain(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    fn is_ready(&self, name: &str, version: &str) -> bool {
+        self.repository
+            .model_version(name, version)
+            .map(|model| model.loaded())
+            .unwrap_or(false)
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn authorize<T>(&self, request: &tonic::Request<T>, access: Access) -> Result<(), Status> {
+        match &self.auth {
+            Some(auth) => auth.authorize(request.extensions().get::<Principal>(), access),
+            None => Ok(()),
+        }
     }
 }
 
+/// When each request in flight was received.
+#[derive(Default)]
+struct InFlight {
+    next: AtomicU64,
+    received: Mutex<HashMap<u64, Instant>>,
+}
+
+impl InFlight {
+    fn start(&self, received: Instant) -> InFlightRequest<'_> {
+        let id = self.next.fetch_add(1, Ordering::SeqCst);
+
+        self.received.lock().unwrap().insert(id, received);
+
+        InFlightRequest(self, id)
+    }
+
+    fn len(&self) -> usize {
+        self.received.lock().unwrap().len()
+    }
+
+    /// How long the oldest request in flight has been running.
+    fn longest(&self) -> Option<Duration> {
+        let received = self.received.lock().unwrap();
+
+        received.values().min().map(|received| received.elapsed())
+    }
+}
+
+/// Counts a request as in flight until dropped.
+struct InFlightRequest<'a>(&'a InFlight, u64);
+
+impl Drop for InFlightRequest<'_> {
+    fn drop(&mut self) {
+        self.0.received.lock().unwrap().remove(&self.1);
+    }
+}
+
+/// The `config` parameter of a load request holds a JSON model config. Its
+/// `model_name` defaults to the model being loaded.
+#[allow(clippy::result_large_err)]
+fn load_config(
+    name: &str,
+    parameters: &HashMap<String, ModelRepositoryParameter>,
+) -> Result<Option<ModelConfig>, Status> {
+    let json = match parameters
+        .get("config")
+        .and_then(|parameter| parameter.parameter_choice.as_ref())
+    {
+        Some(ParameterChoice::StringParam(json)) => json,
+        Some(_) => return Err(Status::invalid_argument("config must be a string")),
+        None => return Ok(None),
+    };
+    let invalid =
+        |error: serde_json::Error| Status::invalid_argument(format!("invalid config: {}", error));
+    let mut config: serde_json::Value = serde_json::from_str(json).map_err(invalid)?;
+
+    if let Some(config) = config.as_object_mut() {
+        config.entry("model_name").or_insert_with(|| name.into());
+    }
+
+    serde_json::from_value(config).map(Some).map_err(invalid)
+}
+
 #[tonic::async_trait]
 impl GrpcInferenceService for GrpcInferenceServiceImpl {
     async fn server_live(
         &self,
-        _: tonic::Request<ServerLiveRequest>,
+        request: tonic::Request<ServerLiveRequest>,
     ) -> std::result::Result<tonic::Response<ServerLiveResponse>, tonic::Status> {
-        return Ok(Response::new(ServerLiveResponse { live: true }));
+        self.authorize(&request, Access::Health)?;
+
+        // A model that never returns holds on to a runtime thread, so the
+        // server would slowly stop answering without being restarted.
+        let live = match (self.stuck_timeout, self.in_flight.longest()) {
+            (Some(timeout), Some(longest)) if longest > timeout => {
+                warn!(
+                    "Inference request running for {:?}, reporting not live",
+                    longest
+                );
+                false
+            }
+            _ => true,
+        };
+
+        return Ok(Response::new(ServerLiveResponse { live }));
     }
 
     /// The ServerReady API indicates if the server is ready for inferencing.
     async fn server_ready(
         &self,
-        _: tonic::Request<ServerReadyRequest>,
+        request: tonic::Request<ServerReadyRequest>,
     ) -> std::result::Result<tonic::Response<ServerReadyResponse>, tonic::Status> {
-        return Ok(Response::new(ServerReadyResponse { ready: true }));
+        self.authorize(&request, Access::Health)?;
+
+        let ready = !self.draining.load(Ordering::SeqCst)
+            && self
+                .required_models
+                .iter()
+                .all(|name| self.is_ready(name, ""));
+
+        return Ok(Response::new(ServerReadyResponse { ready }));
     }
 
     /// The ModelReady API indicates if a specific model is ready for inferencing.
     async fn model_ready(
         &self,
-        _: tonic::Request<ModelReadyRequest>,
+        request: tonic::Request<ModelReadyRequest>,
     ) -> std::result::Result<tonic::Response<ModelReadyResponse>, tonic::Status> {
-        return Ok(Response::new(ModelReadyResponse {
-            ready: self.model.loaded(),
-        }));
+        self.authorize(&request, Access::Health)?;
+
+        let ready = self.is_ready(&request.get_ref().name, &request.get_ref().version);
+
+        return Ok(Response::new(ModelReadyResponse { ready }));
     }
 
     /// The ServerMetadata API provides information about the server. Errors are
@@ -56,10 +233,12 @@ impl GrpcInferenceService for GrpcInferenceServiceImpl {
         &self,
         request: tonic::Request<ServerMetadataRequest>,
     ) -> std::result::Result<tonic::Response<ServerMetadataResponse>, tonic::Status> {
+        self.authorize(&request, Access::Server)?;
+
         return Ok(Response::new(ServerMetadataResponse {
             name: "".to_string(),
             version: "".to_string(),
-            extensions: vec![],
+            extensions: vec!["statistics".to_string(), "model_repository".to_string()],
         }));
     }
 
@@ -70,12 +249,37 @@ impl GrpcInferenceService for GrpcInferenceServiceImpl {
         &self,
         request: tonic::Request<ModelMetadataRequest>,
     ) -> std::result::Result<tonic::Response<ModelMetadataResponse>, tonic::Status> {
+        self.authorize(&request, Access::Model(&request.get_ref().name))?;
+
+        let name = &request.get_ref().name;
+        let model = self
+            .repository
+            .model_version(name, &request.get_ref().version)?;
+        let config = model.model_config();
+        let versions = self
+            .repository
+            .versions(name)?
+            .iter()
+            .map(|model| model.version().to_string())
+            .filter(|version| !version.is_empty())
+            .collect();
+        let tensor_metadata = |specs: &[TensorSpec]| {
+            specs
+                .iter()
+                .map(|spec| TensorMetadata {
+                    name: spec.name.clone(),
+                    datatype: spec.datatype.clone(),
+                    shape: spec.shape.clone(),
+                })
+                .collect()
+        };
+
         return Ok(Response::new(ModelMetadataResponse {
-            name: "".to_string(),
-            versions: vec![],
-            platform: "".to_string(),
-            inputs: vec![],
-            outputs: vec![],
+            name: config.model_name.clone(),
+            versions,
+            platform: config.platform.clone().unwrap_or_default(),
+            inputs: tensor_metadata(&config.inputs),
+            outputs: tensor_metadata(&config.outputs),
         }));
     }
 
@@ -86,28 +290,154 @@ impl GrpcInferenceService for GrpcInferenceServiceImpl {
         &self,
         request: tonic::Request<ModelInferRequest>,
     ) -> std::result::Result<tonic::Response<ModelInferResponse>, tonic::Status> {
-        let infer_request = InferRequest::from_proto(request.into_inner());
-        let infer_result = self.model.predict(infer_request).await;
+        let received = Instant::now();
+        let _in_flight = self.in_flight.start(received);
+
+        self.authorize(&request, Access::Model(&request.get_ref().model_name))?;
+
+        let model = self.repository.model_version(
+            &request.get_ref().model_name,
+            &request.get_ref().model_version,
+        )?;
+
+        let span = info_span!("ModelInfer", otel.kind = "server", rpc.system = "grpc");
+
+        telemetry::continue_from_metadata(&span, request.metadata());
+
+        let mut infer_request = info_span!(parent: &span, "decode")
+            .in_scope(|| InferRequest::from_proto(request.into_inner()))
+            .map_err(|error| Status::invalid_argument(error.to_string()))?;
+
+        // Handlers see the version a label or default resolved to.
+        infer_request.model_version = model.version().to_string();
+
+        let infer_result = model
+            .predict_since(infer_request, received)
+            .instrument(span)
+            .await;
 
         match infer_result {
-            Ok(infer_response) => Ok(Response::new(infer_response.to_proto())),
-            Err(error) => Err(tonic::Status::internal(error.to_string())),
+            Ok(infer_response) => Ok(Response::new(ModelInferResponse {
+                model_version: model.version().to_string(),
+                ..infer_response.to_proto()
+            })),
+            Err(error) => match error.downcast_ref::<ModelError>() {
+                Some(ModelError::InvalidInput(_)) => {
+                    Err(tonic::Status::invalid_argument(error.to_string()))
+                }
+                _ => Err(tonic::Status::internal(error.to_string())),
+            },
         }
     }
-}
 
-pub async fn  serve(
-    port: i16,
-    service: GrpcInferenceServiceImpl,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let addr = format!("[::1]:{}", port).parse().unwrap();
+    /// The ModelStatistics API returns inference statistics for a model, or for
+    /// every model if no name is given. Part of the statistics extension.
+    async fn model_statistics(
+        &self,
+        request: tonic::Request<ModelStatisticsRequest>,
+    ) -> std::result::Result<tonic::Response<ModelStatisticsResponse>, tonic::Status> {
+        let model_stats = match request.get_ref().name.as_str() {
+            // Only the models the caller may use.
+            "" => {
+                self.authorize(&request, Access::Server)?;
+                self.repository
+                    .models()
+                    .iter()
+                    .filter(|model| {
+                        let name = &model.model_config().model_name;
+
+                        self.authorize(&request, Access::Model(name)).is_ok()
+                    })
+                    .map(|model| {
+                        model
+                            .statistics()
+                            .snapshot(&model.model_config().model_name, model.version())
+                    })
+                    .collect()
+            }
+            name => {
+                self.authorize(&request, Access::Model(name))?;
 
-    println!("Ferrix listening on {}", addr);
+                let models = match request.get_ref().version.as_str() {
+                    "" => self.repository.versions(name)?,
+                    version => vec![self.repository.model_version(name, version)?],
+                };
 
-    Server::builder()
-        .add_service(GrpcInferenceServiceServer::new(service))
-        .serve(addr)
-        .await?;
+                models
+                    .iter()
+                    .map(|model| model.statistics().snapshot(name, model.version()))
+                    .collect()
+            }
+        };
 
-    Ok(())
+        Ok(Response::new(ModelStatisticsResponse { model_stats }))
+    }
+
+    /// The RepositoryIndex API lists the models in the model repository and
+    /// their state. Part of the model repository extension.
+    async fn repository_index(
+        &self,
+        request: tonic::Request<RepositoryIndexRequest>,
+    ) -> std::result::Result<tonic::Response<RepositoryIndexResponse>, tonic::Status> {
+        self.authorize(&request, Access::Admin)?;
+
+        Ok(Response::new(RepositoryIndexResponse {
+            models: self.repository.index(request.get_ref().ready),
+        }))
+    }
+
+    /// The RepositoryModelLoad API loads a model, or reloads it if it is
+    /// already loaded. Part of the model repository extension. Only admins
+    /// may call it, since a `config` picks the artifact and the Python paths
+    /// the handler imports from. A `config` also replaces every numbered
+    /// version of the model, see [`Repository::load`].
+    async fn repository_model_load(
+        &self,
+        request: tonic::Request<RepositoryModelLoadRequest>,
+    ) -> std::result::Result<tonic::Response<RepositoryModelLoadResponse>, tonic::Status> {
+        self.authorize(&request, Access::Admin)?;
+
+        let request = request.into_inner();
+        let config = load_config(&request.model_name, &request.parameters)?;
+
+        self.repository.load(&request.model_name, config).await?;
+
+        Ok(Response::new(RepositoryModelLoadResponse {}))
+    }
+
+    /// The RepositoryModelUnload API unloads a model. Part of the model
+    /// repository extension.
+    async fn repository_model_unload(
+        &self,
+        request: tonic::Request<RepositoryModelUnloadRequest>,
+    ) -> std::result::Result<tonic::Response<RepositoryModelUnloadResponse>, tonic::Status> {
+        self.authorize(&request, Access::Admin)?;
+        self.repository.unload(&request.get_ref().model_name)?;
+
+        Ok(Response::new(RepositoryModelUnloadResponse {}))
+    }
+
+    /// The ModelVersionLabels API assigns, moves or removes labels, such as
+    /// "stable" or "canary", that requests can use as a model version.
+    /// Changing labels redirects the model's traffic, so only admins may.
+    /// Anyone allowed to use the model may list them.
+    async fn model_version_labels(
+        &self,
+        request: tonic::Request<ModelVersionLabelsRequest>,
+    ) -> std::result::Result<tonic::Response<ModelVersionLabelsResponse>, tonic::Status> {
+        let access = match request.get_ref().labels.is_empty() {
+            true => Access::Model(&request.get_ref().name),
+            false => Access::Admin,
+        };
+
+        self.authorize(&request, access)?;
+
+        let labels = self
+            .repository
+            .set_labels(&request.get_ref().name, &request.get_ref().labels)?;
+
+        Ok(Response::new(ModelVersionLabelsResponse {
+            labels: labels.into_iter().collect(),
+        }))
+    }
 }
@@ -0,0 +1,199 @@
+use std::fmt::{self, Display};
+use std::io;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use socket2::{Domain, Socket, Type};
+use tokio::net::{TcpListener, UnixListener};
+
+/// An address the server accepts connections on.
+///
+/// Parsed from `HOST:PORT` (`0.0.0.0:6565`, `[::1]:6565`), a bare port, which
+/// listens on every IPv4 and IPv6 interface, or `unix:PATH` for a Unix domain
+/// socket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+pub enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+impl ListenAddr {
+    /// Every interface, IPv4 and IPv6.
+    pub fn any(port: u16) -> Self {
+        ListenAddr::Tcp(SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port))
+    }
+
+    pub fn bind(&self) -> io::Result<Listener> {
+        match self {
+            ListenAddr::Tcp(addr) => bind_tcp(addr).map(Listener::Tcp),
+            ListenAddr::Unix(path) => {
+                remove_stale_socket(path)?;
+                UnixListener::bind(path).map(Listener::Unix)
+            }
+        }
+    }
+}
+
+impl FromStr for ListenAddr {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = value.strip_prefix("unix:") {
+            let path = path.strip_prefix("//").unwrap_or(path);
+
+            return match path.is_empty() {
+                true => Err(format!("{} is missing a socket path", value)),
+                false => Ok(ListenAddr::Unix(PathBuf::from(path))),
+            };
+        }
+
+        if let Ok(port) = value.parse::<u16>() {
+            return Ok(ListenAddr::any(port));
+        }
+
+        value
+            .parse::<SocketAddr>()
+            .map(ListenAddr::Tcp)
+            .map_err(|_| {
+                format!(
+                    "{} is not a HOST:PORT, PORT or unix:PATH listen address",
+                    value
+                )
+            })
+    }
+}
+
+impl Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => write!(f, "{}", addr),
+            ListenAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Binds through socket2 so `[::]` also accepts IPv4 connections, regardless
+/// of the platform's `IPV6_V6ONLY` default.
+fn bind_tcp(addr: &SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(*addr), Type::STREAM, None)?;
+
+    if addr.is_ipv6() {
+        socket.set_only_v6(false)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&(*addr).into())?;
+    socket.listen(1024)?;
+
+    TcpListener::from_std(socket.into())
+}
+
+/// A socket left behind by a previous run would make bind fail. Sockets a
+/// running server still accepts connections on, and anything that isn't a
+/// socket, are left alone.
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            match std::os::unix::net::UnixStream::connect(path) {
+                Ok(_) => Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{} is in use by another server", path.display()),
+                )),
+                Err(error) if error.kind() == io::ErrorKind::ConnectionRefused => {
+                    std::fs::remove_file(path)
+                }
+                Err(error) => Err(error),
+            }
+        }
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        )),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(ListenAddr::any(8080), "8080".parse().unwrap());
+        assert_eq!(
+            ListenAddr::Tcp("0.0.0.0:6565".parse().unwrap()),
+            "0.0.0.0:6565".parse().unwrap()
+        );
+        assert_eq!(
+            ListenAddr::Tcp("[::1]:40000".parse().unwrap()),
+            "[::1]:40000".parse().unwrap()
+        );
+        assert_eq!(
+            ListenAddr::Unix(PathBuf::from("/run/ferrix.sock")),
+            "unix:///run/ferrix.sock".parse().unwrap()
+        );
+        assert_eq!(
+            ListenAddr::Unix(PathBuf::from("ferrix.sock")),
+            "unix:ferrix.sock".parse().unwrap()
+        );
+        assert!("unix:".parse::<ListenAddr>().is_err());
+        assert!("70000".parse::<ListenAddr>().is_err());
+        assert!("localhost".parse::<ListenAddr>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dual_stack_accepts_ipv4() {
+        let Listener::Tcp(listener) = "[::]:0".parse::<ListenAddr>().unwrap().bind().unwrap()
+        else {
+            panic!("expected a TCP listener");
+        };
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replaces_stale_unix_socket() {
+        let path = std::env::temp_dir().join(format!("ferrix-{}.sock", std::process::id()));
+        let addr = ListenAddr::Unix(path.clone());
+
+        drop(addr.bind().unwrap());
+
+        let _listener = addr.bind().unwrap();
+
+        tokio::net::UnixStream::connect(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_keeps_sockets_in_use() {
+        let path = std::env::temp_dir().join(format!("ferrix-{}-live.sock", std::process::id()));
+        let addr = ListenAddr::Unix(path.clone());
+        let _listener = addr.bind().unwrap();
+
+        assert_eq!(io::ErrorKind::AddrInUse, addr.bind().err().unwrap().kind());
+
+        tokio::net::UnixStream::connect(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_keeps_files_that_are_not_sockets() {
+        let path = std::env::temp_dir().join(format!("ferrix-{}.txt", std::process::id()));
+
+        std::fs::write(&path, "keep me").unwrap();
+        assert!(ListenAddr::Unix(path.clone()).bind().is_err());
+        assert_eq!("keep me", std::fs::read_to_string(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,430 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use ferrix_model_api::internal::InferRequest;
+use prometheus::core::Collector;
+use prometheus::{
+    exponential_buckets, Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+use crate::statistics::{Execution, Statistics};
+
+/// The stages of [`crate::inference::Inference::predict`].
+pub const PREPROCESS: &str = "preprocess";
+pub const MODEL: &str = "model";
+pub const POSTPROCESS: &str = "postprocess";
+
+/// Prometheus metrics for the served models, plus the usual `process_*`
+/// metrics on Linux. Clones share the same metrics.
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    failures: IntCounterVec,
+    queue_duration: HistogramVec,
+    stage_duration: HistogramVec,
+    batch_size: HistogramVec,
+    in_flight: IntGaugeVec,
+    load_duration: GaugeVec,
+    loaded: IntGaugeVec,
+}
+
+impl Metrics {
+    pub fn new() -> anyhow::Result<Self> {
+        let labels = &["model", "version"];
+        let stage_labels = &["model", "version", "stage"];
+        // 0.5ms up to about 16s.
+        let latency_buckets = exponential_buckets(0.0005, 2.0, 16)?;
+        let metrics = Metrics {
+            registry: Registry::new(),
+            requests: IntCounterVec::new(
+                Opts::new("ferrix_inference_requests_total", "Inference requests"),
+                labels,
+            )?,
+            failures: IntCounterVec::new(
+                Opts::new(
+                    "ferrix_inference_failures_total",
+                    "Failed inference requests, by the stage that failed",
+                ),
+                stage_labels,
+            )?,
+            queue_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "ferrix_inference_queue_duration_seconds",
+                    "Time from receiving a request until inference starts",
+                )
+                .buckets(latency_buckets.clone()),
+                labels,
+            )?,
+            stage_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "ferrix_inference_stage_duration_seconds",
+                    "Time spent in each inference stage",
+                )
+                .buckets(latency_buckets),
+                stage_labels,
+            )?,
+            batch_size: HistogramVec::new(
+                HistogramOpts::new(
+                    "ferrix_inference_batch_size",
+                    "Leading dimension of the first input of each request",
+                )
+                .buckets(exponential_buckets(1.0, 2.0, 11)?),
+                labels,
+            )?,
+            in_flight: IntGaugeVec::new(
+                Opts::new(
+                    "ferrix_inference_in_flight",
+                    "Inference requests currently being processed",
+                ),
+                labels,
+            )?,
+            load_duration: GaugeVec::new(
+                Opts::new(
+                    "ferrix_model_load_duration_seconds",
+                    "Time the last load of the model took",
+                ),
+                labels,
+            )?,
+            loaded: IntGaugeVec::new(
+                Opts::new(
+                    "ferrix_model_loaded",
+                    "1 if the last load of the model succeeded, 0 otherwise",
+                ),
+                labels,
+            )?,
+        };
+
+        let collectors: [Box<dyn Collector>; 8] = [
+            Box::new(metrics.requests.clone()),
+            Box::new(metrics.failures.clone()),
+            Box::new(metrics.queue_duration.clone()),
+            Box::new(metrics.stage_duration.clone()),
+            Box::new(metrics.batch_size.clone()),
+            Box::new(metrics.in_flight.clone()),
+            Box::new(metrics.load_duration.clone()),
+            Box::new(metrics.loaded.clone()),
+        ];
+
+        for collector in collectors {
+            metrics.registry.register(collector)?;
+        }
+
+        #[cfg(target_os = "linux")]
+        metrics.registry.register(Box::new(
+            prometheus::process_collector::ProcessCollector::for_self(),
+        ))?;
+
+        Ok(metrics)
+    }
+
+    /// Serves the metrics in the Prometheus text format on `/metrics`.
+    pub fn router(&self) -> Router {
+        let metrics = self.clone();
+
+        Router::new().route("/metrics", get(move || async move { metrics.render() }))
+    }
+
+    fn render(&self) -> impl IntoResponse {
+        let encoder = TextEncoder::new();
+        let mut body = Vec::new();
+
+        match encoder.encode(&self.registry.gather(), &mut body) {
+            Ok(()) => (
+                StatusCode::OK,
+                [(header::CONTENT_TYPE, encoder.format_type().to_string())],
+                body,
+            ),
+            Err(error) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [(header::CONTENT_TYPE, "text/plain".to_string())],
+                error.to_string().into_bytes(),
+            ),
+        }
+    }
+
+    pub(crate) fn model_loaded(&self, model: &str, version: &str, took: Duration, loaded: bool) {
+        let labels = &[model, version];
+
+        self.load_duration
+            .with_label_values(labels)
+            .set(took.as_secs_f64());
+        self.loaded.with_label_values(labels).set(loaded as i64);
+    }
+
+    /// A version that stopped serving but can load again.
+    pub(crate) fn model_unloaded(&self, model: &str, version: &str) {
+        self.loaded.with_label_values(&[model, version]).set(0);
+    }
+
+    /// Drops the load series of a version the repository forgot.
+    pub(crate) fn model_removed(&self, model: &str, version: &str) {
+        let labels = &[model, version];
+
+        let _ = self.loaded.remove_label_values(labels);
+        let _ = self.load_duration.remove_label_values(labels);
+    }
+}
+
+/// Records one request as it moves through the inference stages, in the
+/// model's [`Statistics`] and in its metrics if there are any.
+pub(crate) struct RequestMetrics<'a> {
+    metrics: Option<&'a Metrics>,
+    statistics: &'a Statistics,
+    labels: [&'a str; 2],
+    received: Instant,
+    queue: Duration,
+    batch_size: i64,
+    stages: Vec<(&'static str, Duration)>,
+    succeeded: bool,
+}
+
+impl<'a> RequestMetrics<'a> {
+    /// `received` is when the server accepted the request.
+    pub(crate) fn start(
+        metrics: Option<&'a Metrics>,
+        statistics: &'a Statistics,
+        model: &'a str,
+        version: &'a str,
+        received: Instant,
+        request: &InferRequest,
+    ) -> Self {
+        let labels = [model, version];
+        let queue = received.elapsed();
+        let batch_size = batch_size(request);
+
+        if let Some(metrics) = metrics {
+            metrics.requests.with_label_values(&labels).inc();
+            metrics.in_flight.with_label_values(&labels).inc();
+            metrics
+                .queue_duration
+                .with_label_values(&labels)
+                .observe(queue.as_secs_f64());
+            metrics
+                .batch_size
+                .with_label_values(&labels)
+                .observe(batch_size as f64);
+        }
+
+        RequestMetrics {
+            metrics,
+            statistics,
+            labels,
+            received,
+            queue,
+            batch_size,
+            stages: Vec::with_capacity(3),
+            succeeded: false,
+        }
+    }
+
+    pub(crate) async fn stage<T, E>(
+        &mut self,
+        stage: &'static str,
+        future: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let started = Instant::now();
+        let result = future.await;
+        let took = started.elapsed();
+
+        self.stages.push((stage, took));
+
+        if let Some(metrics) = self.metrics {
+            let [model, version] = self.labels;
+            let labels = &[model, version, stage];
+
+            metrics
+                .stage_duration
+                .with_label_values(labels)
+                .observe(took.as_secs_f64());
+
+            if result.is_err() {
+                metrics.failures.with_label_values(labels).inc();
+            }
+        }
+
+        result
+    }
+
+    /// Marks the response as ready. Requests dropped without it count as
+    /// failed in the statistics.
+    pub(crate) fn succeeded(&mut self) {
+        self.succeeded = true;
+    }
+}
+
+/// Also runs when a request is cancelled halfway, e.g. by a client timeout.
+impl Drop for RequestMetrics<'_> {
+    fn drop(&mut self) {
+        self.statistics.record(Execution {
+            succeeded: self.succeeded,
+            batch_size: self.batch_size as u64,
+            total: self.received.elapsed(),
+            queue: self.queue,
+            stages: &self.stages,
+        });
+
+        if let Some(metrics) = self.metrics {
+            metrics.in_flight.with_label_values(&self.labels).dec();
+        }
+    }
+}
+
+/// By convention the leading dimension of an input is the batch. Requests
+/// without a usable one count as a single item.
+fn batch_size(request: &InferRequest) -> i64 {
+    request
+        .inputs
+        .first()
+        .and_then(|input| input.shape.first())
+        .copied()
+        .filter(|size| *size > 0)
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::repository::tests::{model_config, repository};
+
+    fn sample(metrics: &Metrics, name: &str) -> String {
+        let encoder = TextEncoder::new();
+        let mut body = Vec::new();
+
+        encoder
+            .encode(&metrics.registry.gather(), &mut body)
+            .unwrap();
+
+        String::from_utf8(body)
+            .unwrap()
+            .lines()
+            .filter(|line| line.starts_with(name))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[tokio::test]
+    async fn test_request_metrics() {
+        let metrics = Metrics::new().unwrap();
+        let statistics = Statistics::default();
+        let request = InferRequest {
+            model_name: "resnet".to_string(),
+            model_version: "".to_string(),
+            id: "".to_string(),
+            parameters: Default::default(),
+            inputs: vec![],
+            outputs: vec![],
+            raw_input_contents: vec![],
+        };
+
+        {
+            let mut tracker = RequestMetrics::start(
+                Some(&metrics),
+                &statistics,
+                "resnet",
+                "1",
+                Instant::now(),
+                &request,
+            );
+
+            assert_eq!(
+                r#"ferrix_inference_in_flight{model="resnet",version="1"} 1"#,
+                sample(&metrics, "ferrix_inference_in_flight")
+            );
+
+            let _ = tracker.stage(PREPROCESS, async { Ok::<_, ()>(()) }).await;
+            let _ = tracker.stage(MODEL, async { Err::<(), _>(()) }).await;
+        }
+
+        assert_eq!(
+            r#"ferrix_inference_in_flight{model="resnet",version="1"} 0"#,
+            sample(&metrics, "ferrix_inference_in_flight")
+        );
+        assert_eq!(
+            r#"ferrix_inference_failures_total{model="resnet",stage="model",version="1"} 1"#,
+            sample(&metrics, "ferrix_inference_failures_total")
+        );
+        assert_eq!(
+            r#"ferrix_inference_stage_duration_seconds_count{model="resnet",stage="model",version="1"} 1
+ferrix_inference_stage_duration_seconds_count{model="resnet",stage="preprocess",version="1"} 1"#,
+            sample(&metrics, "ferrix_inference_stage_duration_seconds_count")
+        );
+        assert_eq!(
+            r#"ferrix_inference_batch_size_sum{model="resnet",version="1"} 1"#,
+            sample(&metrics, "ferrix_inference_batch_size_sum")
+        );
+
+        let stats = statistics.snapshot("resnet", "1").inference_stats.unwrap();
+
+        assert_eq!(1, stats.fail.unwrap().count);
+        assert_eq!(0, stats.success.unwrap().count);
+    }
+
+    #[tokio::test]
+    async fn test_endpoint() {
+        let metrics = Metrics::new().unwrap();
+
+        metrics.model_loaded("resnet", "1", Duration::from_millis(1500), true);
+
+        let response = metrics
+            .router()
+            .oneshot(
+                axum::http::Request::get("/metrics")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(body.contains(r#"ferrix_model_loaded{model="resnet",version="1"} 1"#));
+        assert!(
+            body.contains(r#"ferrix_model_load_duration_seconds{model="resnet",version="1"} 1.5"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_loaded_follows_repository() {
+        let metrics = Metrics::new().unwrap();
+        let repository = repository(Arc::new(AtomicBool::new(false))).with_metrics(metrics.clone());
+
+        for version in ["1", "2"] {
+            repository.add(model_config("resnet", "model.pt"), version);
+            repository.load_version("resnet", version).await.unwrap();
+        }
+
+        repository.unload("resnet").unwrap();
+
+        assert_eq!(
+            r#"ferrix_model_loaded{model="resnet",version="1"} 0
+ferrix_model_loaded{model="resnet",version="2"} 0"#,
+            sample(&metrics, "ferrix_model_loaded")
+        );
+
+        repository.load_version("resnet", "2").await.unwrap();
+        repository.remove("resnet", "1");
+
+        assert_eq!(
+            r#"ferrix_model_loaded{model="resnet",version="2"} 1"#,
+            sample(&metrics, "ferrix_model_loaded")
+        );
+
+        repository.unload_all();
+
+        assert_eq!(
+            r#"ferrix_model_loaded{model="resnet",version="2"} 0"#,
+            sample(&metrics, "ferrix_model_loaded")
+        );
+    }
+}
@@ -0,0 +1,1044 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::Context;
+use ferrix_model_api::{Model, ModelConfig, ModelResult};
+use ferrix_protos::repository_index_response::ModelIndex;
+use tonic::Status;
+use tracing::{info, warn};
+
+use crate::inference::{Inference, InferenceConfig};
+use crate::metrics::Metrics;
+use crate::storage::{Storage, StorageConfig};
+
+/// Builds the backend model for a config. The binary supplies it, so the
+/// server doesn't have to depend on every backend.
+pub type ModelFactory = Arc<dyn Fn(&ModelConfig) -> ModelResult<Box<dyn Model>> + Send + Sync>;
+
+/// Applies to every model in the repository.
+#[derive(Clone, Debug, Default)]
+pub struct RepositoryConfig {
+    /// The Python handler, evaluated separately by every model version that
+    /// loads, each with its own config, hooks and state.
+    pub handler_path: Option<String>,
+    pub expose_hook_errors: bool,
+    /// How often each loaded version checks its handler for changes, see
+    /// [`Inference::watch_handler`]. Handlers aren't reloaded when unset.
+    pub handler_reload_interval: Option<Duration>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ModelState {
+    Loading,
+    Ready,
+    /// Known but not loaded, or unloaded on request.
+    Unavailable,
+    Failed(String),
+}
+
+impl ModelState {
+    /// The state names used by the KServe repository extension.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ModelState::Loading => "LOADING",
+            ModelState::Ready => "READY",
+            ModelState::Unavailable | ModelState::Failed(_) => "UNAVAILABLE",
+        }
+    }
+
+    pub fn reason(&self) -> &str {
+        match self {
+            ModelState::Failed(reason) => reason,
+            _ => "",
+        }
+    }
+}
+
+/// The models the server knows about and the ones it serves, by name and
+/// version. Versions load and unload at runtime while requests keep going to
+/// whatever is ready.
+pub struct Repository {
+    config: RepositoryConfig,
+    factory: ModelFactory,
+    metrics: Option<Metrics>,
+    storage: Arc<Storage>,
+    models: RwLock<BTreeMap<String, BTreeMap<Version, Entry>>>,
+    /// Version labels by model, such as "stable", pointing at a version.
+    labels: RwLock<BTreeMap<String, BTreeMap<String, String>>>,
+}
+
+/// The label whose version serves requests that don't ask for a version.
+/// Without it they go to the latest ready version.
+pub const DEFAULT_LABEL: &str = "default";
+
+/// Orders numeric versions numerically, after unversioned and named ones.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Version(Option<u64>, String);
+
+impl Version {
+    fn new(version: &str) -> Self {
+        Version(version.parse().ok(), version.to_string())
+    }
+}
+
+struct Entry {
+    config: ModelConfig,
+    state: ModelState,
+    /// Kept while a reload is in progress, so requests aren't interrupted.
+    inference: Option<Arc<Inference>>,
+}
+
+impl Entry {
+    fn new(config: ModelConfig) -> Self {
+        Entry {
+            config,
+            state: ModelState::Unavailable,
+            inference: None,
+        }
+    }
+}
+
+// Statuses go straight back to tonic, which is what they're sized for.
+#[allow(clippy::result_large_err)]
+impl Repository {
+    pub fn new(config: RepositoryConfig, factory: ModelFactory) -> Self {
+        Repository {
+            config,
+            factory,
+            metrics: None,
+            storage: Arc::new(Storage::new(StorageConfig::default())),
+            models: RwLock::new(BTreeMap::new()),
+            labels: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Records load and request metrics for every model, see [`Metrics`].
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.metrics.as_ref()
+    }
+
+    /// Fetches remote artifacts with `storage` instead of the default
+    /// [`StorageConfig`].
+    pub fn with_storage(mut self, storage: Storage) -> Self {
+        self.storage = Arc::new(storage);
+        self
+    }
+
+    /// Makes a model version known without loading it. Models without
+    /// versions use "". Replaces the config of a version that is already
+    /// known, which takes effect on its next load.
+    pub fn add(&self, config: ModelConfig, version: &str) {
+        let mut models = self.models.write().unwrap();
+        let versions = models.entry(config.model_name.clone()).or_default();
+
+        match versions.get_mut(&Version::new(version)) {
+            Some(entry) => entry.config = config,
+            None => {
+                versions.insert(Version::new(version), Entry::new(config));
+            }
+        }
+    }
+
+    /// Stops serving a version and forgets it, along with its labels.
+    /// Requests already using it finish first.
+    pub fn remove(&self, name: &str, version: &str) {
+        let mut models = self.models.write().unwrap();
+        let Some(versions) = models.get_mut(name) else {
+            return;
+        };
+        let removed = versions.remove(&Version::new(version));
+
+        if versions.is_empty() {
+            models.remove(name);
+        }
+
+        drop(models);
+        self.retain_labels(name);
+
+        if let Some(metrics) = &self.metrics {
+            metrics.model_removed(name, version);
+        }
+
+        if let Some(Entry {
+            inference: Some(_), ..
+        }) = removed
+        {
+            info!(model = %name, version = %version, "Unloaded model");
+        }
+    }
+
+    /// Loads every known version of a model, or reloads them. If a reload
+    /// fails the previous model keeps serving.
+    ///
+    /// `config` instead loads the model without versions, adding it if it
+    /// wasn't known. Once it loads, every numbered version of the model is
+    /// unloaded and forgotten, along with labels pointing at them, until
+    /// they're added again.
+    pub async fn load(&self, name: &str, config: Option<ModelConfig>) -> Result<(), Status> {
+        if let Some(config) = config {
+            if config.model_name != name {
+                return Err(Status::invalid_argument(format!(
+                    "config is for model {}, not {}",
+                    config.model_name, name
+                )));
+            }
+
+            self.load_version_with(name, "", Some(config)).await?;
+
+            let mut models = self.models.write().unwrap();
+            let others = match models.get_mut(name) {
+                Some(versions) => {
+                    let unversioned = versions.remove(&Version::new(""));
+                    let others = std::mem::take(versions);
+
+                    if let Some(entry) = unversioned {
+                        versions.insert(Version::new(""), entry);
+                    }
+
+                    others
+                }
+                None => BTreeMap::new(),
+            };
+
+            drop(models);
+
+            if let Some(metrics) = &self.metrics {
+                for version in others.keys() {
+                    metrics.model_removed(name, &version.1);
+                }
+            }
+
+            drop(others);
+            self.retain_labels(name);
+
+            return Ok(());
+        }
+
+        let versions: Vec<String> = {
+            let models = self.models.read().unwrap();
+
+            match models.get(name) {
+                Some(versions) => versions.keys().map(|version| version.1.clone()).collect(),
+                None => return Err(Status::not_found(format!("unknown model {}", name))),
+            }
+        };
+        let mut result = Ok(());
+
+        for version in versions {
+            let loaded = self.load_version(name, &version).await;
+
+            result = result.and(loaded);
+        }
+
+        result
+    }
+
+    /// Loads a known version, or reloads it.
+    pub async fn load_version(&self, name: &str, version: &str) -> Result<(), Status> {
+        self.load_version_with(name, version, None).await
+    }
+
+    async fn load_version_with(
+        &self,
+        name: &str,
+        version: &str,
+        config: Option<ModelConfig>,
+    ) -> Result<(), Status> {
+        let key = Version::new(version);
+        let (config, previous) = {
+            let mut models = self.models.write().unwrap();
+            let entry = match (models.get_mut(name), &config) {
+                (Some(versions), Some(config)) => versions
+                    .entry(key.clone())
+                    .or_insert_with(|| Entry::new(config.clone())),
+                (Some(versions), None) => match versions.get_mut(&key) {
+                    Some(entry) => entry,
+                    None => return Err(unknown_version(name, version)),
+                },
+                (None, Some(config)) => models
+                    .entry(name.to_string())
+                    .or_default()
+                    .entry(key.clone())
+                    .or_insert_with(|| Entry::new(config.clone())),
+                (None, None) => return Err(Status::not_found(format!("unknown model {}", name))),
+            };
+
+            if entry.state == ModelState::Loading {
+                return Err(Status::failed_precondition(format!(
+                    "model {} is already loading",
+                    name
+                )));
+            }
+
+            let previous = std::mem::replace(&mut entry.state, ModelState::Loading);
+
+            (config.unwrap_or_else(|| entry.config.clone()), previous)
+        };
+        let loading = Loading {
+            models: &self.models,
+            name,
+            key: &key,
+            previous: Some(previous),
+        };
+
+        info!(model = %name, version = %version, "Loading model");
+
+        let factory = self.factory.clone();
+        let repository_config = self.config.clone();
+        let metrics = self.metrics.clone();
+        let model_version = version.to_string();
+        let result = async {
+            // The model only ever sees the local copy, the repository keeps
+            // the configured location so reloads fetch it again.
+            let artifact = self
+                .storage
+                .fetch(&config.base_path, config.sha256.as_deref())
+                .await?;
+            let model_config = ModelConfig {
+                base_path: artifact.to_string_lossy().to_string(),
+                ..config.clone()
+            };
+
+            tokio::task::spawn_blocking(move || {
+                build(
+                    &factory,
+                    model_config,
+                    &model_version,
+                    repository_config,
+                    metrics,
+                )
+            })
+            .await?
+        }
+        .await;
+
+        loading.finish();
+
+        let mut models = self.models.write().unwrap();
+        let Some(entry) = models
+            .get_mut(name)
+            .and_then(|versions| versions.get_mut(&key))
+        else {
+            return Err(Status::aborted(format!(
+                "model {} was removed while loading",
+                name
+            )));
+        };
+
+        match result {
+            Ok(mut inference) => {
+                if let Some(interval) = self.config.handler_reload_interval {
+                    inference = inference.watch_handler(interval);
+                }
+
+                let previous = entry.inference.replace(Arc::new(inference));
+
+                entry.config = config;
+                entry.state = ModelState::Ready;
+                drop(models);
+                // Unloads the previous model unless requests still use it.
+                drop(previous);
+                info!(model = %name, version = %version, "Model ready");
+
+                Ok(())
+            }
+            Err(error) => {
+                let reason = format!("{:#}", error);
+
+                warn!(model = %name, version = %version, "Loading model failed: {}", reason);
+                entry.state = match entry.inference {
+                    Some(_) => ModelState::Ready,
+                    None => ModelState::Failed(reason.clone()),
+                };
+
+                Err(Status::internal(format!(
+                    "failed to load model {}: {}",
+                    name, reason
+                )))
+            }
+        }
+    }
+
+    /// Stops serving every version of a model. Requests already using it
+    /// finish first, then it is unloaded. The model stays known, so it can
+    /// load again.
+    pub fn unload(&self, name: &str) -> Result<(), Status> {
+        let mut models = self.models.write().unwrap();
+        let Some(versions) = models.get_mut(name) else {
+            return Err(Status::not_found(format!("unknown model {}", name)));
+        };
+
+        if versions
+            .values()
+            .any(|entry| entry.state == ModelState::Loading)
+        {
+            return Err(Status::failed_precondition(format!(
+                "model {} is loading",
+                name
+            )));
+        }
+
+        let previous: Vec<Arc<Inference>> = versions
+            .values_mut()
+            .filter_map(|entry| {
+                entry.state = ModelState::Unavailable;
+                entry.inference.take()
+            })
+            .collect();
+
+        drop(models);
+        self.record_unloaded(&previous);
+
+        if !previous.is_empty() {
+            info!(model = %name, "Unloaded model");
+        }
+
+        Ok(())
+    }
+
+    /// Stops serving every model, on shutdown. Each is unloaded once the
+    /// requests using it finish.
+    pub fn unload_all(&self) {
+        let mut models = self.models.write().unwrap();
+        let previous: Vec<Arc<Inference>> = models
+            .values_mut()
+            .flat_map(|versions| versions.values_mut())
+            .filter_map(|entry| {
+                entry.state = ModelState::Unavailable;
+                entry.inference.take()
+            })
+            .collect();
+
+        drop(models);
+        self.record_unloaded(&previous);
+
+        if !previous.is_empty() {
+            info!(models = previous.len(), "Unloaded models");
+        }
+    }
+
+    fn record_unloaded(&self, previous: &[Arc<Inference>]) {
+        if let Some(metrics) = &self.metrics {
+            for inference in previous {
+                metrics.model_unloaded(&inference.model_config().model_name, inference.version());
+            }
+        }
+    }
+
+    /// The version serving requests that don't ask for one: the version
+    /// labeled [`DEFAULT_LABEL`], or else the latest ready version.
+    pub fn model(&self, name: &str) -> Result<Arc<Inference>, Status> {
+        if let Some(version) = self.label(name, DEFAULT_LABEL) {
+            return self.model_version(name, &version);
+        }
+
+        let models = self.models.read().unwrap();
+        let Some(versions) = models.get(name) else {
+            return Err(Status::not_found(format!("unknown model {}", name)));
+        };
+
+        versions
+            .values()
+            .rev()
+            .find_map(|entry| entry.inference.clone())
+            .ok_or_else(|| Status::unavailable(format!("model {} is not ready", name)))
+    }
+
+    /// A version of `name`, or the version a label points at. An empty
+    /// version is the one [`Repository::model`] picks.
+    pub fn model_version(&self, name: &str, version: &str) -> Result<Arc<Inference>, Status> {
+        if version.is_empty() {
+            return self.model(name);
+        }
+
+        let label = self.label(name, version);
+        let models = self.models.read().unwrap();
+        let Some(versions) = models.get(name) else {
+            return Err(Status::not_found(format!("unknown model {}", name)));
+        };
+        let entry = versions
+            .get(&Version::new(version))
+            .or_else(|| versions.get(&Version::new(label.as_deref()?)));
+
+        match entry {
+            Some(entry) => entry.inference.clone().ok_or_else(|| {
+                Status::unavailable(format!(
+                    "version {} of model {} is not ready",
+                    version, name
+                ))
+            }),
+            None => Err(unknown_version(name, version)),
+        }
+    }
+
+    /// Every ready version of `name`, oldest first.
+    pub fn versions(&self, name: &str) -> Result<Vec<Arc<Inference>>, Status> {
+        let models = self.models.read().unwrap();
+        let Some(versions) = models.get(name) else {
+            return Err(Status::not_found(format!("unknown model {}", name)));
+        };
+
+        Ok(versions
+            .values()
+            .filter_map(|entry| entry.inference.clone())
+            .collect())
+    }
+
+    pub fn labels(&self, name: &str) -> BTreeMap<String, String> {
+        let labels = self.labels.read().unwrap();
+
+        labels.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Points labels at versions of `name`, or removes them for an empty
+    /// version, and returns the model's labels. Labels can only point at
+    /// ready versions, so moving a label never sends requests to a version
+    /// that can't serve them. Nothing changes unless every label is valid.
+    pub fn set_labels(
+        &self,
+        name: &str,
+        changes: &HashMap<String, String>,
+    ) -> Result<BTreeMap<String, String>, Status> {
+        let models = self.models.read().unwrap();
+        let Some(versions) = models.get(name) else {
+            return Err(Status::not_found(format!("unknown model {}", name)));
+        };
+
+        for (label, version) in changes {
+            if label.is_empty() || versions.contains_key(&Version::new(label)) {
+                return Err(Status::invalid_argument(format!(
+                    "label {:?} of model {} can't be used, it names a version",
+                    label, name
+                )));
+            }
+
+            if version.is_empty() {
+                continue;
+            }
+
+            match versions.get(&Version::new(version)) {
+                Some(entry) if entry.inference.is_some() => {}
+                Some(_) => {
+                    return Err(Status::failed_precondition(format!(
+                        "version {} of model {} is not ready",
+                        version, name
+                    )))
+                }
+                None => return Err(unknown_version(name, version)),
+            }
+        }
+
+        let mut labels = self.labels.write().unwrap();
+        let model_labels = labels.entry(name.to_string()).or_default();
+
+        for (label, version) in changes {
+            match version.as_str() {
+                "" => model_labels.remove(label),
+                version => model_labels.insert(label.clone(), version.to_string()),
+            };
+        }
+
+        let model_labels = model_labels.clone();
+
+        drop(labels);
+        drop(models);
+
+        if !changes.is_empty() {
+            info!(model = %name, labels = ?model_labels, "Version labels changed");
+        }
+
+        Ok(model_labels)
+    }
+
+    fn label(&self, name: &str, label: &str) -> Option<String> {
+        let labels = self.labels.read().unwrap();
+
+        labels.get(name)?.get(label).cloned()
+    }
+
+    /// Drops the labels of versions that are no longer known.
+    fn retain_labels(&self, name: &str) {
+        let models = self.models.read().unwrap();
+        let mut labels = self.labels.write().unwrap();
+        let Some(model_labels) = labels.get_mut(name) else {
+            return;
+        };
+
+        match models.get(name) {
+            Some(versions) => {
+                model_labels.retain(|_, version| versions.contains_key(&Version::new(version)))
+            }
+            None => model_labels.clear(),
+        }
+
+        if model_labels.is_empty() {
+            labels.remove(name);
+        }
+    }
+
+    /// Every version serving requests.
+    pub fn models(&self) -> Vec<Arc<Inference>> {
+        let models = self.models.read().unwrap();
+
+        models
+            .values()
+            .flat_map(|versions| versions.values())
+            .filter_map(|entry| entry.inference.clone())
+            .collect()
+    }
+
+    /// All known versions, or only the ready ones.
+    pub fn index(&self, ready: bool) -> Vec<ModelIndex> {
+        let models = self.models.read().unwrap();
+
+        models
+            .iter()
+            .flat_map(|(name, versions)| {
+                versions
+                    .iter()
+                    .map(move |(version, entry)| (name, version, entry))
+            })
+            .filter(|(_, _, entry)| !ready || entry.state == ModelState::Ready)
+            .map(|(name, version, entry)| ModelIndex {
+                name: name.clone(),
+                version: version.1.clone(),
+                state: entry.state.name().to_string(),
+                reason: entry.state.reason().to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Puts a loading version back in its previous state if the load is
+/// cancelled, such as when the client asking for it goes away, so it doesn't
+/// stay loading forever.
+struct Loading<'a> {
+    models: &'a RwLock<BTreeMap<String, BTreeMap<Version, Entry>>>,
+    name: &'a str,
+    key: &'a Version,
+    previous: Option<ModelState>,
+}
+
+impl Loading<'_> {
+    /// The load ran to completion and sets the state itself.
+    fn finish(mut self) {
+        self.previous = None;
+    }
+}
+
+impl Drop for Loading<'_> {
+    fn drop(&mut self) {
+        let Some(previous) = self.previous.take() else {
+            return;
+        };
+        let mut models = self.models.write().unwrap();
+        let entry = models
+            .get_mut(self.name)
+            .and_then(|versions| versions.get_mut(self.key));
+
+        if let Some(entry) = entry.filter(|entry| entry.state == ModelState::Loading) {
+            warn!(model = %self.name, version = %self.key.1, "Loading model cancelled");
+            entry.state = previous;
+        }
+    }
+}
+
+fn unknown_version(name: &str, version: &str) -> Status {
+    Status::not_found(format!("unknown version {} of model {}", version, name))
+}
+
+fn build(
+    factory: &ModelFactory,
+    model_config: ModelConfig,
+    version: &str,
+    config: RepositoryConfig,
+    metrics: Option<Metrics>,
+) -> anyhow::Result<Inference> {
+    let model = factory(&model_config)?;
+    let mut inference = Inference::new(
+        InferenceConfig {
+            model_config,
+            handler_path: config.handler_path,
+            expose_hook_errors: config.expose_hook_errors,
+        },
+        model,
+    )?
+    .with_version(version);
+
+    if let Some(metrics) = metrics {
+        inference = inference.with_metrics(metrics);
+    }
+
+    inference.load().context("model failed to load")?;
+
+    Ok(inference)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use anyhow::bail;
+    use ferrix_model_api::internal::{InferRequest, InferResponse, OutputTensor};
+    use ferrix_model_api::TensorSpec;
+
+    use super::*;
+
+    /// Fails to load if the config's base path is "missing", takes a while
+    /// for "slow", and reports
+    /// itself as not loaded for "cold", like a model whose backend hasn't
+    /// warmed up. Predictions echo the inputs back as outputs.
+    pub(crate) struct Stub {
+        base_path: String,
+        unloaded: Arc<AtomicBool>,
+    }
+
+    impl Model for Stub {
+        fn load(&mut self) -> ModelResult<()> {
+            match self.base_path.as_str() {
+                "missing" => bail!("no such file"),
+                "slow" => {
+                    std::thread::sleep(std::time::Duration::from_millis(200));
+                    Ok(())
+                }
+                _ => Ok(()),
+            }
+        }
+
+        fn unload(&mut self) -> ModelResult<()> {
+            self.unloaded.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn loaded(&self) -> bool {
+            self.base_path != "cold"
+        }
+
+        fn predict(&self, request: &InferRequest) -> ModelResult<InferResponse> {
+            Ok(InferResponse {
+                model_name: request.model_name.clone(),
+                id: request.id.clone(),
+                parameters: HashMap::new(),
+                outputs: request
+                    .inputs
+                    .iter()
+                    .map(|input| OutputTensor {
+                        name: input.name.clone(),
+                        datatype: input.datatype.clone(),
+                        shape: input.shape.clone(),
+                        parameters: input.parameters.clone(),
+                        data: input.data.clone(),
+                    })
+                    .collect(),
+            })
+        }
+    }
+
+    pub(crate) fn model_config(name: &str, base_path: &str) -> ModelConfig {
+        ModelConfig {
+            model_name: name.to_string(),
+            base_path: base_path.to_string(),
+            sha256: None,
+            platform: Some("pytorch_libtorch".to_string()),
+            inputs: vec![TensorSpec {
+                name: "input".to_string(),
+                datatype: "FP32".to_string(),
+                shape: vec![-1, 3, 224, 224],
+            }],
+            outputs: vec![],
+            extended_config: None,
+            python: None,
+        }
+    }
+
+    pub(crate) fn repository(unloaded: Arc<AtomicBool>) -> Repository {
+        Repository::new(
+            RepositoryConfig::default(),
+            Arc::new(move |config: &ModelConfig| {
+                Ok(Box::new(Stub {
+                    base_path: config.base_path.clone(),
+                    unloaded: unloaded.clone(),
+                }) as Box<dyn Model>)
+            }),
+        )
+    }
+
+    fn states(repository: &Repository) -> Vec<(String, String, String)> {
+        repository
+            .index(false)
+            .into_iter()
+            .map(|model| (model.name, model.state, model.reason))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_load_and_unload() {
+        let unloaded = Arc::new(AtomicBool::new(false));
+        let repository = repository(unloaded.clone());
+
+        repository.add(model_config("resnet", "model.pt"), "");
+
+        assert_eq!(
+            Some(tonic::Code::Unavailable),
+            repository.model("resnet").err().map(|status| status.code())
+        );
+
+        repository.load("resnet", None).await.unwrap();
+
+        let model = repository.model("resnet").unwrap();
+
+        assert_eq!(1, repository.index(true).len());
+
+        repository.unload("resnet").unwrap();
+
+        assert!(!unloaded.load(Ordering::SeqCst), "still in use");
+        drop(model);
+        assert!(unloaded.load(Ordering::SeqCst));
+        assert_eq!(
+            vec![(
+                "resnet".to_string(),
+                "UNAVAILABLE".to_string(),
+                "".to_string()
+            )],
+            states(&repository)
+        );
+        assert_eq!(
+            Some(tonic::Code::NotFound),
+            repository.unload("bert").err().map(|status| status.code())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_load() {
+        let repository = repository(Arc::new(AtomicBool::new(false)));
+
+        assert!(repository
+            .load("bert", Some(model_config("bert", "missing")))
+            .await
+            .is_err());
+        assert_eq!(
+            vec![(
+                "bert".to_string(),
+                "UNAVAILABLE".to_string(),
+                "model failed to load: no such file".to_string()
+            )],
+            states(&repository)
+        );
+
+        repository
+            .load("resnet", Some(model_config("resnet", "model.pt")))
+            .await
+            .unwrap();
+
+        // A failed reload keeps the previous model serving.
+        assert!(repository
+            .load("resnet", Some(model_config("resnet", "missing")))
+            .await
+            .is_err());
+        assert!(repository.model("resnet").is_ok());
+        assert_eq!(1, repository.index(true).len());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_load() {
+        let repository = repository(Arc::new(AtomicBool::new(false)));
+        let load = repository.load("resnet", Some(model_config("resnet", "slow")));
+
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(20), load)
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            vec![(
+                "resnet".to_string(),
+                "UNAVAILABLE".to_string(),
+                "".to_string()
+            )],
+            states(&repository)
+        );
+
+        repository.load("resnet", None).await.unwrap();
+        repository.unload("resnet").unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_versions() {
+        let repository = repository(Arc::new(AtomicBool::new(false)));
+        let version = |model: Arc<Inference>| model.version().to_string();
+
+        for version in ["9", "10", "2"] {
+            repository.add(model_config("resnet", "model.pt"), version);
+        }
+
+        repository.load_version("resnet", "9").await.unwrap();
+        repository.load_version("resnet", "2").await.unwrap();
+
+        assert_eq!("9", version(repository.model("resnet").unwrap()));
+
+        repository.load("resnet", None).await.unwrap();
+
+        assert_eq!("10", version(repository.model("resnet").unwrap()));
+        assert_eq!(
+            vec!["2", "9", "10"],
+            repository
+                .index(true)
+                .into_iter()
+                .map(|model| model.version)
+                .collect::<Vec<_>>()
+        );
+
+        repository.remove("resnet", "10");
+
+        assert_eq!("9", version(repository.model("resnet").unwrap()));
+        assert_eq!(
+            Some(tonic::Code::NotFound),
+            repository
+                .load_version("resnet", "10")
+                .await
+                .err()
+                .map(|status| status.code())
+        );
+
+        // An explicit config replaces the versions once it loads.
+        repository
+            .load("resnet", Some(model_config("resnet", "model.pt")))
+            .await
+            .unwrap();
+
+        assert_eq!("", version(repository.model("resnet").unwrap()));
+        assert_eq!(1, repository.index(false).len());
+    }
+
+    #[tokio::test]
+    async fn test_load_config_replaces_versions() {
+        let repository = repository(Arc::new(AtomicBool::new(false)));
+        let code = |result: Result<(), Status>| result.err().map(|status| status.code());
+        let stable = HashMap::from([("stable".to_string(), "1".to_string())]);
+
+        for version in ["1", "2", "3"] {
+            repository.add(model_config("resnet", "model.pt"), version);
+        }
+
+        repository.load("resnet", None).await.unwrap();
+        repository.set_labels("resnet", &stable).unwrap();
+
+        // Versions keep serving until the config loads.
+        assert!(repository
+            .load("resnet", Some(model_config("resnet", "missing")))
+            .await
+            .is_err());
+        assert_eq!(3, repository.index(true).len());
+        assert!(repository.model_version("resnet", "stable").is_ok());
+
+        repository
+            .load("resnet", Some(model_config("resnet", "model.pt")))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            vec![""],
+            repository
+                .index(false)
+                .into_iter()
+                .map(|model| model.version)
+                .collect::<Vec<_>>()
+        );
+        assert!(repository.labels("resnet").is_empty());
+        assert_eq!(
+            Some(tonic::Code::NotFound),
+            code(repository.load_version("resnet", "2").await)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_labels() {
+        let repository = repository(Arc::new(AtomicBool::new(false)));
+        let version = |model: Arc<Inference>| model.version().to_string();
+        let code = |result: Result<BTreeMap<String, String>, Status>| {
+            result.err().map(|status| status.code())
+        };
+        let labels = |labels: &[(&str, &str)]| {
+            labels
+                .iter()
+                .map(|(label, version)| (label.to_string(), version.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+
+        for version in ["1", "2", "3"] {
+            repository.add(model_config("resnet", "model.pt"), version);
+        }
+
+        repository.load_version("resnet", "1").await.unwrap();
+        repository.load_version("resnet", "2").await.unwrap();
+        repository
+            .set_labels("resnet", &labels(&[("stable", "1"), ("canary", "2")]))
+            .unwrap();
+
+        assert_eq!(
+            "1",
+            version(repository.model_version("resnet", "stable").unwrap())
+        );
+        assert_eq!(
+            "2",
+            version(repository.model_version("resnet", "2").unwrap())
+        );
+        assert_eq!("2", version(repository.model("resnet").unwrap()));
+        assert_eq!(
+            Some(tonic::Code::Unavailable),
+            repository
+                .model_version("resnet", "3")
+                .err()
+                .map(|status| status.code())
+        );
+        assert_eq!(
+            Some(tonic::Code::NotFound),
+            repository
+                .model_version("resnet", "beta")
+                .err()
+                .map(|status| status.code())
+        );
+
+        // Labels only move to ready versions, and all of them or none.
+        assert_eq!(
+            Some(tonic::Code::FailedPrecondition),
+            code(repository.set_labels("resnet", &labels(&[("stable", "2"), ("canary", "3")])))
+        );
+        assert_eq!(
+            Some(tonic::Code::NotFound),
+            code(repository.set_labels("resnet", &labels(&[("canary", "4")])))
+        );
+        assert_eq!(
+            Some(tonic::Code::InvalidArgument),
+            code(repository.set_labels("resnet", &labels(&[("3", "1")])))
+        );
+        assert_eq!(
+            "1",
+            version(repository.model_version("resnet", "stable").unwrap())
+        );
+
+        repository
+            .set_labels("resnet", &labels(&[(DEFAULT_LABEL, "1"), ("canary", "")]))
+            .unwrap();
+
+        assert_eq!("1", version(repository.model("resnet").unwrap()));
+        assert_eq!(
+            BTreeMap::from([
+                (DEFAULT_LABEL.to_string(), "1".to_string()),
+                ("stable".to_string(), "1".to_string())
+            ]),
+            repository.labels("resnet")
+        );
+
+        repository.remove("resnet", "1");
+
+        assert!(repository.labels("resnet").is_empty());
+        assert_eq!("2", version(repository.model("resnet").unwrap()));
+    }
+}
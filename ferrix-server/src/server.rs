@@ -0,0 +1,391 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use axum::routing::IntoMakeService;
+use ferrix_protos::grpc_inference_service_server::GrpcInferenceServiceServer;
+use hyper::server::accept::from_stream;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio_stream::wrappers::{TcpListenerStream, UnixListenerStream};
+use tokio_stream::Stream;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::server::{Connected, Router};
+use tonic::transport::Server;
+use tracing::{info, warn};
+
+use crate::http;
+use crate::listen::{ListenAddr, Listener};
+use crate::tls::{Tls, TlsConfig};
+use crate::GrpcInferenceServiceImpl;
+
+const TLS_RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Listen addresses for each protocol. A protocol without addresses isn't
+/// served.
+#[derive(Clone, Debug)]
+pub struct ServeConfig {
+    pub grpc: Vec<ListenAddr>,
+    pub http: Vec<ListenAddr>,
+    /// Serves Prometheus metrics on `/metrics`. Requires the repository to have
+    /// [`crate::metrics::Metrics`].
+    pub metrics: Vec<ListenAddr>,
+    /// Applies to every TCP listener. Unix sockets stay plaintext since they
+    /// never leave the host.
+    pub tls: Option<TlsConfig>,
+    /// How long to keep accepting requests once readiness turns false on
+    /// shutdown, so load balancers stop routing to the server first.
+    pub shutdown_delay: Duration,
+    /// How long in-flight requests get to finish on shutdown.
+    pub drain_timeout: Duration,
+}
+
+type ServeFuture = Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>;
+
+/// Resolves once the server stops accepting requests.
+type Stopped = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// A protocol that can be served on connections from any kind of listener.
+trait Protocol {
+    /// Offered during the TLS handshake.
+    const ALPN: &'static [&'static [u8]];
+    const NAME: &'static str;
+
+    /// Serves until `stopped`, then finishes the requests in flight.
+    fn serve<S, IO>(self, incoming: S, stopped: Stopped) -> ServeFuture
+    where
+        S: Stream<Item = io::Result<IO>> + Send + 'static,
+        IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
+        IO::ConnectInfo: Clone + Send + Sync + 'static;
+}
+
+struct Grpc(Router);
+
+impl Protocol for Grpc {
+    const ALPN: &'static [&'static [u8]] = &[b"h2"];
+    const NAME: &'static str = "gRPC";
+
+    fn serve<S, IO>(self, incoming: S, stopped: Stopped) -> ServeFuture
+    where
+        S: Stream<Item = io::Result<IO>> + Send + 'static,
+        IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
+        IO::ConnectInfo: Clone + Send + Sync + 'static,
+    {
+        Box::pin(async move {
+            Ok(self
+                .0
+                .serve_with_incoming_shutdown(incoming, stopped)
+                .await?)
+        })
+    }
+}
+
+struct Http(IntoMakeService<axum::Router>);
+
+struct Prometheus(IntoMakeService<axum::Router>);
+
+impl Protocol for Http {
+    const ALPN: &'static [&'static [u8]] = &[b"http/1.1"];
+    const NAME: &'static str = "HTTP";
+
+    fn serve<S, IO>(self, incoming: S, stopped: Stopped) -> ServeFuture
+    where
+        S: Stream<Item = io::Result<IO>> + Send + 'static,
+        IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
+        IO::ConnectInfo: Clone + Send + Sync + 'static,
+    {
+        Box::pin(async move {
+            Ok(axum::Server::builder(from_stream(incoming))
+                .serve(self.0)
+                .with_graceful_shutdown(stopped)
+                .await?)
+        })
+    }
+}
+
+impl Protocol for Prometheus {
+    const ALPN: &'static [&'static [u8]] = Http::ALPN;
+    const NAME: &'static str = "metrics";
+
+    fn serve<S, IO>(self, incoming: S, stopped: Stopped) -> ServeFuture
+    where
+        S: Stream<Item = io::Result<IO>> + Send + 'static,
+        IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
+        IO::ConnectInfo: Clone + Send + Sync + 'static,
+    {
+        Http(self.0).serve(incoming, stopped)
+    }
+}
+
+/// Serves until SIGTERM or SIGINT, then shuts down gracefully, see
+/// [`serve_with_shutdown`].
+pub async fn serve(config: ServeConfig, service: GrpcInferenceServiceImpl) -> anyhow::Result<()> {
+    serve_with_shutdown(config, service, shutdown_signal()).await
+}
+
+/// Serves until `signal` resolves. Readiness then turns false, and after
+/// [`ServeConfig::shutdown_delay`] the listeners close and the requests in
+/// flight get [`ServeConfig::drain_timeout`] to finish before every model is
+/// unloaded. Fails if requests were still running at the deadline.
+pub async fn serve_with_shutdown(
+    config: ServeConfig,
+    service: GrpcInferenceServiceImpl,
+    signal: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
+    if config.grpc.is_empty() && config.http.is_empty() {
+        bail!("no listen addresses configured");
+    }
+
+    let service = Arc::new(service);
+    let mut servers = JoinSet::new();
+    let (stop, stopped) = watch::channel(false);
+
+    let grpc_tls = tls::<Grpc>(&config)?;
+    for addr in &config.grpc {
+        let grpc = GrpcInferenceServiceServer::from_arc(service.clone());
+        let router = match &service.auth {
+            Some(auth) => {
+                Server::builder().add_service(InterceptedService::new(grpc, auth.interceptor()))
+            }
+            None => Server::builder().add_service(grpc),
+        };
+
+        spawn(
+            &mut servers,
+            addr,
+            grpc_tls.as_ref(),
+            &stopped,
+            Grpc(router),
+        )?;
+    }
+
+    let http_tls = tls::<Http>(&config)?;
+    for addr in &config.http {
+        let app = http::router(service.clone()).into_make_service();
+
+        spawn(&mut servers, addr, http_tls.as_ref(), &stopped, Http(app))?;
+    }
+
+    if !config.metrics.is_empty() {
+        let Some(metrics) = service.repository.metrics() else {
+            bail!("metrics listen addresses configured, but the repository doesn't record metrics");
+        };
+        let metrics_tls = tls::<Prometheus>(&config)?;
+
+        for addr in &config.metrics {
+            let app = metrics.router().into_make_service();
+
+            spawn(
+                &mut servers,
+                addr,
+                metrics_tls.as_ref(),
+                &stopped,
+                Prometheus(app),
+            )?;
+        }
+    }
+
+    tokio::select! {
+        // Servers only return on failure, which takes the others down with it.
+        result = join_all(&mut servers) => return result,
+        _ = signal => {}
+    }
+
+    info!("Shutting down, no longer ready");
+    service.drain();
+    tokio::time::sleep(config.shutdown_delay).await;
+
+    info!(in_flight = service.in_flight(), "Draining requests");
+    let _ = stop.send(true);
+    let drained = tokio::time::timeout(config.drain_timeout, join_all(&mut servers)).await;
+
+    // Cancels whatever is left, releasing the models it held.
+    servers.shutdown().await;
+    service.repository.unload_all();
+
+    match drained {
+        Ok(result) => result,
+        Err(_) => bail!(
+            "{} request(s) still in flight after draining for {:?}",
+            service.in_flight(),
+            config.drain_timeout
+        ),
+    }
+}
+
+async fn join_all(servers: &mut JoinSet<anyhow::Result<()>>) -> anyhow::Result<()> {
+    while let Some(result) = servers.join_next().await {
+        result??;
+    }
+
+    Ok(())
+}
+
+/// Resolves on SIGTERM, sent by Kubernetes and most supervisors, or SIGINT.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+                return;
+            }
+            Err(error) => warn!("Failed to listen for SIGTERM: {}", error),
+        }
+    }
+
+    if let Err(error) = tokio::signal::ctrl_c().await {
+        warn!(
+            "Failed to listen for SIGINT, serving until killed: {}",
+            error
+        );
+        std::future::pending::<()>().await;
+    }
+}
+
+fn stopped(mut stopped: watch::Receiver<bool>) -> Stopped {
+    Box::pin(async move {
+        while !*stopped.borrow_and_update() {
+            // Serving without a sender is serving forever.
+            if stopped.changed().await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        }
+    })
+}
+
+fn tls<P: Protocol>(config: &ServeConfig) -> anyhow::Result<Option<Tls>> {
+    let Some(tls_config) = &config.tls else {
+        return Ok(None);
+    };
+    let tls = Tls::new(tls_config, P::NAME, P::ALPN)
+        .with_context(|| format!("failed to load TLS config for {}", P::NAME))?;
+
+    tls.watch(tls_config, TLS_RELOAD_INTERVAL);
+
+    Ok(Some(tls))
+}
+
+fn spawn<P: Protocol>(
+    servers: &mut JoinSet<anyhow::Result<()>>,
+    addr: &ListenAddr,
+    tls: Option<&Tls>,
+    stop: &watch::Receiver<bool>,
+    protocol: P,
+) -> anyhow::Result<()> {
+    let listener = addr
+        .bind()
+        .with_context(|| format!("failed to listen on {}", addr))?;
+
+    match (listener, tls) {
+        (Listener::Tcp(listener), Some(tls)) => {
+            info!(protocol = P::NAME, %addr, tls = true, "Ferrix listening");
+            servers.spawn(protocol.serve(
+                tls.accept(TcpListenerStream::new(listener)),
+                stopped(stop.clone()),
+            ));
+        }
+        (Listener::Tcp(listener), None) => {
+            info!(protocol = P::NAME, %addr, tls = false, "Ferrix listening");
+            servers.spawn(protocol.serve(TcpListenerStream::new(listener), stopped(stop.clone())));
+        }
+        (Listener::Unix(listener), _) => {
+            info!(protocol = P::NAME, %addr, tls = false, "Ferrix listening");
+            servers.spawn(protocol.serve(UnixListenerStream::new(listener), stopped(stop.clone())));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::UnixStream;
+
+    use super::*;
+    use crate::repository::tests::{model_config, repository};
+
+    /// The status line of a plain HTTP/1.1 request over a Unix socket.
+    async fn status(socket: &Path, uri: &str) -> io::Result<String> {
+        let mut stream = UnixStream::connect(socket).await?;
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: ferrix\r\nConnection: close\r\n\r\n",
+            uri
+        );
+        let mut response = String::new();
+
+        stream.write_all(request.as_bytes()).await?;
+        stream.read_to_string(&mut response).await?;
+
+        Ok(response.lines().next().unwrap_or_default().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown() {
+        let socket =
+            std::env::temp_dir().join(format!("ferrix-shutdown-{}.sock", std::process::id()));
+        let unloaded = Arc::new(AtomicBool::new(false));
+        let repository = repository(unloaded.clone());
+
+        repository
+            .load("resnet", Some(model_config("resnet", "model.pt")))
+            .await
+            .unwrap();
+
+        let config = ServeConfig {
+            grpc: vec![],
+            http: vec![format!("unix:{}", socket.display()).parse().unwrap()],
+            metrics: vec![],
+            tls: None,
+            shutdown_delay: Duration::from_millis(300),
+            drain_timeout: Duration::from_secs(5),
+        };
+        let service = GrpcInferenceServiceImpl::with_repository(Arc::new(repository));
+        let (shutdown, signal) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve_with_shutdown(config, service, async {
+            let _ = signal.await;
+        }));
+
+        let mut ready = status(&socket, "/v2/health/ready").await;
+
+        for _ in 0..50 {
+            if ready.is_ok() {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            ready = status(&socket, "/v2/health/ready").await;
+        }
+
+        assert_eq!("HTTP/1.1 200 OK", ready.unwrap());
+
+        shutdown.send(()).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Still answering during the shutdown delay, but no longer ready.
+        assert_eq!(
+            "HTTP/1.1 503 Service Unavailable",
+            status(&socket, "/v2/health/ready").await.unwrap()
+        );
+
+        server.await.unwrap().unwrap();
+
+        assert!(unloaded.load(Ordering::SeqCst));
+        assert!(status(&socket, "/v2/health/live").await.is_err());
+
+        let _ = std::fs::remove_file(&socket);
+    }
+}
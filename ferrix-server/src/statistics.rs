@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ferrix_protos::{InferBatchStatistics, InferStatistics, ModelStatistics, StatisticDuration};
+
+use crate::metrics::{MODEL, POSTPROCESS, PREPROCESS};
+
+/// Cumulative inference statistics for a model, as reported by the KServe
+/// statistics extension. Unlike [`crate::metrics::Metrics`] they are always
+/// kept, since they only cost a few counters.
+#[derive(Debug, Default)]
+pub struct Statistics {
+    recorded: Mutex<Recorded>,
+}
+
+#[derive(Debug, Default)]
+struct Recorded {
+    last_inference: u64,
+    inference_count: u64,
+    execution_count: u64,
+    success: Cumulative,
+    fail: Cumulative,
+    queue: Cumulative,
+    stages: Stages,
+    /// Keyed by batch size rounded up to a power of two. Clients choose the
+    /// batch size, so exact sizes would let them grow this without bound.
+    batches: BTreeMap<u64, Stages>,
+}
+
+/// The compute statistics, keyed by the stage they were measured in.
+#[derive(Clone, Copy, Debug, Default)]
+struct Stages {
+    compute_input: Cumulative,
+    compute_infer: Cumulative,
+    compute_output: Cumulative,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Cumulative {
+    count: u64,
+    ns: u64,
+}
+
+/// One finished request, successful or not.
+pub(crate) struct Execution<'a> {
+    pub(crate) succeeded: bool,
+    pub(crate) batch_size: u64,
+    /// From receiving the request until the response was ready or it failed.
+    pub(crate) total: Duration,
+    pub(crate) queue: Duration,
+    pub(crate) stages: &'a [(&'static str, Duration)],
+}
+
+impl Statistics {
+    /// Failed requests only count towards `fail`, like in Triton, so the
+    /// compute statistics describe work that produced a response.
+    pub(crate) fn record(&self, execution: Execution) {
+        let mut guard = self.recorded.lock().unwrap();
+        let recorded = &mut *guard;
+
+        recorded.last_inference = now_millis();
+
+        if !execution.succeeded {
+            recorded.fail.add(execution.total);
+            return;
+        }
+
+        recorded.success.add(execution.total);
+        recorded.queue.add(execution.queue);
+        recorded.inference_count = recorded
+            .inference_count
+            .saturating_add(execution.batch_size);
+        recorded.execution_count += 1;
+
+        let batch = recorded
+            .batches
+            .entry(batch_bucket(execution.batch_size))
+            .or_default();
+
+        for (stage, took) in execution.stages {
+            recorded.stages.add(stage, *took);
+            batch.add(stage, *took);
+        }
+    }
+
+    pub fn snapshot(&self, name: &str, version: &str) -> ModelStatistics {
+        let recorded = self.recorded.lock().unwrap();
+
+        ModelStatistics {
+            name: name.to_string(),
+            version: version.to_string(),
+            last_inference: recorded.last_inference,
+            inference_count: recorded.inference_count,
+            execution_count: recorded.execution_count,
+            inference_stats: Some(InferStatistics {
+                success: recorded.success.to_proto(),
+                fail: recorded.fail.to_proto(),
+                queue: recorded.queue.to_proto(),
+                compute_input: recorded.stages.compute_input.to_proto(),
+                compute_infer: recorded.stages.compute_infer.to_proto(),
+                compute_output: recorded.stages.compute_output.to_proto(),
+            }),
+            batch_stats: recorded
+                .batches
+                .iter()
+                .map(|(batch_size, stages)| InferBatchStatistics {
+                    batch_size: *batch_size,
+                    compute_input: stages.compute_input.to_proto(),
+                    compute_infer: stages.compute_infer.to_proto(),
+                    compute_output: stages.compute_output.to_proto(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Stages {
+    fn add(&mut self, stage: &str, took: Duration) {
+        match stage {
+            PREPROCESS => self.compute_input.add(took),
+            MODEL => self.compute_infer.add(took),
+            POSTPROCESS => self.compute_output.add(took),
+            _ => {}
+        }
+    }
+}
+
+impl Cumulative {
+    fn add(&mut self, took: Duration) {
+        self.count += 1;
+        self.ns += took.as_nanos() as u64;
+    }
+
+    fn to_proto(self) -> Option<StatisticDuration> {
+        Some(StatisticDuration {
+            count: self.count,
+            ns: self.ns,
+        })
+    }
+}
+
+/// The smallest power of two at least `batch_size`, so there are at most 64
+/// buckets.
+fn batch_bucket(batch_size: u64) -> u64 {
+    batch_size
+        .checked_next_power_of_two()
+        .unwrap_or(1 << (u64::BITS - 1))
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn duration(count: u64, ns: u64) -> Option<StatisticDuration> {
+        Some(StatisticDuration { count, ns })
+    }
+
+    #[test]
+    fn test_record() {
+        let statistics = Statistics::default();
+        let millis = Duration::from_millis;
+
+        statistics.record(Execution {
+            succeeded: true,
+            batch_size: 4,
+            total: millis(10),
+            queue: millis(1),
+            stages: &[(PREPROCESS, millis(2)), (MODEL, millis(5))],
+        });
+        statistics.record(Execution {
+            succeeded: true,
+            batch_size: 1,
+            total: millis(6),
+            queue: millis(1),
+            stages: &[(MODEL, millis(3))],
+        });
+        statistics.record(Execution {
+            succeeded: false,
+            batch_size: 8,
+            total: millis(7),
+            queue: millis(1),
+            stages: &[(MODEL, millis(6))],
+        });
+
+        let snapshot = statistics.snapshot("resnet", "1");
+        let stats = snapshot.inference_stats.unwrap();
+
+        assert_eq!("resnet", snapshot.name);
+        assert!(snapshot.last_inference > 0);
+        assert_eq!(5, snapshot.inference_count);
+        assert_eq!(2, snapshot.execution_count);
+        assert_eq!(duration(2, 16_000_000), stats.success);
+        assert_eq!(duration(1, 7_000_000), stats.fail);
+        assert_eq!(duration(2, 2_000_000), stats.queue);
+        assert_eq!(duration(1, 2_000_000), stats.compute_input);
+        assert_eq!(duration(2, 8_000_000), stats.compute_infer);
+        assert_eq!(duration(0, 0), stats.compute_output);
+        assert_eq!(
+            vec![1, 4],
+            snapshot
+                .batch_stats
+                .iter()
+                .map(|batch| batch.batch_size)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            duration(1, 5_000_000),
+            snapshot.batch_stats[1].compute_infer
+        );
+    }
+
+    #[test]
+    fn test_batch_sizes_are_bucketed() {
+        let statistics = Statistics::default();
+
+        for batch_size in [0, 1, 3, 4, 5, 1000, u64::MAX] {
+            statistics.record(Execution {
+                succeeded: true,
+                batch_size,
+                total: Duration::from_millis(1),
+                queue: Duration::ZERO,
+                stages: &[(MODEL, Duration::from_millis(1))],
+            });
+        }
+
+        let snapshot = statistics.snapshot("resnet", "1");
+
+        assert_eq!(
+            vec![(1, 2), (4, 2), (8, 1), (1024, 1), (1 << 63, 1)],
+            snapshot
+                .batch_stats
+                .iter()
+                .map(|batch| (
+                    batch.batch_size,
+                    batch.compute_infer.as_ref().unwrap().count
+                ))
+                .collect::<Vec<_>>()
+        );
+    }
+}
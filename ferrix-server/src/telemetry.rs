@@ -0,0 +1,147 @@
+use axum::http::HeaderMap;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::Context;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use tonic::metadata::{KeyRef, MetadataMap};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Continues the caller's trace on `span` if the gRPC metadata carries a W3C
+/// `traceparent`. Otherwise `span` keeps its parent, so a call made from
+/// within another traced request, like the HTTP routes, stays nested in it.
+pub(crate) fn continue_from_metadata(span: &Span, metadata: &MetadataMap) {
+    continue_trace(
+        span,
+        TraceContextPropagator::new().extract(&Metadata(metadata)),
+    );
+}
+
+/// As [`continue_from_metadata`], for HTTP headers.
+pub(crate) fn continue_from_headers(span: &Span, headers: &HeaderMap) {
+    continue_trace(
+        span,
+        TraceContextPropagator::new().extract(&Headers(headers)),
+    );
+}
+
+fn continue_trace(span: &Span, context: Context) {
+    if context.span().span_context().is_valid() {
+        span.set_parent(context);
+    }
+}
+
+struct Metadata<'a>(&'a MetadataMap);
+
+impl Extractor for Metadata<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0
+            .keys()
+            .filter_map(|key| match key {
+                KeyRef::Ascii(key) => Some(key.as_str()),
+                KeyRef::Binary(_) => None,
+            })
+            .collect()
+    }
+}
+
+struct Headers<'a>(&'a HeaderMap);
+
+impl Extractor for Headers<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::{SpanId, TraceId, TracerProvider as _};
+    use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use tracing::info_span;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT_ID: &str = "00f067aa0ba902b7";
+
+    /// Runs `f` with spans exported to memory and returns them.
+    fn exported(f: impl FnOnce()) -> Vec<opentelemetry_sdk::export::trace::SpanData> {
+        let exporter = InMemorySpanExporter::default();
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        tracing::subscriber::with_default(subscriber, f);
+        provider.force_flush();
+
+        exporter.get_finished_spans().unwrap()
+    }
+
+    #[test]
+    fn test_continues_trace_from_metadata() {
+        let mut metadata = MetadataMap::new();
+
+        metadata.insert(
+            "traceparent",
+            format!("00-{}-{}-01", TRACE_ID, PARENT_ID).parse().unwrap(),
+        );
+
+        let spans = exported(|| {
+            let span = info_span!("ModelInfer");
+
+            continue_from_metadata(&span, &metadata);
+            span.in_scope(|| drop(info_span!("model").entered()));
+        });
+        let server = spans.iter().find(|span| span.name == "ModelInfer").unwrap();
+        let model = spans.iter().find(|span| span.name == "model").unwrap();
+
+        assert_eq!(
+            TraceId::from_hex(TRACE_ID).unwrap(),
+            server.span_context.trace_id()
+        );
+        assert_eq!(SpanId::from_hex(PARENT_ID).unwrap(), server.parent_span_id);
+        assert_eq!(
+            server.span_context.trace_id(),
+            model.span_context.trace_id()
+        );
+        assert_eq!(server.span_context.span_id(), model.parent_span_id);
+    }
+
+    #[test]
+    fn test_keeps_parent_without_traceparent() {
+        let mut headers = HeaderMap::new();
+
+        headers.insert("traceparent", "not a traceparent".parse().unwrap());
+
+        let spans = exported(|| {
+            let outer = info_span!("GET /v2/models/:model").entered();
+            let span = info_span!("ModelMetadata");
+
+            continue_from_headers(&span, &headers);
+            drop(span.entered());
+            drop(outer);
+        });
+        let outer = spans
+            .iter()
+            .find(|span| span.name.starts_with("GET"))
+            .unwrap();
+        let inner = spans
+            .iter()
+            .find(|span| span.name == "ModelMetadata")
+            .unwrap();
+
+        assert_eq!(outer.span_context.span_id(), inner.parent_span_id);
+    }
+}
@@ -0,0 +1,349 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use rustls::server::AllowAnyAuthenticatedClient;
+use rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig, SignatureScheme};
+use rustls_pemfile::Item;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tracing::{info, warn};
+use webpki::SignatureAlgorithm;
+
+use crate::watch::watch_file;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// PEM files for the server identity. With a client CA, every connection has
+/// to present a certificate signed by it.
+#[derive(Clone, Debug)]
+pub struct TlsConfig {
+    pub certificate: PathBuf,
+    pub key: PathBuf,
+    pub client_ca: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    fn paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.certificate.clone(), self.key.clone()];
+
+        paths.extend(self.client_ca.clone());
+        paths
+    }
+}
+
+/// Rustls configs for one set of listeners, rebuilt when the PEM files change.
+/// Connections already established keep the certificate they negotiated.
+#[derive(Clone)]
+pub struct Tls {
+    name: &'static str,
+    alpn: Vec<Vec<u8>>,
+    current: Arc<RwLock<Arc<ServerConfig>>>,
+}
+
+impl Tls {
+    /// `name` identifies the listeners in reload messages.
+    pub fn new(config: &TlsConfig, name: &'static str, alpn: &[&[u8]]) -> anyhow::Result<Self> {
+        let alpn: Vec<Vec<u8>> = alpn.iter().map(|protocol| protocol.to_vec()).collect();
+        let current = server_config(config, &alpn)?;
+
+        Ok(Tls {
+            name,
+            alpn,
+            current: Arc::new(RwLock::new(Arc::new(current))),
+        })
+    }
+
+    /// Reloads on change of any of the files. A config that fails to load is
+    /// reported and the previous one kept, so renewing the certificate and key
+    /// one file at a time doesn't interrupt the listener.
+    pub fn watch(&self, config: &TlsConfig, interval: Duration) -> Vec<JoinHandle<()>> {
+        config
+            .paths()
+            .into_iter()
+            .map(|path| {
+                let tls = self.clone();
+                let config = config.clone();
+
+                watch_file(path, interval, move |path| {
+                    tls.reload(&config, path);
+                    true
+                })
+            })
+            .collect()
+    }
+
+    fn reload(&self, config: &TlsConfig, changed: &Path) {
+        match server_config(config, &self.alpn) {
+            Ok(server_config) => {
+                *self.current.write().unwrap() = Arc::new(server_config);
+                info!(
+                    listeners = self.name,
+                    changed = %changed.display(),
+                    "Reloaded TLS config"
+                );
+            }
+            Err(error) => warn!(
+                listeners = self.name,
+                changed = %changed.display(),
+                "Reloading TLS config failed, keeping previous version: {:#}",
+                error
+            ),
+        }
+    }
+
+    /// Performs the handshake for each accepted connection on its own task, so
+    /// a slow client can't hold up the others. Failed handshakes are logged
+    /// and dropped rather than ending the stream.
+    pub fn accept<S, IO>(&self, incoming: S) -> ReceiverStream<io::Result<TlsStream<IO>>>
+    where
+        S: Stream<Item = io::Result<IO>> + Send + Unpin + 'static,
+        IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(64);
+        let current = self.current.clone();
+
+        tokio::spawn(async move {
+            let mut incoming = incoming;
+
+            while let Some(stream) = incoming.next().await {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(error) => {
+                        warn!("Failed to accept connection: {}", error);
+                        continue;
+                    }
+                };
+                let acceptor = TlsAcceptor::from(current.read().unwrap().clone());
+                let sender = sender.clone();
+
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = sender.send(Ok(stream)).await;
+                        }
+                        Ok(Err(error)) => warn!("TLS handshake failed: {}", error),
+                        Err(_) => warn!("TLS handshake timed out"),
+                    }
+                });
+            }
+        });
+
+        ReceiverStream::new(receiver)
+    }
+}
+
+fn server_config(config: &TlsConfig, alpn: &[Vec<u8>]) -> anyhow::Result<ServerConfig> {
+    let chain = certificates(&config.certificate)?;
+    let key = private_key(&config.key)?;
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match &config.client_ca {
+        Some(client_ca) => {
+            let mut roots = RootCertStore::empty();
+
+            for certificate in certificates(client_ca)? {
+                roots.add(&certificate).with_context(|| {
+                    format!("invalid CA certificate in {}", client_ca.display())
+                })?;
+            }
+
+            builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+        }
+        None => builder.with_no_client_auth(),
+    };
+    check_key_matches(&chain[0], &key)?;
+
+    let mut server_config = builder
+        .with_single_cert(chain, key)
+        .context("invalid certificate or key")?;
+
+    server_config.alpn_protocols = alpn.to_vec();
+
+    Ok(server_config)
+}
+
+/// Rustls doesn't check that the key belongs to the certificate, and a
+/// mismatched pair, e.g. halfway through replacing both files, would only show
+/// up as failing handshakes. Sign a probe with the key and verify it against
+/// the certificate instead.
+fn check_key_matches(certificate: &Certificate, key: &PrivateKey) -> anyhow::Result<()> {
+    let schemes: [(SignatureScheme, &SignatureAlgorithm); 4] = [
+        (
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            &webpki::ECDSA_P256_SHA256,
+        ),
+        (
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+            &webpki::ECDSA_P384_SHA384,
+        ),
+        (SignatureScheme::ED25519, &webpki::ED25519),
+        (
+            SignatureScheme::RSA_PKCS1_SHA256,
+            &webpki::RSA_PKCS1_2048_8192_SHA256,
+        ),
+    ];
+    let offered: Vec<SignatureScheme> = schemes.iter().map(|(scheme, _)| *scheme).collect();
+    let signer = rustls::sign::any_supported_type(key)
+        .ok()
+        .and_then(|key| key.choose_scheme(&offered))
+        .ok_or_else(|| anyhow!("unsupported private key type"))?;
+    let (_, algorithm) = schemes
+        .iter()
+        .find(|(scheme, _)| *scheme == signer.scheme())
+        .unwrap();
+    let probe = b"ferrix key check";
+    let signature = signer.sign(probe)?;
+
+    webpki::EndEntityCert::try_from(certificate.0.as_slice())
+        .and_then(|certificate| certificate.verify_signature(algorithm, probe, &signature))
+        .map_err(|_| anyhow!("certificate doesn't match the key"))
+}
+
+fn read_pem(path: &Path) -> anyhow::Result<Vec<Item>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+
+    rustls_pemfile::read_all(&mut BufReader::new(file))
+        .with_context(|| format!("failed to read {}", path.display()))
+}
+
+fn certificates(path: &Path) -> anyhow::Result<Vec<Certificate>> {
+    let certificates: Vec<Certificate> = read_pem(path)?
+        .into_iter()
+        .filter_map(|item| match item {
+            Item::X509Certificate(der) => Some(Certificate(der)),
+            _ => None,
+        })
+        .collect();
+
+    match certificates.is_empty() {
+        true => bail!("no certificates in {}", path.display()),
+        false => Ok(certificates),
+    }
+}
+
+fn private_key(path: &Path) -> anyhow::Result<PrivateKey> {
+    read_pem(path)?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(der) | Item::RSAKey(der) | Item::ECKey(der) => Some(PrivateKey(der)),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("no private key in {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use rustls::ClientConfig;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_rustls::TlsConnector;
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    use super::*;
+
+    fn resource(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("resource/tls")
+            .join(name)
+    }
+
+    fn config(client_ca: bool) -> TlsConfig {
+        TlsConfig {
+            certificate: resource("server.pem"),
+            key: resource("server.key"),
+            client_ca: client_ca.then(|| resource("ca.pem")),
+        }
+    }
+
+    /// Whether the server side of a handshake completes.
+    async fn handshake(tls: &Tls, client_identity: bool) -> bool {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut accepted = tls.accept(TcpListenerStream::new(listener));
+        let mut roots = RootCertStore::empty();
+
+        roots
+            .add(&certificates(&resource("ca.pem")).unwrap()[0])
+            .unwrap();
+
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots);
+        let client_config = match client_identity {
+            true => builder
+                .with_client_auth_cert(
+                    certificates(&resource("client.pem")).unwrap(),
+                    private_key(&resource("client.key")).unwrap(),
+                )
+                .unwrap(),
+            false => builder.with_no_client_auth(),
+        };
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let _client = TlsConnector::from(Arc::new(client_config))
+            .connect("localhost".try_into().unwrap(), stream)
+            .await;
+
+        tokio::time::timeout(Duration::from_secs(2), accepted.next())
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_tls() {
+        let tls = Tls::new(&config(false), "gRPC", &[b"h2"]).unwrap();
+
+        assert!(handshake(&tls, false).await);
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls_requires_client_certificate() {
+        let tls = Tls::new(&config(true), "gRPC", &[b"h2"]).unwrap();
+
+        assert!(handshake(&tls, true).await);
+        assert!(!handshake(&tls, false).await);
+    }
+
+    #[tokio::test]
+    async fn test_failed_reload_keeps_previous_config() {
+        let tls = Tls::new(&config(false), "gRPC", &[b"h2"]).unwrap();
+        let mismatched = TlsConfig {
+            key: resource("client.key"),
+            ..config(false)
+        };
+
+        tls.reload(&mismatched, &mismatched.key);
+        assert!(handshake(&tls, false).await);
+
+        tls.reload(&config(true), &resource("ca.pem"));
+        assert!(!handshake(&tls, false).await);
+    }
+
+    #[test]
+    fn test_rejects_mismatched_key() {
+        let mismatched = TlsConfig {
+            key: resource("client.key"),
+            ..config(false)
+        };
+        let error = Tls::new(&mismatched, "gRPC", &[b"h2"]).err().unwrap();
+
+        assert_eq!("certificate doesn't match the key", error.to_string());
+    }
+
+    #[test]
+    fn test_rejects_missing_files() {
+        let missing = TlsConfig {
+            certificate: resource("missing.pem"),
+            ..config(false)
+        };
+
+        assert!(Tls::new(&missing, "gRPC", &[b"h2"]).is_err());
+        assert!(certificates(&resource("server.key")).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use tokio::task::JoinHandle;
+
+/// Polls `path` every `interval` and calls `on_change` on a blocking thread
+/// whenever its modification time changes. Polling rather than filesystem
+/// events keeps editors that save by rename-and-replace working. Stops once
+/// `on_change` returns false.
+pub fn watch_file<F>(path: PathBuf, interval: Duration, on_change: F) -> JoinHandle<()>
+where
+    F: Fn(&Path) -> bool + Send + Sync + 'static,
+{
+    let on_change = Arc::new(on_change);
+
+    tokio::spawn(async move {
+        let mut last_modified = modified(&path);
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            let current = modified(&path);
+
+            if current.is_none() || current == last_modified {
+                continue;
+            }
+
+            last_modified = current;
+
+            let on_change = on_change.clone();
+            let path = path.clone();
+            if let Ok(false) = tokio::task::spawn_blocking(move || on_change(&path)).await {
+                break;
+            }
+        }
+    })
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stops_when_callback_returns_false() {
+        let path = std::env::temp_dir().join(format!("ferrix-{}-watched.py", std::process::id()));
+
+        std::fs::write(&path, "").unwrap();
+
+        let watcher = watch_file(path.clone(), Duration::from_millis(10), |_| false);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(UNIX_EPOCH)
+            .unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), watcher)
+            .await
+            .unwrap()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}